@@ -1,11 +1,12 @@
-import {
-  Amount,
-  borrow,
-  deriveAssociatedTokenAccount,
-  TxnResponse,
-} from "@honey-finance/sdk";
 import { Keypair, PublicKey } from "@solana/web3.js";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
 import { loadHoneyProgram } from "../helpers";
+import {
+  amountTokens,
+  findObligations,
+  loadOrCreateLoanAccount,
+  reserveRefreshAccounts,
+} from "../helpers/obligation";
 import { initWrappers } from "./initWrappers";
 
 export async function borrowTokens(
@@ -16,23 +17,68 @@ export async function borrowTokens(
   env: string = "devnet"
 ):Promise<boolean> {
   const program = await loadHoneyProgram(wallet, env);
+  const { reserves } = await initWrappers(wallet, program, marketPk, env);
+  const reserve = reserves.find((reserve) => reserve.data.tokenMint.equals(borrowTokenMint));
+  const [obligation] = await findObligations(program, marketPk, wallet.publicKey);
+  if (!reserve || !obligation) {
+    console.log("Borrowing needs a reserve for the token and an obligation with collateral");
+    return false;
+  }
 
-  const { user, reserves } = await initWrappers(
-    wallet,
+  const market = await program.account.market.fetch(marketPk);
+  const reserveData = await program.account.reserve.fetch(reserve.reserve);
+  const loanAccount = await loadOrCreateLoanAccount(
     program,
+    wallet,
     marketPk,
-    env
+    obligation,
+    reserve.reserve
   );
-  await user.refresh();
-
-  // const txid = await user.borrow(reserves[0], associatedTokenAccount, Amount.tokens(amount))
-  const txid = await borrow(
-    user,
-    amount,
-    new PublicKey(borrowTokenMint),
-    reserves
+  const receiverAccount = await Token.getAssociatedTokenAddress(
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    TOKEN_PROGRAM_ID,
+    borrowTokenMint,
+    wallet.publicKey
   );
-  console.log(`Borrow complete: ${txid}`);
+  const preInstructions = [];
+  if (!(await program.provider.connection.getAccountInfo(receiverAccount))) {
+    preInstructions.push(
+      Token.createAssociatedTokenAccountInstruction(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        borrowTokenMint,
+        receiverAccount,
+        wallet.publicKey,
+        wallet.publicKey
+      )
+    );
+  }
 
-  return txid[0] == TxnResponse.Success;
+  try {
+    const txid = await program.methods
+      .borrow(amountTokens(Math.floor(amount)))
+      .accounts({
+        market: marketPk,
+        marketAuthority: market.marketAuthority,
+        obligation,
+        reserve: reserve.reserve,
+        vault: reserveData.vault,
+        loanNoteMint: reserveData.loanNoteMint,
+        borrower: wallet.publicKey,
+        loanAccount,
+        receiverAccount,
+        tokenMint: borrowTokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        nftSwitchboardPriceAggregator: market.nftSwitchboardPriceAggregator,
+      })
+      .remainingAccounts(await reserveRefreshAccounts(program, reserve.reserve))
+      .preInstructions(preInstructions)
+      .signers([wallet])
+      .rpc();
+    console.log(`Borrow complete: ${txid}`);
+    return true;
+  } catch (err) {
+    console.log("Error borrowing", err);
+    return false;
+  }
 }
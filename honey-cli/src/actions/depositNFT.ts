@@ -1,12 +1,13 @@
-import {
-  TxnResponse,
-} from "@honey-finance/sdk";
-
-import { Keypair, PublicKey } from "@solana/web3.js";
+import { Keypair, PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID } from "@solana/spl-token";
 
 import { mintNFT } from "./mintNFT";
-import { initWrappers } from "./initWrappers";
 import { loadHoneyProgram } from "../helpers";
+import {
+  findCollateralAddress,
+  findMetadataAddress,
+  loadOrCreateObligation,
+} from "../helpers/obligation";
 
 export async function depositNFT(
   wallet: Keypair,
@@ -17,34 +18,45 @@ export async function depositNFT(
   env: string = "devnet"
 ):Promise<boolean> {
   const program = await loadHoneyProgram(wallet, env);
-  const { user } = await initWrappers(
-    wallet,
-    program,
-    marketPk,
-    env
-  );
-  if (tokenAccount && tokenMint) {
-    const txid = await user.depositNFT(tokenAccount, tokenMint, verifiedCreator);
-
-    if(txid[0] == TxnResponse.Success)
-      console.log(
-        `Token mint ${tokenMint.toString()} was deposited in account ${tokenAccount.toString()}`
-      );
-    console.log(`Txn ${txid}`);
-    return txid[0] == TxnResponse.Success;
-  } else {
+  if (!tokenAccount || !tokenMint) {
     // if tokenAccount and tokenMint aren't supplied, it will use the base user wallet
     const { userAssosciatedAccount, nftMint } = await mintNFT(wallet, program.provider);
-    const txid = await user.depositNFT(
-      userAssosciatedAccount.address,
-      nftMint.publicKey,
-      wallet.publicKey
-    );
+    tokenAccount = userAssosciatedAccount.address;
+    tokenMint = nftMint.publicKey;
+    verifiedCreator = wallet.publicKey;
+  }
+
+  const obligation = await loadOrCreateObligation(program, wallet, marketPk);
+  const { marketAuthority } = await program.account.market.fetch(marketPk);
+  const [metadata, metadataBump] = await findMetadataAddress(tokenMint);
+
+  try {
+    const txid = await program.methods
+      .depositNft(metadataBump)
+      .accounts({
+        market: marketPk,
+        marketAuthority,
+        obligation,
+        owner: wallet.publicKey,
+        depositSource: tokenAccount,
+        depositNftMint: tokenMint,
+        nftCollectionCreator: verifiedCreator,
+        metadata,
+        collateralAccount: await findCollateralAddress(marketAuthority, tokenMint),
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .signers([wallet])
+      .rpc();
     console.log(
-      `Token mint ${nftMint.publicKey.toString()} was deposited from account ${userAssosciatedAccount.address.toString()}`
+      `Token mint ${tokenMint.toString()} was deposited from account ${tokenAccount.toString()}`
     );
     console.log(`Txn ${txid}`);
-    return txid[0] == TxnResponse.Success;
+    return true;
+  } catch (err) {
+    console.log("Error depositing nft", err);
+    return false;
   }
 }
-
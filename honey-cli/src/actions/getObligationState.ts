@@ -1,9 +1,10 @@
-import { ObligationAccount } from "@honey-finance/sdk";
+import { ObligationAccount, parseObligationAccount } from "@honey-finance/sdk";
 import { Keypair, PublicKey } from "@solana/web3.js";
 import { loadHoneyProgram } from "../helpers";
-import { initWrappers } from "./initWrappers";
+import { findObligations } from "../helpers/obligation";
 
-function displayObligation(obligation: ObligationAccount) {
+function displayObligation(address: PublicKey, obligation: ObligationAccount) {
+  console.log(`Obligation: ${address.toString()}`);
   obligation.collateralNftMint.map((collateralMint) =>
     console.log(`Nft mint: ${collateralMint.toString()}`)
   );
@@ -23,18 +24,14 @@ export async function getObligationState(
 ):Promise<ObligationAccount | null> {
   const program = await loadHoneyProgram(wallet, env);
 
-  const { user } = await initWrappers(
-    wallet,
-    program,
-    marketPk,
-    env
-  );
-  const obligation = await user.getObligationData();
-  if (obligation instanceof Error) {
-    console.log(obligation);
+  const [address] = await findObligations(program, marketPk, wallet.publicKey);
+  const data = address && (await program.provider.connection.getAccountInfo(address));
+  if (!data) {
+    console.log(`${wallet.publicKey.toString()} has no obligation in the market`);
     return null;
-  } else {
-    displayObligation(obligation);
-    return obligation;
   }
+
+  const obligation = parseObligationAccount(data.data, program.coder);
+  displayObligation(address, obligation);
+  return obligation;
 }
@@ -1,8 +1,29 @@
-import { repay, TxnResponse } from "@honey-finance/sdk";
-import { Keypair, PublicKey } from "@solana/web3.js";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, NATIVE_MINT, Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
 import { loadHoneyProgram } from "../helpers";
+import {
+  amountTokens,
+  findLoanAccountAddress,
+  findObligations,
+  reserveRefreshAccounts,
+} from "../helpers/obligation";
 import { initWrappers } from "./initWrappers";
 
+// The spl token `SyncNative` instruction, which credits lamports sent to a
+// wrapped SOL account to its token balance
+function syncNativeInstruction(account: PublicKey): TransactionInstruction {
+  return new TransactionInstruction({
+    programId: TOKEN_PROGRAM_ID,
+    keys: [{ pubkey: account, isSigner: false, isWritable: true }],
+    data: Buffer.from([17]),
+  });
+}
+
 export async function repayTokens(
   wallet: Keypair,
   marketPk: PublicKey,
@@ -11,19 +32,77 @@ export async function repayTokens(
   env: string = "devnet"
 ): Promise<boolean> {
   const program = await loadHoneyProgram(wallet, env);
-  const { user, reserves } = await initWrappers(
-    wallet,
+  const { reserves } = await initWrappers(wallet, program, marketPk, env);
+  const reserve = reserves.find((reserve) => reserve.data.tokenMint.equals(borrowTokenMint));
+  const [obligation] = await findObligations(program, marketPk, wallet.publicKey);
+  if (!reserve || !obligation) {
+    console.log("Repaying needs a reserve for the token and an obligation with a loan");
+    return false;
+  }
+
+  const { marketAuthority } = await program.account.market.fetch(marketPk);
+  const reserveData = await program.account.reserve.fetch(reserve.reserve);
+  const [loanAccount] = await findLoanAccountAddress(
     program,
-    marketPk,
-    env
+    reserve.reserve,
+    obligation,
+    wallet.publicKey
   );
-  const txid = await repay(
-    user,
-    amount,
+  const payerAccount = await Token.getAssociatedTokenAddress(
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    TOKEN_PROGRAM_ID,
     borrowTokenMint,
-    reserves
+    wallet.publicKey
   );
-  console.log(`Repay complete: ${txid}`);
 
-  return txid[0] == TxnResponse.Success;
+  // SOL is repaid from the wallet's wrapped SOL account, topped up here
+  const preInstructions = [];
+  if (borrowTokenMint.equals(NATIVE_MINT)) {
+    if (!(await program.provider.connection.getAccountInfo(payerAccount))) {
+      preInstructions.push(
+        Token.createAssociatedTokenAccountInstruction(
+          ASSOCIATED_TOKEN_PROGRAM_ID,
+          TOKEN_PROGRAM_ID,
+          NATIVE_MINT,
+          payerAccount,
+          wallet.publicKey,
+          wallet.publicKey
+        )
+      );
+    }
+    preInstructions.push(
+      SystemProgram.transfer({
+        fromPubkey: wallet.publicKey,
+        toPubkey: payerAccount,
+        lamports: Math.ceil(amount),
+      }),
+      syncNativeInstruction(payerAccount)
+    );
+  }
+
+  try {
+    const txid = await program.methods
+      .repay(amountTokens(Math.ceil(amount)))
+      .accounts({
+        market: marketPk,
+        marketAuthority,
+        obligation,
+        reserve: reserve.reserve,
+        vault: reserveData.vault,
+        loanNoteMint: reserveData.loanNoteMint,
+        loanAccount,
+        payerAccount,
+        payer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(await reserveRefreshAccounts(program, reserve.reserve))
+      .preInstructions(preInstructions)
+      .signers([wallet])
+      .rpc();
+    console.log(`Repay complete: ${txid}`);
+    return true;
+  } catch (err) {
+    console.log("Error repaying", err);
+    return false;
+  }
 }
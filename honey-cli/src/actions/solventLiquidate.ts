@@ -12,6 +12,7 @@ import {
 } from "@solana/spl-token-latest";
 import { findMarketAuthorityAddress } from "../helpers/utils";
 import { loadHoneyProgram } from "../helpers";
+import { findObligations } from "../helpers/obligation";
 
 export async function solventLiquidate(
   provider: anchor.AnchorProvider,
@@ -113,11 +114,12 @@ export async function solventLiquidate(
     )[0];
     console.log('reserve', reserve.reserve.toString());
 
-    const [obligationAddress, obligationBump] = await PublicKey.findProgramAddress(
-      [Buffer.from('obligation'), new PublicKey(marketPkString).toBuffer(), new PublicKey(depositor).toBuffer()],
-      HONEY_PROGRAM_ID,
+    const [obligationAddress] = await findObligations(
+      program,
+      new PublicKey(marketPkString),
+      new PublicKey(depositor),
     );
-    const obligationData = await provider.connection.getAccountInfo(obligationAddress);
+    const obligationData = obligationAddress && await provider.connection.getAccountInfo(obligationAddress);
     if (!obligationData) {
       console.log('Wrong depositor address!');
       return;
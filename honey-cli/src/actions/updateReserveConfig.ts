@@ -1,5 +1,4 @@
-import { UpdateReserveConfigParams } from "@honey-finance/sdk/dist/wrappers/reserve";
-import { Keypair, PublicKey } from "@solana/web3.js";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
 import { loadHoneyProgram } from "../helpers";
 import { reserveConfig } from "../helpers/utils";
 import { initWrappers } from "./initWrappers";
@@ -10,18 +9,41 @@ export async function updateReserveConfig(
     env: string = "devnet"
 ) {
     const program = await loadHoneyProgram(wallet, env);
+    const market = new PublicKey(marketPkString);
     const { reserves } = await initWrappers(
       wallet,
       program,
-      new PublicKey(marketPkString),
+      market,
       env
     );
 
-    const config = {
-        config: reserveConfig,
+    // the market owner or its risk admin signs, checked against the market's roles
+    const [roles] = await PublicKey.findProgramAddress(
+      [Buffer.from("roles"), market.toBuffer()],
+      program.programId
+    );
+    if (!(await program.provider.connection.getAccountInfo(roles))) {
+      await program.methods
+        .initMarketRoles()
+        .accounts({
+          market,
+          roles,
+          owner: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([wallet])
+        .rpc();
+    }
+
+    const txid = await program.methods
+      .updateReserveConfig(reserveConfig)
+      .accounts({
+        market,
+        roles,
         reserve: reserves[0].reserve,
-        market: new PublicKey(marketPkString),
-        owner: wallet
-    } as UpdateReserveConfigParams;
-    await reserves[0].updateReserveConfig(config);
-}
\ No newline at end of file
+        owner: wallet.publicKey,
+      })
+      .signers([wallet])
+      .rpc();
+    console.log(`Updated reserve config: ${txid}`);
+}
@@ -1,7 +1,11 @@
-import { TxnResponse } from "@honey-finance/sdk";
 import { Keypair, PublicKey } from "@solana/web3.js";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
 import { loadHoneyProgram } from "../helpers";
-import { initWrappers } from "./initWrappers";
+import {
+  findCollateralAddress,
+  findMetadataAddress,
+  findObligations,
+} from "../helpers/obligation";
 
 export async function withdrawNFT(
   wallet: Keypair,
@@ -12,13 +16,36 @@ export async function withdrawNFT(
   env: string = "devnet"
 ):Promise<boolean> {
   const program = await loadHoneyProgram(wallet, env);
-  const { user } = await initWrappers(
-    wallet,
-    program,
-    marketPk,
-    env
-  );
-  const txid = await user.withdrawNFT(tokenAccount, tokenMint, verifiedCreator);
-  console.log(txid);
-  return txid[0] == TxnResponse.Success;
+  const [obligation] = await findObligations(program, marketPk, wallet.publicKey);
+  if (!obligation) {
+    console.log(`${wallet.publicKey.toString()} has no obligation in the market`);
+    return false;
+  }
+
+  const market = await program.account.market.fetch(marketPk);
+  const [metadata, metadataBump] = await findMetadataAddress(tokenMint);
+
+  try {
+    const txid = await program.methods
+      .withdrawNft(metadataBump)
+      .accounts({
+        market: marketPk,
+        marketAuthority: market.marketAuthority,
+        obligation,
+        owner: wallet.publicKey,
+        depositTo: tokenAccount,
+        nftCollectionCreator: verifiedCreator ?? market.nftCollectionCreator,
+        metadata,
+        depositNftMint: tokenMint,
+        collateralAccount: await findCollateralAddress(market.marketAuthority, tokenMint),
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([wallet])
+      .rpc();
+    console.log(txid);
+    return true;
+  } catch (err) {
+    console.log("Error withdrawing nft", err);
+    return false;
+  }
 }
@@ -0,0 +1,176 @@
+import { METADATA_PROGRAM_ID } from "@honey-finance/sdk";
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import {
+  AccountMeta,
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+} from "@solana/web3.js";
+
+// Obligations are keypair accounts, so they're found by their market and
+// owner: after the 8 byte discriminator come the u32 version and a reserved
+// u32, then the market, then the owner.
+const OBLIGATION_MARKET_OFFSET = 16;
+const OBLIGATION_OWNER_OFFSET = 48;
+
+/**
+ * Find the obligations an owner has in a market
+ * @param program The honey program
+ * @param market The market the obligations are in
+ * @param owner The owner of the obligations
+ */
+export async function findObligations(
+  program: Program,
+  market: PublicKey,
+  owner: PublicKey
+): Promise<PublicKey[]> {
+  const obligations = await program.account.obligation.all([
+    { memcmp: { offset: OBLIGATION_MARKET_OFFSET, bytes: market.toBase58() } },
+    { memcmp: { offset: OBLIGATION_OWNER_OFFSET, bytes: owner.toBase58() } },
+  ]);
+  return obligations.map((obligation) => obligation.publicKey);
+}
+
+/**
+ * Create a new obligation for the wallet in a market
+ * @returns The address of the new obligation
+ */
+export async function createObligation(
+  program: Program,
+  wallet: Keypair,
+  market: PublicKey
+): Promise<PublicKey> {
+  const obligation = Keypair.generate();
+  const { marketAuthority } = await program.account.market.fetch(market);
+
+  await program.methods
+    .initObligation()
+    .accounts({
+      market,
+      marketAuthority,
+      borrower: wallet.publicKey,
+      obligation: obligation.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    })
+    .signers([wallet, obligation])
+    .rpc();
+
+  console.log(`Created obligation ${obligation.publicKey.toString()}`);
+  return obligation.publicKey;
+}
+
+/**
+ * Get the wallet's first obligation in a market, creating one if it has none
+ */
+export async function loadOrCreateObligation(
+  program: Program,
+  wallet: Keypair,
+  market: PublicKey
+): Promise<PublicKey> {
+  const [obligation] = await findObligations(program, market, wallet.publicKey);
+  return obligation ?? createObligation(program, wallet, market);
+}
+
+export async function findLoanAccountAddress(
+  program: Program,
+  reserve: PublicKey,
+  obligation: PublicKey,
+  owner: PublicKey
+): Promise<[PublicKey, number]> {
+  return PublicKey.findProgramAddress(
+    [Buffer.from("loan"), reserve.toBuffer(), obligation.toBuffer(), owner.toBuffer()],
+    program.programId
+  );
+}
+
+/**
+ * Get the obligation's loan account for a reserve, creating it if needed
+ */
+export async function loadOrCreateLoanAccount(
+  program: Program,
+  wallet: Keypair,
+  market: PublicKey,
+  obligation: PublicKey,
+  reserve: PublicKey
+): Promise<PublicKey> {
+  const [loanAccount] = await findLoanAccountAddress(
+    program,
+    reserve,
+    obligation,
+    wallet.publicKey
+  );
+  if (await program.provider.connection.getAccountInfo(loanAccount)) {
+    return loanAccount;
+  }
+
+  const { marketAuthority } = await program.account.market.fetch(market);
+  const { loanNoteMint } = await program.account.reserve.fetch(reserve);
+  await program.methods
+    .initLoanAccount(0)
+    .accounts({
+      market,
+      marketAuthority,
+      obligation,
+      reserve,
+      loanNoteMint,
+      owner: wallet.publicKey,
+      loanAccount,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY,
+    })
+    .signers([wallet])
+    .rpc();
+
+  return loanAccount;
+}
+
+export async function findMetadataAddress(mint: PublicKey): Promise<[PublicKey, number]> {
+  return PublicKey.findProgramAddress(
+    [Buffer.from("metadata"), METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+    METADATA_PROGRAM_ID
+  );
+}
+
+/**
+ * The market authority's token account that escrows an nft deposited as collateral
+ */
+export async function findCollateralAddress(
+  marketAuthority: PublicKey,
+  nftMint: PublicKey
+): Promise<PublicKey> {
+  return Token.getAssociatedTokenAddress(
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    TOKEN_PROGRAM_ID,
+    nftMint,
+    marketAuthority,
+    true
+  );
+}
+
+/**
+ * The trailing accounts that let an instruction refresh a stale reserve: the
+ * price aggregator, fee note vault, protocol fee note vault and deposit note
+ * mint, in that order
+ */
+export async function reserveRefreshAccounts(
+  program: Program,
+  reserve: PublicKey
+): Promise<AccountMeta[]> {
+  const data = await program.account.reserve.fetch(reserve);
+  return [
+    { pubkey: data.switchboardPriceAggregator, isSigner: false, isWritable: false },
+    { pubkey: data.feeNoteVault, isSigner: false, isWritable: true },
+    { pubkey: data.protocolFeeNoteVault, isSigner: false, isWritable: true },
+    { pubkey: data.depositNoteMint, isSigner: false, isWritable: true },
+  ];
+}
+
+export const amountTokens = (value: number | anchor.BN) => ({
+  units: { tokens: {} },
+  value: new anchor.BN(value),
+});
@@ -61,4 +61,13 @@ export const reserveConfig = {
   manageFeeRate: 50,
   manageFeeCollectionThreshold: new anchor.BN(10),
   loanOriginationFee: 250,
-} as ReserveConfig;
+  liquidationProtocolFee: 0,
+  insuranceFundFeeShare: 0,
+  // caps of zero leave deposits, borrows and utilization unlimited
+  depositCap: new anchor.BN(0),
+  borrowCap: new anchor.BN(0),
+  maxObligationBorrow: new anchor.BN(0),
+  maxUtilization: 0,
+  flashLoanFee: 0,
+  _reserved1: [0, 0, 0, 0],
+} as unknown as ReserveConfig;
@@ -0,0 +1,254 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Support for Bubblegum compressed NFTs, which are leaves of a concurrent
+//! Merkle tree rather than SPL mints, and move by proving the leaf against
+//! the tree's root.
+//!
+//! There's no Bubblegum crate pinned here, so its `transfer` instruction is
+//! encoded by hand.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::program::invoke_signed;
+
+use crate::errors::ErrorCode;
+
+pub mod bubblegum_program {
+    anchor_lang::declare_id!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+}
+
+pub mod account_compression_program {
+    anchor_lang::declare_id!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+}
+
+pub mod noop_program {
+    anchor_lang::declare_id!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+}
+
+/// The anchor discriminator of Bubblegum's `transfer` instruction
+const TRANSFER_DISCRIMINATOR: [u8; 8] = [163, 52, 200, 231, 140, 3, 69, 186];
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct CompressedNftCreator {
+    pub address: Pubkey,
+    pub verified: bool,
+    pub share: u8,
+}
+
+/// A compressed nft's leaf, as read from an indexer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct CompressedNft {
+    /// The root of the tree the proof leads to
+    pub root: [u8; 32],
+
+    /// The hash of the nft's metadata
+    pub data_hash: [u8; 32],
+
+    /// The nft's creators, which are hashed into the leaf
+    pub creators: Vec<CompressedNftCreator>,
+
+    /// The leaf's nonce, which its asset id is derived from
+    pub nonce: u64,
+
+    /// The leaf's position in the tree
+    pub index: u32,
+}
+
+impl CompressedNft {
+    /// The asset id Bubblegum gives the nft, which stands in for a mint
+    pub fn asset_id(&self, merkle_tree: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"asset", merkle_tree.as_ref(), &self.nonce.to_le_bytes()],
+            &bubblegum_program::ID,
+        )
+        .0
+    }
+
+    /// The hash of the creators stored in the leaf. Since the leaf only
+    /// verifies with the right hash, the creators can be trusted once the
+    /// nft has moved.
+    pub fn creator_hash(&self) -> [u8; 32] {
+        let creators = self
+            .creators
+            .iter()
+            .map(|c| [c.address.as_ref(), &[c.verified as u8], &[c.share]].concat())
+            .collect::<Vec<_>>();
+
+        keccak::hashv(&creators.iter().map(|c| c.as_slice()).collect::<Vec<_>>()).0
+    }
+
+    /// Verify the nft has the given verified creator
+    pub fn verify_creator(&self, creator: &Pubkey) -> Result<()> {
+        if !self.creators.iter().any(|c| c.verified && c.address == *creator) {
+            return err!(ErrorCode::VerifiedCreatorMismatch);
+        }
+
+        Ok(())
+    }
+
+    fn transfer_instruction_data(&self) -> Vec<u8> {
+        let mut data = TRANSFER_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&self.root);
+        data.extend_from_slice(&self.data_hash);
+        data.extend_from_slice(&self.creator_hash());
+        data.extend_from_slice(&self.nonce.to_le_bytes());
+        data.extend_from_slice(&self.index.to_le_bytes());
+        data
+    }
+}
+
+/// The accounts for Bubblegum to give a compressed nft's leaf a new owner
+pub struct BubblegumTransfer<'a, 'info> {
+    pub tree_authority: &'a AccountInfo<'info>,
+    pub leaf_owner: &'a AccountInfo<'info>,
+    pub leaf_delegate: &'a AccountInfo<'info>,
+    pub new_leaf_owner: &'a AccountInfo<'info>,
+    pub merkle_tree: &'a AccountInfo<'info>,
+    pub log_wrapper: &'a AccountInfo<'info>,
+    pub compression_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub bubblegum_program: &'a AccountInfo<'info>,
+
+    /// The proof nodes for the leaf, from the leaf up
+    pub proof: &'a [AccountInfo<'info>],
+}
+
+impl<'a, 'info> BubblegumTransfer<'a, 'info> {
+    /// Move the leaf, with the leaf owner signing
+    pub fn invoke_signed(&self, nft: &CompressedNft, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        self.verify_accounts()?;
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*self.tree_authority.key, false),
+            AccountMeta::new_readonly(*self.leaf_owner.key, true),
+            AccountMeta::new_readonly(*self.leaf_delegate.key, self.leaf_delegate.is_signer),
+            AccountMeta::new_readonly(*self.new_leaf_owner.key, false),
+            AccountMeta::new(*self.merkle_tree.key, false),
+            AccountMeta::new_readonly(*self.log_wrapper.key, false),
+            AccountMeta::new_readonly(*self.compression_program.key, false),
+            AccountMeta::new_readonly(*self.system_program.key, false),
+        ];
+        accounts.extend(self.proof.iter().map(|node| AccountMeta::new_readonly(*node.key, false)));
+
+        let mut infos = vec![
+            self.tree_authority.clone(),
+            self.leaf_owner.clone(),
+            self.leaf_delegate.clone(),
+            self.new_leaf_owner.clone(),
+            self.merkle_tree.clone(),
+            self.log_wrapper.clone(),
+            self.compression_program.clone(),
+            self.system_program.clone(),
+            self.bubblegum_program.clone(),
+        ];
+        infos.extend(self.proof.iter().cloned());
+
+        invoke_signed(
+            &Instruction {
+                program_id: bubblegum_program::ID,
+                accounts,
+                data: nft.transfer_instruction_data(),
+            },
+            &infos,
+            signer_seeds,
+        )?;
+
+        Ok(())
+    }
+
+    fn verify_accounts(&self) -> Result<()> {
+        let tree_authority =
+            Pubkey::find_program_address(&[self.merkle_tree.key.as_ref()], &bubblegum_program::ID).0;
+
+        if *self.bubblegum_program.key != bubblegum_program::ID
+            || *self.compression_program.key != account_compression_program::ID
+            || *self.log_wrapper.key != noop_program::ID
+            || *self.tree_authority.key != tree_authority
+        {
+            msg!("the bubblegum accounts are not the ones for the tree");
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nft(creators: Vec<CompressedNftCreator>) -> CompressedNft {
+        CompressedNft {
+            root: [1; 32],
+            data_hash: [2; 32],
+            creators,
+            nonce: 7,
+            index: 3,
+        }
+    }
+
+    #[test]
+    fn checks_for_a_verified_creator() {
+        let creator = Pubkey::new_unique();
+        let unverified = nft(vec![CompressedNftCreator { address: creator, verified: false, share: 100 }]);
+        let verified = nft(vec![
+            CompressedNftCreator { address: Pubkey::new_unique(), verified: true, share: 0 },
+            CompressedNftCreator { address: creator, verified: true, share: 100 },
+        ]);
+
+        assert!(unverified.verify_creator(&creator).is_err());
+        assert!(nft(vec![]).verify_creator(&creator).is_err());
+        verified.verify_creator(&creator).unwrap();
+    }
+
+    #[test]
+    fn hashes_creators_like_bubblegum() {
+        let creator = CompressedNftCreator { address: Pubkey::new_unique(), verified: true, share: 100 };
+        let expected = keccak::hashv(&[creator.address.as_ref(), &[1], &[100]]).0;
+
+        assert_eq!(nft(vec![creator.clone()]).creator_hash(), expected);
+        assert_ne!(
+            nft(vec![CompressedNftCreator { verified: false, ..creator }]).creator_hash(),
+            expected
+        );
+    }
+
+    #[test]
+    fn encodes_transfers_of_the_leaf() {
+        let nft = nft(vec![]);
+        let data = nft.transfer_instruction_data();
+
+        assert_eq!(data.len(), 8 + 32 * 3 + 8 + 4);
+        assert_eq!(&data[..8], &TRANSFER_DISCRIMINATOR);
+        assert_eq!(&data[8..40], &nft.root);
+        assert_eq!(&data[72..104], &nft.creator_hash());
+        assert_eq!(&data[104..], &[7, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0]);
+    }
+
+    #[test]
+    fn asset_ids_depend_on_the_tree_and_nonce() {
+        let tree = Pubkey::new_unique();
+        let mut other = nft(vec![]);
+        other.nonce += 1;
+
+        assert_eq!(nft(vec![]).asset_id(&tree), nft(vec![]).asset_id(&tree));
+        assert_ne!(nft(vec![]).asset_id(&tree), other.asset_id(&tree));
+        assert_ne!(nft(vec![]).asset_id(&tree), nft(vec![]).asset_id(&Pubkey::new_unique()));
+    }
+}
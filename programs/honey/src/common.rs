@@ -9,6 +9,19 @@ pub enum AmountUnits {
     Tokens,
     DepositNotes,
     LoanNotes,
+
+    /// As many deposit notes as the reserve has the liquidity to withdraw
+    MaxWithdrawable,
+
+    /// The whole balance of a deposit, or the whole debt of a loan
+    All,
+
+    /// A share of a deposit, loan or the obligation's borrowing capacity,
+    /// in basis points
+    Bps,
+
+    /// As many tokens as the obligation can borrow while staying healthy
+    MaxBorrowable,
 }
 
 /// Represent an amount of some value (like tokens, or notes)
@@ -18,6 +31,24 @@ pub struct Amount {
     pub value: u64,
 }
 
+/// The position an amount is taken from, which the relative units are
+/// resolved against
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum AmountBasis {
+    /// The amount isn't taken from a position, so only absolute units apply
+    NoPosition,
+
+    /// Withdrawing from a deposit of `notes`, of which the reserve has the
+    /// liquidity to pay out `available_notes`
+    Deposit { notes: u64, available_notes: u64 },
+
+    /// Repaying a loan of `notes`
+    Loan { notes: u64 },
+
+    /// Borrowing, where the obligation can borrow at most `max_tokens`
+    Borrow { max_tokens: u64 },
+}
+
 /// Specifies rounding integers up or down
 pub enum Rounding {
     Up,
@@ -26,11 +57,19 @@ pub enum Rounding {
 
 impl Amount {
     /// Get the amount represented in tokens
-    pub fn as_tokens(&self, reserve_info: &CachedReserveInfo, rounding: Rounding) -> u64 {
-        match self.units {
-            AmountUnits::Tokens => self.value,
-            AmountUnits::DepositNotes => reserve_info.deposit_notes_to_tokens(self.value, rounding),
-            AmountUnits::LoanNotes => reserve_info.loan_notes_to_tokens(self.value, rounding),
+    pub fn as_tokens(
+        &self,
+        reserve_info: &CachedReserveInfo,
+        rounding: Rounding,
+        basis: AmountBasis,
+    ) -> Result<u64> {
+        let amount = self.resolve(basis)?;
+
+        match amount.units {
+            AmountUnits::Tokens => Ok(amount.value),
+            AmountUnits::DepositNotes => Ok(reserve_info.deposit_notes_to_tokens(amount.value, rounding)),
+            AmountUnits::LoanNotes => Ok(reserve_info.loan_notes_to_tokens(amount.value, rounding)),
+            _ => err!(ErrorCode::InvalidAmountUnits),
         }
     }
 
@@ -39,11 +78,14 @@ impl Amount {
         &self,
         reserve_info: &CachedReserveInfo,
         rounding: Rounding,
+        basis: AmountBasis,
     ) -> Result<u64> {
-        match self.units {
-            AmountUnits::Tokens => Ok(reserve_info.deposit_notes_from_tokens(self.value, rounding)),
-            AmountUnits::DepositNotes => Ok(self.value),
-            AmountUnits::LoanNotes => err!(ErrorCode::InvalidAmountUnits),
+        let amount = self.resolve(basis)?;
+
+        match amount.units {
+            AmountUnits::Tokens => Ok(reserve_info.deposit_notes_from_tokens(amount.value, rounding)),
+            AmountUnits::DepositNotes => Ok(amount.value),
+            _ => err!(ErrorCode::InvalidAmountUnits),
         }
     }
 
@@ -52,12 +94,61 @@ impl Amount {
         &self,
         reserve_info: &CachedReserveInfo,
         rounding: Rounding,
+        basis: AmountBasis,
     ) -> Result<u64> {
-        match self.units {
-            AmountUnits::Tokens => Ok(reserve_info.loan_notes_from_tokens(self.value, rounding)),
-            AmountUnits::LoanNotes => Ok(self.value),
-            AmountUnits::DepositNotes => err!(ErrorCode::InvalidAmountUnits),
+        let amount = self.resolve(basis)?;
+
+        match amount.units {
+            AmountUnits::Tokens => Ok(reserve_info.loan_notes_from_tokens(amount.value, rounding)),
+            AmountUnits::LoanNotes => Ok(amount.value),
+            _ => err!(ErrorCode::InvalidAmountUnits),
+        }
+    }
+
+    /// Whether the amount is relative to the position it's taken from
+    pub fn is_relative(&self) -> bool {
+        !matches!(
+            self.units,
+            AmountUnits::Tokens | AmountUnits::DepositNotes | AmountUnits::LoanNotes
+        )
+    }
+
+    /// Resolve a relative amount into tokens or notes, given the position
+    /// it's taken from
+    pub fn resolve(&self, basis: AmountBasis) -> Result<Amount> {
+        use AmountBasis::*;
+
+        let amount = match (self.units, basis) {
+            _ if !self.is_relative() => *self,
+
+            (AmountUnits::All, Deposit { notes, .. }) => Amount::from_deposit_notes(notes),
+            (AmountUnits::All, Loan { notes }) => Amount::from_loan_notes(notes),
+
+            (AmountUnits::Bps, Deposit { notes, .. }) => {
+                Amount::from_deposit_notes(self.bps_of(notes)?)
+            }
+            (AmountUnits::Bps, Loan { notes }) => Amount::from_loan_notes(self.bps_of(notes)?),
+            (AmountUnits::Bps, Borrow { max_tokens }) => {
+                Amount::from_tokens(self.bps_of(max_tokens)?)
+            }
+
+            (AmountUnits::MaxWithdrawable, Deposit { notes, available_notes }) => {
+                Amount::from_deposit_notes(std::cmp::min(notes, available_notes))
+            }
+            (AmountUnits::MaxBorrowable, Borrow { max_tokens }) => Amount::from_tokens(max_tokens),
+
+            _ => return err!(ErrorCode::InvalidAmountUnits),
+        };
+
+        Ok(amount)
+    }
+
+    fn bps_of(&self, total: u64) -> Result<u64> {
+        if self.value > 10_000 {
+            return err!(ErrorCode::InvalidAmountUnits);
         }
+
+        Ok((total as u128 * self.value as u128 / 10_000) as u64)
     }
 
     pub fn from_tokens(value: u64) -> Amount {
@@ -81,3 +172,75 @@ impl Amount {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(units: AmountUnits, value: u64) -> Amount {
+        Amount { units, value }
+    }
+
+    #[test]
+    fn withdrawals_resolve_against_the_deposit() {
+        let basis = AmountBasis::Deposit { notes: 1_000, available_notes: 600 };
+
+        assert_eq!(
+            amount(AmountUnits::All, 0).resolve(basis).unwrap(),
+            Amount::from_deposit_notes(1_000)
+        );
+        assert_eq!(
+            amount(AmountUnits::Bps, 2_500).resolve(basis).unwrap(),
+            Amount::from_deposit_notes(250)
+        );
+        assert_eq!(
+            amount(AmountUnits::MaxWithdrawable, 0).resolve(basis).unwrap(),
+            Amount::from_deposit_notes(600)
+        );
+        assert!(amount(AmountUnits::MaxBorrowable, 0).resolve(basis).is_err());
+    }
+
+    #[test]
+    fn repays_resolve_against_the_loan() {
+        let basis = AmountBasis::Loan { notes: 1_001 };
+
+        assert_eq!(
+            amount(AmountUnits::All, 0).resolve(basis).unwrap(),
+            Amount::from_loan_notes(1_001)
+        );
+        assert_eq!(
+            amount(AmountUnits::Bps, 5_000).resolve(basis).unwrap(),
+            Amount::from_loan_notes(500)
+        );
+        assert_eq!(
+            amount(AmountUnits::Bps, 10_000).resolve(basis).unwrap(),
+            Amount::from_loan_notes(1_001)
+        );
+        assert!(amount(AmountUnits::Bps, 10_001).resolve(basis).is_err());
+        assert!(amount(AmountUnits::MaxWithdrawable, 0).resolve(basis).is_err());
+    }
+
+    #[test]
+    fn borrows_resolve_against_the_borrowing_capacity() {
+        let basis = AmountBasis::Borrow { max_tokens: 800 };
+
+        assert_eq!(
+            amount(AmountUnits::MaxBorrowable, 0).resolve(basis).unwrap(),
+            Amount::from_tokens(800)
+        );
+        assert_eq!(
+            amount(AmountUnits::Bps, 5_000).resolve(basis).unwrap(),
+            Amount::from_tokens(400)
+        );
+        assert!(amount(AmountUnits::All, 0).resolve(basis).is_err());
+    }
+
+    #[test]
+    fn absolute_amounts_ignore_the_basis() {
+        let tokens = Amount::from_tokens(42);
+
+        assert_eq!(tokens.resolve(AmountBasis::NoPosition).unwrap(), tokens);
+        assert_eq!(tokens.resolve(AmountBasis::Loan { notes: 0 }).unwrap(), tokens);
+        assert!(amount(AmountUnits::All, 0).resolve(AmountBasis::NoPosition).is_err());
+    }
+}
@@ -123,6 +123,87 @@ pub enum ErrorCode {
 
     #[msg("Obligation only allows for one loan at a time")]
     AnotherLoanOutstanding,
+
+    #[msg("reserve data is stale; call refresh_reserve before this instruction")]
+    StaleReserve,
+
+    #[msg("the reserve has currently halted this kind of operation")]
+    ReserveHalted,
+
+    #[msg("the signer is not authorized to perform this action")]
+    Unauthorized,
+
+    #[msg("liquidations are paused while borrowers get a chance to repay")]
+    LiquidationGracePeriod,
+
+    #[msg("the reserve must be winding down with no deposits or loans outstanding to be closed")]
+    ReserveNotClosable,
+
+    #[msg("the bid has expired")]
+    BidExpired,
+
+    #[msg("the bid has not expired yet")]
+    BidNotExpired,
+
+    #[msg("the bid is restricted to a different obligation or nft")]
+    BidTargetMismatch,
+
+    #[msg("the liquidation payouts don't sum to the amount taken from escrow")]
+    LiquidationSplitMismatch,
+
+    #[msg("the reserve already has an insurance fund")]
+    InsuranceFundExists,
+
+    #[msg("bad debt can only be written off once the obligation has no collateral left")]
+    ObligationHasCollateral,
+
+    #[msg("the deposit would take the reserve over its deposit cap")]
+    DepositCapExceeded,
+
+    #[msg("the loan would take the reserve over its borrow cap")]
+    BorrowCapExceeded,
+
+    #[msg("the loan would take the obligation over the most it may borrow from the reserve")]
+    ObligationBorrowCapExceeded,
+
+    #[msg("the loan would take the reserve over its maximum utilization")]
+    MaxUtilizationExceeded,
+
+    #[msg("the reserve doesn't have the liquidity to pay out the withdrawal")]
+    InsufficientLiquidity,
+
+    #[msg("the withdrawal request has nothing left to fill")]
+    WithdrawalRequestFilled,
+
+    #[msg("a flash loan is already outstanding from the reserve")]
+    FlashLoanActive,
+
+    #[msg("the reserve has no flash loan to repay")]
+    NoFlashLoan,
+
+    #[msg("a flash loan must be repaid later in the same transaction")]
+    FlashLoanNotRepaid,
+
+    #[msg("the nft is not a programmable nft")]
+    NotProgrammableNft,
+
+    #[msg("the authorization rules don't match the nft's rule set")]
+    RuleSetMismatch,
+
+    #[msg("collateral frozen in the owner's wallet can't be transferred with the obligation")]
+    FrozenCollateral,
+
+    #[msg("the nft is not held the way this instruction expects")]
+    NftCustodyMismatch,
+
+    #[msg("the withdrawal request still has notes left to fill")]
+    WithdrawalRequestPending,
+
+    #[msg("the market has no fee collector assigned")]
+    FeeCollectorUnassigned,
+
+    #[msg("the reserve config has invalid settings")]
+    InvalidReserveConfig,
 }
 
 impl From<jet_math::Error> for ErrorCode {
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[event]
+pub struct AcceptMarketOwnerEvent {
+    market: Pubkey,
+    previous_owner: Pubkey,
+    owner: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct AcceptMarketOwner<'info> {
+    #[account(mut)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The account proposed as the new owner
+    pub new_owner: Signer<'info>,
+}
+
+/// Accept a proposed ownership change on a market
+pub fn handler(ctx: Context<AcceptMarketOwner>) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    let previous_owner = market.accept_owner(ctx.accounts.new_owner.key)?;
+
+    emit!(AcceptMarketOwnerEvent {
+        market: ctx.accounts.market.key(),
+        previous_owner,
+        owner: market.owner,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[event]
+pub struct AcceptObligationTransferEvent {
+    obligation: Pubkey,
+    previous_owner: Pubkey,
+    owner: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct AcceptObligationTransfer<'info> {
+    #[account(mut)]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The account the obligation was offered to
+    pub new_owner: Signer<'info>,
+}
+
+/// Accept an offered obligation, along with its collateral and debt
+pub fn handler(ctx: Context<AcceptObligationTransfer>) -> Result<()> {
+    let mut obligation = ctx.accounts.obligation.load_mut()?;
+    let previous_owner = obligation.accept_transfer(ctx.accounts.new_owner.key)?;
+
+    emit!(AcceptObligationTransferEvent {
+        obligation: ctx.accounts.obligation.key(),
+        previous_owner,
+        owner: obligation.owner,
+    });
+
+    Ok(())
+}
@@ -24,8 +24,9 @@ use anchor_spl::token::{self, Mint, MintTo, Transfer};
 use jet_math::Number;
 pub use switchboard_v2::AggregatorAccountData;
 
-use crate::common::Amount;
+use crate::common::{Amount, AmountBasis};
 use crate::common::Rounding;
+use crate::instructions::refresh_reserve_if_stale;
 use crate::state::*;
 
 #[event]
@@ -35,13 +36,7 @@ pub struct BorrowEvent {
     debt: u64,
 }
 
-#[derive(AnchorDeserialize, AnchorSerialize)]
-pub struct BorrowBumpSeeds {
-    loan_account: u8,
-}
-
 #[derive(Accounts)]
-#[instruction(bump: BorrowBumpSeeds)]
 pub struct Borrow<'info> {
     /// The relevant market this borrow is for
     #[account(
@@ -57,7 +52,7 @@ pub struct Borrow<'info> {
     /// The obligation with collateral to borrow with
     #[account(mut,
         has_one = market,
-        constraint = obligation.load().unwrap().owner == borrower.key(),
+        constraint = obligation.load().unwrap().is_authorized(borrower.key, ObligationPermissions::BORROW) @ ErrorCode::Unauthorized,
         constraint = obligation.load().unwrap().has_loan_custody(&loan_account.key()),
     )]
     pub obligation: AccountLoader<'info, Obligation>,
@@ -79,23 +74,20 @@ pub struct Borrow<'info> {
     #[account(mut)]
     pub loan_note_mint: Account<'info, Mint>,
 
-    /// The user/authority that is borrowing
+    /// The obligation owner, or a delegate allowed to borrow
     pub borrower: Signer<'info>,
 
-    /// The account to track the borrower's balance to repay
-    #[account(mut,
-              seeds = [
-                  b"loan".as_ref(),
-                  reserve.key().as_ref(),
-                  obligation.key().as_ref(),
-                  borrower.key.as_ref()
-              ],
-              bump)]
+    /// The account to track the borrower's balance to repay, which the
+    /// obligation must have custody of
+    #[account(mut)]
     pub loan_account: Account<'info, TokenAccount>,
 
-    /// The token account that the borrowed funds will be transferred to
+    /// The token account that the borrowed funds will be transferred to,
+    /// which must belong to the obligation owner when a delegate borrows
     #[account(mut,
-        constraint = receiver_account.key() != vault.key())]
+        constraint = receiver_account.key() != vault.key(),
+        constraint = obligation.load().unwrap().owner == borrower.key()
+            || obligation.load().unwrap().owner == receiver_account.owner @ ErrorCode::Unauthorized)]
     pub receiver_account: Account<'info, TokenAccount>,
 
     /// The mint for the token being stored in this reserve.
@@ -133,8 +125,11 @@ impl<'info> Borrow<'info> {
 }
 
 /// Borrow tokens from a reserve
-pub fn handler(ctx: Context<Borrow>, _bump: BorrowBumpSeeds, amount: Amount) -> Result<()> {
-    // update market's nft floor prices
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, Borrow<'info>>,
+    amount: Amount,
+) -> Result<()> {
+    // update market's nft floor prices, and the reserve if it has gone stale
     {
         let mut market = ctx.accounts.market.load_mut()?;
         let price_decimal =
@@ -145,18 +140,44 @@ pub fn handler(ctx: Context<Borrow>, _bump: BorrowBumpSeeds, amount: Amount) ->
             price_decimal.mantissa as u128,
             -(price_decimal.scale as i32),
         );
+
+        let mut reserve = ctx.accounts.reserve.load_mut()?;
+        refresh_reserve_if_stale(
+            &mut market,
+            &mut reserve,
+            &ctx.accounts.market_authority,
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
     }
     let market = ctx.accounts.market.load()?;
     let mut reserve = ctx.accounts.reserve.load_mut()?;
     let loan_account = &ctx.accounts.loan_account.key();
 
     market.verify_ability_borrow()?;
+    reserve.verify_ability_borrow()?;
     let market_reserves = market.reserves();
     let clock = Clock::get().unwrap();
 
-    let reserve_info = market_reserves.get_cached(reserve.index, clock.slot);
+    let reserve_info = market_reserves.get_cached(reserve.index, clock.slot)?;
+
+    // relative amounts are taken from the most the obligation can borrow
+    let basis = if amount.is_relative() {
+        if reserve_info.price == Number::ZERO {
+            return err!(ErrorCode::InvalidOracle);
+        }
+
+        let mut obligation = ctx.accounts.obligation.load_mut()?;
+        obligation.cache_calculations(market_reserves, clock.slot, market.market_oracle())?;
+
+        AmountBasis::Borrow {
+            max_tokens: max_borrowable(&obligation, &reserve, reserve_info, clock.slot)?,
+        }
+    } else {
+        AmountBasis::NoPosition
+    };
 
-    let requested_tokens = amount.as_tokens(reserve_info, Rounding::Down);
+    let requested_tokens = amount.as_tokens(reserve_info, Rounding::Down, basis)?;
     let fees = reserve.borrow_fee(requested_tokens);
     let protocol_fees = reserve.protocol_fee(requested_tokens);
     let mut total_token_debt = requested_tokens
@@ -180,9 +201,17 @@ pub fn handler(ctx: Context<Borrow>, _bump: BorrowBumpSeeds, amount: Amount) ->
     // this borrower's debt.
     let new_notes = reserve_info.loan_notes_from_tokens(total_token_debt, Rounding::Up);
 
+    // Check the loan against the reserve's caps, counting any debt the
+    // obligation already owes the reserve
+    let existing_notes = ctx.accounts.loan_account.amount;
+    let obligation_debt = reserve_info.loan_notes_to_tokens(existing_notes, Rounding::Up)
+        .checked_add(total_token_debt)
+        .ok_or(ErrorCode::MathOverflow)?;
+    reserve.verify_borrow_limits(clock.slot, requested_tokens, total_token_debt, obligation_debt)?;
+
     // Record the borrow onto the reserve account, and also add any fees
     // to get the total amount borrowed.
-    reserve.borrow(clock.slot, requested_tokens, new_notes, fees, protocol_fees);
+    reserve.borrow(clock.slot, requested_tokens, new_notes, fees, protocol_fees)?;
 
     token::mint_to(
         ctx.accounts
@@ -196,12 +225,12 @@ pub fn handler(ctx: Context<Borrow>, _bump: BorrowBumpSeeds, amount: Amount) ->
     obligation.borrow(loan_account, reserve.amount(new_notes))?;
 
     let market_oracle = market.market_oracle();
-    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle);
+    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle)?;
 
     // Validate that the obligation has sufficient collateral to borrow
     // the requested amount, by checking that its still healthy after
     // minting the new debt.
-    if !obligation.is_healthy(&market_reserves, clock.slot) {
+    if !obligation.is_healthy(&market_reserves, clock.slot)? {
         return err!(ErrorCode::InsufficientCollateral);
     }
 
@@ -222,3 +251,88 @@ pub fn handler(ctx: Context<Borrow>, _bump: BorrowBumpSeeds, amount: Amount) ->
 
     Ok(())
 }
+
+/// The most tokens an obligation can borrow from a reserve while staying
+/// healthy, once fees are added and the debt is rounded up into notes.
+/// Requires the obligation's calculations to have been cached.
+pub fn max_borrowable(
+    obligation: &Obligation,
+    reserve: &Reserve,
+    reserve_info: &CachedReserveInfo,
+    current_slot: u64,
+) -> Result<u64> {
+    let capacity = obligation.borrowing_capacity(reserve_info, current_slot)?;
+    let max_debt = (capacity / reserve_info.price).as_u64(reserve.exponent);
+
+    // the new notes are rounded up, so the debt must fit in whole notes
+    let max_notes = reserve_info.loan_notes_from_tokens(max_debt, Rounding::Down);
+    let max_debt = reserve_info.loan_notes_to_tokens(max_notes, Rounding::Down);
+
+    Ok(reserve.max_borrow_for_debt(max_debt))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+
+    use super::*;
+
+    /// Borrow as the handler does, returning whether the obligation is
+    /// still healthy
+    fn borrow_is_healthy(
+        market: &MarketReserves,
+        obligation: &mut Obligation,
+        reserve: &Reserve,
+        loan: &Pubkey,
+        oracle: &MarketOracle,
+        tokens: u64,
+    ) -> bool {
+        let reserve_info = market.get_cached(0, 0).unwrap();
+        let notes = reserve_info.loan_notes_from_tokens(reserve.borrow_debt(tokens), Rounding::Up);
+
+        obligation.borrow(loan, reserve.amount(notes)).unwrap();
+        obligation.cache_calculations(market, 0, oracle).unwrap();
+        obligation.is_healthy(market, 0).unwrap()
+    }
+
+    #[test]
+    fn borrowing_the_max_stays_healthy() {
+        let mut reserve = Reserve::zeroed();
+        reserve.exponent = -9;
+        reserve.config.loan_origination_fee = 250;
+
+        for (loan_note_rate, price, nft_price) in [
+            (Number::ONE, 20u64, 1_000u64),
+            (Number::from_bps(10_003), 17, 1_234),
+            (Number::from_bps(13_337), 31, 999),
+            (Number::from_bps(19_999), 1, 7),
+        ] {
+            let mut market = MarketReserves::zeroed();
+            market.register(&Pubkey::new_unique()).unwrap();
+            let cache = market.get_mut(0).cache.get_stale_mut();
+            cache.price = Number::from(price);
+            cache.loan_note_exchange_rate = loan_note_rate;
+            cache.min_collateral_ratio = Number::from_bps(12_500);
+
+            let oracle = MarketOracle { price: Number::from(nft_price) };
+            let loan = Pubkey::new_unique();
+            let mut obligation = Obligation::zeroed();
+            obligation.register_nft(Pubkey::new_unique()).unwrap();
+            obligation.register_loan(&loan, 0).unwrap();
+
+            // with nothing owed, and again with a loan already taken
+            for existing_loan in [0, 1_000_003] {
+                assert!(borrow_is_healthy(&market, &mut obligation, &reserve, &loan, &oracle, existing_loan));
+
+                let reserve_info = market.get_cached(0, 0).unwrap();
+                let max = max_borrowable(&obligation, &reserve, reserve_info, 0).unwrap();
+                assert!(max > 0);
+
+                let mut over = obligation;
+                let mut at_max = obligation;
+                assert!(borrow_is_healthy(&market, &mut at_max, &reserve, &loan, &oracle, max));
+                assert!(!borrow_is_healthy(&market, &mut over, &reserve, &loan, &oracle, max + max / 1_000));
+            }
+        }
+    }
+}
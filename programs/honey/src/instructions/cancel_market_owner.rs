@@ -14,23 +14,35 @@
 //
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
-
 use anchor_lang::prelude::*;
 
 use crate::state::*;
 
+#[event]
+pub struct CancelMarketOwnerEvent {
+    market: Pubkey,
+    owner: Pubkey,
+    cancelled_owner: Pubkey,
+}
+
 #[derive(Accounts)]
-pub struct SetMarketOwner<'info> {
+pub struct CancelMarketOwner<'info> {
     #[account(mut, has_one = owner)]
     pub market: AccountLoader<'info, Market>,
 
     pub owner: Signer<'info>,
 }
 
-/// Change the owner on a market
-pub fn handler(ctx: Context<SetMarketOwner>, new_owner: Pubkey) -> Result<()> {
+/// Withdraw a pending ownership change on a market
+pub fn handler(ctx: Context<CancelMarketOwner>) -> Result<()> {
     let mut market = ctx.accounts.market.load_mut()?;
-    market.owner = new_owner;
+    let cancelled_owner = market.cancel_owner_proposal()?;
+
+    emit!(CancelMarketOwnerEvent {
+        market: ctx.accounts.market.key(),
+        owner: market.owner,
+        cancelled_owner,
+    });
 
     Ok(())
 }
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    /// The user/authority that owns the deposit, which receives the rent
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    /// The request being cancelled
+    #[account(mut,
+              has_one = depositor,
+              close = depositor)]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+}
+
+/// Cancel the rest of a queued withdrawal
+pub fn handler(ctx: Context<CancelWithdrawal>) -> Result<()> {
+    msg!(
+        "cancelled withdrawal with {} notes remaining",
+        ctx.accounts.withdrawal_request.notes_remaining
+    );
+    Ok(())
+}
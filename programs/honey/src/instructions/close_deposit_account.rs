@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, CloseAccount, Token, TokenAccount};
+
+use crate::state::*;
+use crate::utils::verify_account_empty;
+
+#[derive(Accounts)]
+pub struct CloseDepositAccount<'info> {
+    /// The relevant market the deposit is in
+    #[account(has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The reserve the deposit account holds notes for
+    #[account(has_one = market)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// The user/authority that owns the deposits, which receives the rent
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    /// The withdrawn account that stored the deposit notes
+    #[account(mut,
+              seeds = [
+                  b"deposits".as_ref(),
+                  reserve.key().as_ref(),
+                  depositor.key.as_ref()
+              ],
+              bump)]
+    pub deposit_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> CloseDepositAccount<'info> {
+    fn close_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.deposit_account.to_account_info(),
+                destination: self.depositor.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+}
+
+/// Close a deposit account that has had all of its notes withdrawn
+pub fn handler(ctx: Context<CloseDepositAccount>) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+
+    verify_account_empty(&ctx.accounts.deposit_account.to_account_info())?;
+
+    token::close_account(
+        ctx.accounts
+            .close_context()
+            .with_signer(&[&market.authority_seeds()]),
+    )?;
+
+    msg!("closed deposit account");
+    Ok(())
+}
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, CloseAccount, Token, TokenAccount};
+
+use crate::state::*;
+use crate::utils::verify_account_empty;
+
+#[derive(Accounts)]
+pub struct CloseLoanAccount<'info> {
+    /// The relevant market the loan is in
+    #[account(has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The obligation the loan account is registered with
+    #[account(mut,
+              has_one = market,
+              has_one = owner,
+              constraint = obligation.load()?.has_loan_custody(&loan_account.key()))]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The user/authority that owns the loan, which receives the rent
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// The repaid account that stored the loan notes
+    #[account(mut)]
+    pub loan_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> CloseLoanAccount<'info> {
+    fn close_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.loan_account.to_account_info(),
+                destination: self.owner.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+}
+
+/// Unregister a repaid loan account from its obligation and close it
+pub fn handler(ctx: Context<CloseLoanAccount>) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    let mut obligation = ctx.accounts.obligation.load_mut()?;
+
+    verify_account_empty(&ctx.accounts.loan_account.to_account_info())?;
+    obligation.unregister_loan(&ctx.accounts.loan_account.key())?;
+
+    token::close_account(
+        ctx.accounts
+            .close_context()
+            .with_signer(&[&market.authority_seeds()]),
+    )?;
+
+    msg!("closed loan account");
+    Ok(())
+}
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct CloseObligation<'info> {
+    /// The relevant market
+    pub market: AccountLoader<'info, Market>,
+
+    /// The obligation being closed
+    #[account(mut,
+              close = owner,
+              has_one = market,
+              has_one = owner)]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The user/authority that owns the obligation, which receives the rent
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+/// Close an obligation with no loans or collateral left registered
+pub fn handler(ctx: Context<CloseObligation>) -> Result<()> {
+    let obligation = ctx.accounts.obligation.load()?;
+    obligation.verify_empty()?;
+
+    msg!("closed obligation");
+    Ok(())
+}
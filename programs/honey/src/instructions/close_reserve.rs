@@ -0,0 +1,230 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, CloseAccount, Mint, SetAuthority, Token, TokenAccount, Transfer};
+use anchor_spl::token::spl_token::instruction::AuthorityType;
+
+use crate::errors::ErrorCode;
+use crate::state::*;
+
+#[event]
+pub struct CloseReserveEvent {
+    market: Pubkey,
+    reserve: Pubkey,
+    index: ReserveIndex,
+}
+
+#[derive(Accounts)]
+pub struct CloseReserve<'info> {
+    /// The market the reserve is being removed from
+    #[account(mut,
+              has_one = owner,
+              has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account, which owns the vaults and mints
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The reserve being closed
+    #[account(mut,
+              close = owner,
+              has_one = market,
+              has_one = vault,
+              has_one = fee_note_vault,
+              has_one = protocol_fee_note_vault,
+              has_one = deposit_note_mint,
+              has_one = loan_note_mint)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// The reserve's vault, holding any tokens left over from fees
+    #[account(mut)]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// The account holding notes from fees collected by the reserve
+    #[account(mut)]
+    pub fee_note_vault: Box<Account<'info, TokenAccount>>,
+
+    /// The account holding notes from protocol fees collected by the reserve
+    #[account(mut)]
+    pub protocol_fee_note_vault: Box<Account<'info, TokenAccount>>,
+
+    /// The reserve's insurance fund, which has nothing left to cover. Until
+    /// the reserve has a fund, this can be any writable account, such as
+    /// the vault, and is left alone.
+    /// CHECK: checked against the reserve, and only used once it has a fund
+    #[account(mut,
+              constraint = reserve.load()?.is_insurance_fund(insurance_fund.key) @ ErrorCode::InvalidParameter)]
+    pub insurance_fund: AccountInfo<'info>,
+
+    /// The mint for the reserve's deposit notes
+    #[account(mut)]
+    pub deposit_note_mint: Box<Account<'info, Mint>>,
+
+    /// The mint for the reserve's loan notes
+    #[account(mut)]
+    pub loan_note_mint: Box<Account<'info, Mint>>,
+
+    /// The market owner's account receiving any tokens left in the vault
+    /// and insurance fund
+    #[account(mut,
+              constraint = token_receiver.owner == owner.key() @ ErrorCode::InvalidParameter)]
+    pub token_receiver: Box<Account<'info, TokenAccount>>,
+
+    /// The market owner, which receives the rent from closed accounts
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> CloseReserve<'info> {
+    fn burn_fee_notes_context(
+        &self,
+        from: &Account<'info, TokenAccount>,
+    ) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Burn {
+                mint: self.deposit_note_mint.to_account_info(),
+                from: from.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+
+    fn transfer_leftovers_context(
+        &self,
+        from: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: from.clone(),
+                to: self.token_receiver.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+
+    fn close_account_context(
+        &self,
+        account: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: account.clone(),
+                destination: self.owner.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+
+    fn revoke_mint_authority_context(
+        &self,
+        mint: &Account<'info, Mint>,
+    ) -> CpiContext<'_, '_, '_, 'info, SetAuthority<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            SetAuthority {
+                account_or_mint: mint.to_account_info(),
+                current_authority: self.market_authority.clone(),
+            },
+        )
+    }
+}
+
+/// Close a reserve that has been wound down and emptied, returning the rent
+/// to the market owner and freeing its slot in the market for reuse. The
+/// reserve account, its vault, fee note vaults and insurance fund are all
+/// closed.
+///
+/// The token program can't close mints, so the note mints, which must have
+/// no supply left beyond the fee notes being burned, are left behind with
+/// their mint authority revoked.
+pub fn handler(ctx: Context<CloseReserve>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let mut market = accounts.market.load_mut()?;
+    let reserve = accounts.reserve.load()?;
+
+    let fee_notes = accounts.fee_note_vault.amount + accounts.protocol_fee_note_vault.amount;
+    reserve.verify_closable(fee_notes)?;
+
+    if accounts.deposit_note_mint.supply != fee_notes || accounts.loan_note_mint.supply != 0 {
+        msg!("the reserve still has notes outstanding");
+        return Err(ErrorCode::ReserveNotClosable.into());
+    }
+
+    // Fee notes are the only claim left on the vault, so the remaining
+    // tokens are paid out to the owner along with them.
+    for fee_vault in [&accounts.fee_note_vault, &accounts.protocol_fee_note_vault] {
+        token::burn(
+            accounts
+                .burn_fee_notes_context(fee_vault)
+                .with_signer(&[&market.authority_seeds()]),
+            fee_vault.amount,
+        )?;
+    }
+
+    // reserves from before insurance funds may not have one to close
+    let mut token_vaults = vec![accounts.vault.to_account_info()];
+    if reserve.has_insurance_fund() {
+        token_vaults.push(accounts.insurance_fund.clone());
+    }
+
+    for token_vault in &token_vaults {
+        token::transfer(
+            accounts
+                .transfer_leftovers_context(token_vault)
+                .with_signer(&[&market.authority_seeds()]),
+            token::accessor::amount(token_vault)?,
+        )?;
+    }
+
+    let fee_vaults = [
+        accounts.fee_note_vault.to_account_info(),
+        accounts.protocol_fee_note_vault.to_account_info(),
+    ];
+    for token_account in token_vaults.iter().chain(&fee_vaults) {
+        token::close_account(
+            accounts
+                .close_account_context(token_account)
+                .with_signer(&[&market.authority_seeds()]),
+        )?;
+    }
+
+    for mint in [&accounts.deposit_note_mint, &accounts.loan_note_mint] {
+        token::set_authority(
+            accounts
+                .revoke_mint_authority_context(mint)
+                .with_signer(&[&market.authority_seeds()]),
+            AuthorityType::MintTokens,
+            None,
+        )?;
+    }
+
+    market.reserves_mut().remove(reserve.index);
+
+    emit!(CloseReserveEvent {
+        market: accounts.market.key(),
+        reserve: accounts.reserve.key(),
+        index: reserve.index,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::errors::ErrorCode;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct CloseWithdrawal<'info> {
+    /// The user that queued the withdrawal, which receives the rent
+    /// CHECK: checked against the withdrawal request
+    #[account(mut)]
+    pub depositor: AccountInfo<'info>,
+
+    /// The filled request being closed
+    #[account(mut,
+              has_one = depositor,
+              constraint = withdrawal_request.is_filled() @ ErrorCode::WithdrawalRequestPending,
+              close = depositor)]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+}
+
+/// Close a filled withdrawal request, returning its rent to the depositor,
+/// which anyone may do
+pub fn handler(ctx: Context<CloseWithdrawal>) -> Result<()> {
+    msg!(
+        "closed withdrawal that paid out {} tokens",
+        ctx.accounts.withdrawal_request.tokens_filled
+    );
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use crate::errors::ErrorCode;
+use crate::state::Bid;
+use crate::Market;
+use anchor_lang::prelude::*;
+use anchor_spl::token;
+use anchor_spl::token::{CloseAccount, Mint, Token, TokenAccount, Transfer};
+
+#[event]
+pub struct CrankExpiredBidEvent {
+    bid: Pubkey,
+    bidder: Pubkey,
+    refunded: u64,
+}
+
+#[derive(Accounts)]
+pub struct CrankExpiredBid<'info> {
+    pub market: AccountLoader<'info, Market>,
+
+    #[account(mut,
+        seeds = [
+            b"bid".as_ref(),
+            market.key().as_ref(),
+            bidder.key.as_ref(),
+        ],
+        has_one = market @ ErrorCode::InvalidParameter,
+        has_one = bid_escrow_authority @ ErrorCode::InvalidParameter,
+        has_one = bid_escrow @ ErrorCode::InvalidParameter,
+        has_one = bidder @ ErrorCode::InvalidParameter,
+        has_one = bid_mint @ ErrorCode::InvalidParameter,
+        bump,
+        close = bidder)]
+    pub bid: Account<'info, Bid>,
+
+    /// CHECK: bidder checked against bid, and only receives the refund
+    #[account(mut)]
+    pub bidder: AccountInfo<'info>,
+
+    /// The bidder's token account receiving the refund
+    #[account(mut,
+        constraint = withdraw_destination.owner == bidder.key() @ ErrorCode::InvalidParameter,
+        constraint = withdraw_destination.mint == bid_mint.key() @ ErrorCode::InvalidParameter)]
+    pub withdraw_destination: Account<'info, TokenAccount>,
+
+    pub bid_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub bid_escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: bid has one bid_escrow_authority
+    pub bid_escrow_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> CrankExpiredBid<'info> {
+    fn transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.bid_escrow.to_account_info(),
+                to: self.withdraw_destination.to_account_info(),
+                authority: self.bid_escrow_authority.clone(),
+            },
+        )
+    }
+
+    fn close_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                authority: self.bid_escrow_authority.clone(),
+                account: self.bid_escrow.to_account_info(),
+                destination: self.bidder.to_account_info(),
+            },
+        )
+    }
+}
+
+/// Refund an expired bid to its bidder, which anyone may do
+pub fn handler(ctx: Context<CrankExpiredBid>) -> Result<()> {
+    let bid = &ctx.accounts.bid;
+
+    if !bid.is_expired(Clock::get()?.unix_timestamp) {
+        return Err(ErrorCode::BidNotExpired.into());
+    }
+
+    let refunded = ctx.accounts.bid_escrow.amount;
+
+    token::transfer(
+        ctx.accounts
+            .transfer_context()
+            .with_signer(&[&bid.authority_seeds()]),
+        refunded,
+    )?;
+
+    token::close_account(
+        ctx.accounts
+            .close_context()
+            .with_signer(&[&bid.authority_seeds()]),
+    )?;
+
+    emit!(CrankExpiredBidEvent {
+        bid: ctx.accounts.bid.key(),
+        bidder: ctx.accounts.bidder.key(),
+        refunded,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::cnft::{
+    account_compression_program, bubblegum_program, noop_program, BubblegumTransfer,
+    CompressedNft,
+};
+use crate::errors::ErrorCode;
+use crate::state::*;
+
+#[event]
+pub struct DepositCompressedCollateralEvent {
+    depositor: Pubkey,
+    market: Pubkey,
+    asset_id: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct DepositCNFT<'info> {
+    /// The relevant market this deposit is for
+    #[account(mut, has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account, which becomes the leaf's owner
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The obligation the collateral is being deposited toward
+    #[account(mut,
+              has_one = market,
+              constraint = obligation.load()?.is_authorized(owner.key, ObligationPermissions::DEPOSIT_NFT) @ ErrorCode::Unauthorized)]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The owner of the compressed nft, which is the obligation owner or
+    /// a delegate allowed to deposit collateral
+    pub owner: Signer<'info>,
+
+    /// CHECK: Bubblegum checks this is the leaf's delegate
+    pub leaf_delegate: AccountInfo<'info>,
+
+    /// CHECK: Bubblegum checks the proof against this tree
+    #[account(mut)]
+    pub merkle_tree: AccountInfo<'info>,
+
+    /// CHECK: checked against the tree's Bubblegum authority
+    pub tree_authority: AccountInfo<'info>,
+
+    /// CHECK: must be the noop program
+    #[account(address = noop_program::ID)]
+    pub log_wrapper: AccountInfo<'info>,
+
+    /// CHECK: must be the account compression program
+    #[account(address = account_compression_program::ID)]
+    pub compression_program: AccountInfo<'info>,
+
+    /// CHECK: must be the Bubblegum program
+    #[account(address = bubblegum_program::ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Deposit a compressed nft as collateral for an obligation, by making the
+/// market authority its owner. The proof nodes are the trailing accounts.
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, DepositCNFT<'info>>,
+    nft: CompressedNft,
+) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    let asset_id = nft.asset_id(ctx.accounts.merkle_tree.key);
+
+    nft.verify_creator(&market.nft_collection_creator)?;
+
+    market.verify_ability_deposit_nft()?;
+
+    BubblegumTransfer {
+        tree_authority: &ctx.accounts.tree_authority,
+        leaf_owner: ctx.accounts.owner.as_ref(),
+        leaf_delegate: &ctx.accounts.leaf_delegate,
+        new_leaf_owner: &ctx.accounts.market_authority,
+        merkle_tree: &ctx.accounts.merkle_tree,
+        log_wrapper: &ctx.accounts.log_wrapper,
+        compression_program: &ctx.accounts.compression_program,
+        system_program: ctx.accounts.system_program.as_ref(),
+        bubblegum_program: &ctx.accounts.bubblegum_program,
+        proof: ctx.remaining_accounts,
+    }
+    .invoke_signed(&nft, &[])?;
+
+    let mut obligation = ctx.accounts.obligation.load_mut()?;
+    obligation.register_nft_with_custody(asset_id, NftCustody::Compressed)?;
+
+    emit!(DepositCompressedCollateralEvent {
+        depositor: ctx.accounts.owner.key(),
+        market: ctx.accounts.market.key(),
+        asset_id,
+    });
+
+    Ok(())
+}
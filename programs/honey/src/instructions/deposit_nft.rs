@@ -22,6 +22,7 @@ use anchor_spl::token::Token;
 use anchor_spl::{
     token::{Mint, self, Transfer, TokenAccount},
 };
+use crate::errors::ErrorCode;
 use crate::state::*;
 use crate::utils::verify_valid_metadata;
 use crate::utils::validate;
@@ -48,12 +49,13 @@ pub struct DepositNFT<'info> {
     /// The obligation the collateral is being deposited toward
     #[account(mut, 
         has_one = market, 
-        has_one = owner,
+        constraint = obligation.load()?.is_authorized(owner.key, ObligationPermissions::DEPOSIT_NFT) @ ErrorCode::Unauthorized,
         // constraint = obligation.load().unwrap().has_collateral_custody(&collateral_account.key()),
     )]
     pub obligation: AccountLoader<'info, Obligation>,
 
-    /// The user/authority that owns the deposit
+    /// The user/authority that owns the deposit, which is the obligation
+    /// owner or a delegate allowed to deposit collateral
     #[account(mut)]
     pub owner: Signer<'info>,
 
@@ -114,7 +116,7 @@ pub fn handler(
 
     verify_valid_metadata(&ctx.accounts.metadata, &ctx.accounts.nft_collection_creator)?;
 
-    market.verify_ability_deposit_withdraw()?;
+    market.verify_ability_deposit_nft()?;
 
     let note_amount = 1;
 
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Approve, Mint, Token, TokenAccount};
+use mpl_token_metadata::ID as metadata_program_id;
+
+use crate::errors::ErrorCode;
+use crate::state::*;
+use crate::utils::{validate, verify_valid_metadata, DelegatedNft};
+
+#[event]
+pub struct DepositEscrowlessCollateralEvent {
+    depositor: Pubkey,
+    market: Pubkey,
+    mint: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct DepositNFTEscrowless<'info> {
+    /// The relevant market this deposit is for
+    #[account(mut,
+              has_one = market_authority,
+              has_one = nft_collection_creator)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account, which becomes the nft's delegate
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The obligation the collateral is being deposited toward
+    #[account(mut,
+              has_one = market,
+              has_one = owner)]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The owner of the obligation, whose wallet keeps the nft
+    pub owner: Signer<'info>,
+
+    /// The owner's account holding the nft, which gets frozen in place
+    #[account(mut,
+              constraint = deposit_source.owner == owner.key() @ ErrorCode::InvalidParameter,
+              constraint = deposit_source.mint == deposit_nft_mint.key() @ ErrorCode::InvalidParameter,
+              constraint = deposit_source.amount == 1 @ ErrorCode::InvalidParameter)]
+    pub deposit_source: Account<'info, TokenAccount>,
+
+    pub deposit_nft_mint: Account<'info, Mint>,
+
+    /// verified collection creator
+    /// CHECK: market must have a nft_collection_creator account
+    pub nft_collection_creator: AccountInfo<'info>,
+
+    /// CHECK: metadata validated with validate check
+    pub metadata: AccountInfo<'info>,
+
+    /// CHECK: Token Metadata checks this is the nft's edition
+    pub edition: AccountInfo<'info>,
+
+    /// CHECK: must be the Token Metadata program
+    #[account(address = metadata_program_id)]
+    pub token_metadata_program: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> DepositNFTEscrowless<'info> {
+    fn approve_context(&self) -> CpiContext<'_, '_, '_, 'info, Approve<'info>> {
+        CpiContext::new(self.token_program.to_account_info(), Approve {
+            to: self.deposit_source.to_account_info(),
+            delegate: self.market_authority.clone(),
+            authority: self.owner.to_account_info(),
+        })
+    }
+
+    fn delegated_nft(&self) -> DelegatedNft<'_, 'info> {
+        DelegatedNft {
+            delegate: &self.market_authority,
+            token_account: self.deposit_source.as_ref(),
+            edition: &self.edition,
+            mint: self.deposit_nft_mint.as_ref(),
+            token_program: self.token_program.as_ref(),
+            token_metadata_program: &self.token_metadata_program,
+        }
+    }
+}
+
+/// Pledge an nft as collateral while leaving it in the owner's wallet,
+/// frozen with the market authority as its delegate
+#[access_control(validate(metadata_bump, &ctx.accounts.metadata, &ctx.accounts.deposit_nft_mint))]
+pub fn handler(ctx: Context<DepositNFTEscrowless>, metadata_bump: u8) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    let deposit_nft_mint = ctx.accounts.deposit_nft_mint.key();
+
+    verify_valid_metadata(&ctx.accounts.metadata, &ctx.accounts.nft_collection_creator)?;
+
+    market.verify_ability_deposit_nft()?;
+
+    token::approve(ctx.accounts.approve_context(), 1)?;
+    ctx.accounts.delegated_nft().freeze(&[&market.authority_seeds()])?;
+
+    let mut obligation = ctx.accounts.obligation.load_mut()?;
+    obligation.register_nft_with_custody(deposit_nft_mint, NftCustody::Frozen)?;
+
+    emit!(DepositEscrowlessCollateralEvent {
+        depositor: ctx.accounts.owner.key(),
+        market: ctx.accounts.market.key(),
+        mint: deposit_nft_mint,
+    });
+
+    Ok(())
+}
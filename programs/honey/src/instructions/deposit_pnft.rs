@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use mpl_token_metadata::ID as metadata_program_id;
+
+use crate::errors::ErrorCode;
+use crate::pnft::PnftTransfer;
+use crate::state::*;
+use crate::utils::{validate, verify_valid_metadata};
+
+#[event]
+pub struct DepositProgrammableCollateralEvent {
+    depositor: Pubkey,
+    market: Pubkey,
+    mint: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct DepositPNFT<'info> {
+    /// The relevant market this deposit is for
+    #[account(mut,
+              has_one = market_authority,
+              has_one = nft_collection_creator)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The obligation the collateral is being deposited toward
+    #[account(mut,
+              has_one = market,
+              constraint = obligation.load()?.is_authorized(owner.key, ObligationPermissions::DEPOSIT_NFT) @ ErrorCode::Unauthorized)]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The user/authority that owns the deposit, which is the obligation
+    /// owner or a delegate allowed to deposit collateral
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// The account holding the pNFT being deposited
+    #[account(mut)]
+    pub deposit_source: Box<Account<'info, TokenAccount>>,
+
+    pub deposit_nft_mint: Box<Account<'info, Mint>>,
+
+    /// verified collection creator
+    /// CHECK: market must have a nft_collection_creator account
+    pub nft_collection_creator: AccountInfo<'info>,
+
+    /// CHECK: metadata validated with validate check
+    #[account(mut)]
+    pub metadata: AccountInfo<'info>,
+
+    /// CHECK: checked against the pNFT's master edition address
+    pub edition: AccountInfo<'info>,
+
+    /// CHECK: checked against the token record for the deposit source
+    #[account(mut)]
+    pub owner_token_record: AccountInfo<'info>,
+
+    /// CHECK: checked against the token record for the collateral account
+    #[account(mut)]
+    pub collateral_token_record: AccountInfo<'info>,
+
+    /// The market authority's account that will hold the pNFT as
+    /// collateral, which Token Metadata creates if needed
+    /// CHECK: must be the market authority's associated token account
+    #[account(mut,
+              address = get_associated_token_address(market_authority.key, &deposit_nft_mint.key()))]
+    pub collateral_account: AccountInfo<'info>,
+
+    /// CHECK: must be the Token Metadata program
+    #[account(address = metadata_program_id)]
+    pub token_metadata_program: AccountInfo<'info>,
+
+    /// CHECK: must be the instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: AccountInfo<'info>,
+
+    /// CHECK: checked against the pNFT's rule set, when it has one
+    pub authorization_rules_program: AccountInfo<'info>,
+
+    /// CHECK: checked against the pNFT's rule set, when it has one
+    pub authorization_rules: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> DepositPNFT<'info> {
+    fn pnft_transfer(&self) -> PnftTransfer<'_, 'info> {
+        PnftTransfer {
+            token: self.deposit_source.as_ref().as_ref(),
+            token_owner: self.owner.as_ref(),
+            destination: &self.collateral_account,
+            destination_owner: &self.market_authority,
+            mint: self.deposit_nft_mint.as_ref().as_ref(),
+            metadata: &self.metadata,
+            edition: &self.edition,
+            owner_token_record: &self.owner_token_record,
+            destination_token_record: &self.collateral_token_record,
+            authority: self.owner.as_ref(),
+            payer: self.owner.as_ref(),
+            system_program: self.system_program.as_ref(),
+            sysvar_instructions: &self.sysvar_instructions,
+            token_program: self.token_program.as_ref(),
+            associated_token_program: self.associated_token_program.as_ref(),
+            token_metadata_program: &self.token_metadata_program,
+            authorization_rules_program: &self.authorization_rules_program,
+            authorization_rules: &self.authorization_rules,
+        }
+    }
+}
+
+/// Deposit a programmable NFT as collateral for an obligation, moving it
+/// with Token Metadata so its rule set is respected
+#[access_control(validate(metadata_bump, &ctx.accounts.metadata, &ctx.accounts.deposit_nft_mint))]
+pub fn handler(ctx: Context<DepositPNFT>, metadata_bump: u8) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    let deposit_nft_mint = ctx.accounts.deposit_nft_mint.key();
+
+    verify_valid_metadata(&ctx.accounts.metadata, &ctx.accounts.nft_collection_creator)?;
+
+    market.verify_ability_deposit_nft()?;
+
+    ctx.accounts.pnft_transfer().invoke_signed(&[])?;
+
+    let mut obligation = ctx.accounts.obligation.load_mut()?;
+    obligation.register_nft(deposit_nft_mint)?;
+
+    emit!(DepositProgrammableCollateralEvent {
+        depositor: ctx.accounts.owner.key(),
+        market: ctx.accounts.market.key(),
+        mint: deposit_nft_mint,
+    });
+
+    Ok(())
+}
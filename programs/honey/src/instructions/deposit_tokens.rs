@@ -16,7 +16,8 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{
-    common::{Amount, Rounding},
+    common::{Amount, AmountBasis, Rounding},
+    instructions::refresh_reserve_if_stale,
     state::*,
 };
 use anchor_lang::prelude::*;
@@ -26,7 +27,7 @@ use anchor_spl::token::{self, Mint, MintTo, Token, TokenAccount, Transfer};
 #[instruction(bump: u8)]
 pub struct DepositTokens<'info> {
     /// The relevant market this deposit is for
-    #[account(has_one = market_authority)]
+    #[account(mut, has_one = market_authority)]
     pub market: AccountLoader<'info, Market>,
 
     /// The market's authority account
@@ -93,19 +94,34 @@ impl<'info> DepositTokens<'info> {
 }
 
 /// Deposit tokens into a reserve
-pub fn handler(ctx: Context<DepositTokens>, _bump: u8, amount: Amount) -> Result<()> {
-    let market = ctx.accounts.market.load()?;
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, DepositTokens<'info>>,
+    _bump: u8,
+    amount: Amount,
+) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
     let mut reserve = ctx.accounts.reserve.load_mut()?;
+
+    refresh_reserve_if_stale(
+        &mut market,
+        &mut reserve,
+        &ctx.accounts.market_authority,
+        &ctx.accounts.token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
     let clock = Clock::get()?;
-    let reserve_info = market.reserves().get_cached(reserve.index, clock.slot);
+    let reserve_info = market.reserves().get_cached(reserve.index, clock.slot)?;
 
     market.verify_ability_deposit_withdraw()?;
+    reserve.verify_ability_deposit()?;
 
     // Calculate the number of new notes that need to be minted to represent
     // the current value being deposited
-    let token_amount = amount.as_tokens(reserve_info, Rounding::Up);
-    let note_amount = amount.as_deposit_notes(reserve_info, Rounding::Down)?;
+    let token_amount = amount.as_tokens(reserve_info, Rounding::Up, AmountBasis::NoPosition)?;
+    let note_amount = amount.as_deposit_notes(reserve_info, Rounding::Down, AmountBasis::NoPosition)?;
 
+    reserve.verify_deposit_cap(clock.slot, token_amount)?;
     reserve.deposit(token_amount, note_amount);
 
     // Now that we have the note value, we can transfer this deposit
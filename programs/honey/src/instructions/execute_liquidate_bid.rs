@@ -1,10 +1,14 @@
 use crate::errors::ErrorCode;
-use crate::state::Bid;
+use crate::instructions::BadDebtEvent;
+use crate::pnft::PnftTransfer;
+use crate::state::{BadDebtCover, Bid, LiquidationSplit, MarketRole, MarketRoles, NftCustody};
+use crate::utils::DelegatedNft;
 use crate::{ Amount, Market, Obligation, Reserve, Rounding };
 use anchor_lang::prelude::*;
-use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{ self, Burn, Mint, Token, TokenAccount, Transfer };
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
+use anchor_spl::token::{ self, Burn, CloseAccount, Mint, Token, TokenAccount, Transfer };
 use jet_math::Number;
+use solana_program::program_option::COption;
 use solana_program::account_info::AccountInfo;
 
 #[event]
@@ -13,6 +17,16 @@ pub struct ExecuteLiquidateEvent {
     owner: Pubkey
 }
 
+#[event]
+pub struct BadDebtLiquidationEvent {
+    bid: Pubkey,
+    obligation: Pubkey,
+    owner: Pubkey,
+    executor: Pubkey,
+    repaid: u64,
+    shortfall: u64,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct ExecuteLiquidateBidBumps {
     bid: u8,
@@ -38,6 +52,13 @@ pub struct ExecuteLiquidateBid<'info> {
     )]
     pub obligation: AccountLoader<'info, Obligation>,
 
+    /// The obligation's owner, which paid for an escrowed nft's account and
+    /// receives its rent once the nft is liquidated
+    /// CHECK: checked against the obligation
+    #[account(mut,
+        constraint = obligation.load()?.owner == obligation_owner.key() @ ErrorCode::InvalidParameter)]
+    pub obligation_owner: AccountInfo<'info>,
+
     #[account(mut,
         has_one = market,
         has_one = vault,
@@ -48,6 +69,14 @@ pub struct ExecuteLiquidateBid<'info> {
     #[account(mut)]
     pub vault: Box<Account<'info, TokenAccount>>,
 
+    /// The reserve's insurance fund, which receives a share of the protocol
+    /// fee and covers any shortfall. Until the reserve has a fund, this can
+    /// be any writable account, such as the vault.
+    /// CHECK: checked against the reserve, and only used once it has a fund
+    #[account(mut,
+              constraint = reserve.load()?.is_insurance_fund(insurance_fund.key) @ ErrorCode::InvalidParameter)]
+    pub insurance_fund: AccountInfo<'info>,
+
     /// The mint for the debt/loan notes
     #[account(mut)]
     pub loan_note_mint: Box<Account<'info, Mint>>,
@@ -70,8 +99,7 @@ pub struct ExecuteLiquidateBid<'info> {
         bump,
         has_one = bidder,
         has_one = bid_mint,
-        has_one = bid_escrow_authority,
-        close = bidder
+        has_one = bid_escrow_authority
     )]
     pub bid: Box<Account<'info, Bid>>,
 
@@ -79,10 +107,16 @@ pub struct ExecuteLiquidateBid<'info> {
     /// CHECK: bidder checked against bid
     pub bidder: AccountInfo<'info>,
 
-    #[account(mut,
-        constraint = * root_authority.key == crate::ROOT_AUTHORITY)]
-    /// CHECK: constraint root authority address
-    pub root_authority: AccountInfo<'info>,
+    /// The protocol fee would be burned into an account of the default
+    /// key, so liquidations wait until a fee collector is assigned
+    #[account(has_one = market,
+              has_one = fee_collector,
+              constraint = roles.holder(MarketRole::FeeCollector) != Pubkey::default() @ ErrorCode::FeeCollectorUnassigned)]
+    pub roles: Box<Account<'info, MarketRoles>>,
+
+    #[account(mut)]
+    /// CHECK: fee collector checked against the market roles
+    pub fee_collector: AccountInfo<'info>,
 
     pub bid_mint: Box<Account<'info, Mint>>,
 
@@ -101,10 +135,10 @@ pub struct ExecuteLiquidateBid<'info> {
     /// mint of the nft you are liquidating
     pub nft_mint: Box<Account<'info, Mint>>,
 
-    /// The account that stores the nft
+    /// The account that stores the nft, which is the market authority's
+    /// account for escrowed nfts, or the borrower's for nfts frozen in place
     #[account(mut,
-            associated_token::mint = nft_mint,
-            associated_token::authority = market_authority)]
+            constraint = collateral_account.mint == nft_mint.key() @ ErrorCode::InvalidParameter)]
     pub collateral_account: Box<Account<'info, TokenAccount>>,
 
     /// The account that will receive a portion of the borrower's collateral
@@ -116,21 +150,26 @@ pub struct ExecuteLiquidateBid<'info> {
     )]
     pub receiver_account: Box<Account<'info, TokenAccount>>,
 
+    /// The keeper's account receiving the reward for executing the liquidation
     #[account(
         init_if_needed,
         payer = payer,
         associated_token::mint = bid_mint,
-        associated_token::authority = payer
+        associated_token::authority = keeper
     )]
-    pub liquidation_fee_receiver: Box<Account<'info, TokenAccount>>,
+    pub keeper_reward_receiver: Box<Account<'info, TokenAccount>>,
 
     #[account(
         init_if_needed,
         payer = payer,
         associated_token::mint = bid_mint,
-        associated_token::authority = root_authority
+        associated_token::authority = fee_collector
     )]
-    pub leftovers_receiver: Box<Account<'info, TokenAccount>>,
+    pub protocol_fee_receiver: Box<Account<'info, TokenAccount>>,
+
+    /// Whoever executes the liquidation, which anyone may do for an
+    /// unhealthy obligation
+    pub keeper: Signer<'info>,
 
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -141,7 +180,27 @@ pub struct ExecuteLiquidateBid<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
-impl<'info> ExecuteLiquidateBid<'info> {
+/// The accounts a liquidation by bid settles the debt with, whichever way
+/// the liquidated nft is held
+pub struct LiquidationSettlement<'a, 'info> {
+    pub market: &'a AccountLoader<'info, Market>,
+    pub market_authority: &'a AccountInfo<'info>,
+    pub obligation: &'a AccountLoader<'info, Obligation>,
+    pub reserve: &'a AccountLoader<'info, Reserve>,
+    pub vault: &'a Account<'info, TokenAccount>,
+    pub insurance_fund: &'a AccountInfo<'info>,
+    pub loan_note_mint: &'a Account<'info, Mint>,
+    pub loan_account: &'a Account<'info, TokenAccount>,
+    pub bid: &'a mut Account<'info, Bid>,
+    pub bid_escrow: &'a Account<'info, TokenAccount>,
+    pub bid_escrow_authority: &'a AccountInfo<'info>,
+    pub keeper_reward_receiver: &'a Account<'info, TokenAccount>,
+    pub protocol_fee_receiver: &'a Account<'info, TokenAccount>,
+    pub keeper: &'a Signer<'info>,
+    pub token_program: &'a Program<'info, Token>,
+}
+
+impl<'a, 'info> LiquidationSettlement<'a, 'info> {
     fn note_burn_context(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
         CpiContext::new(self.token_program.to_account_info().clone(), Burn {
             from: self.loan_account.to_account_info(),
@@ -158,78 +217,269 @@ impl<'info> ExecuteLiquidateBid<'info> {
         })
     }
 
-    fn liquidation_fee_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+    fn keeper_reward_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         CpiContext::new(self.token_program.to_account_info().clone(), Transfer {
             from: self.bid_escrow.to_account_info(),
-            to: self.liquidation_fee_receiver.as_ref().to_account_info(),
+            to: self.keeper_reward_receiver.to_account_info(),
             authority: self.bid_escrow_authority.clone(),
         })
     }
 
-    fn leftovers_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+    fn protocol_fee_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         CpiContext::new(self.token_program.to_account_info().clone(), Transfer {
             from: self.bid_escrow.to_account_info(),
-            to: self.leftovers_receiver.as_ref().to_account_info(),
+            to: self.protocol_fee_receiver.to_account_info(),
             authority: self.bid_escrow_authority.clone(),
         })        
     }
 
-    fn transfer_nft_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+    fn insurance_fee_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         CpiContext::new(self.token_program.to_account_info().clone(), Transfer {
-            from: self.collateral_account.to_account_info(),
-            to: self.receiver_account.to_account_info(),
+            from: self.bid_escrow.to_account_info(),
+            to: self.insurance_fund.clone(),
+            authority: self.bid_escrow_authority.clone(),
+        })
+    }
+
+    fn insurance_claim_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(self.token_program.to_account_info().clone(), Transfer {
+            from: self.insurance_fund.clone(),
+            to: self.vault.to_account_info(),
             authority: self.market_authority.clone(),
         })
     }
 }
 
-pub fn handler(ctx: Context<ExecuteLiquidateBid>, _bump: ExecuteLiquidateBidBumps) -> Result<()> {
-    execute_liquidation(ctx.accounts)?;
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteLiquidateBid<'info>>,
+    _bump: ExecuteLiquidateBidBumps,
+) -> Result<()> {
+    liquidate_escrowed(ctx.accounts, ctx.remaining_accounts, false)?;
+
+    Ok(())
+}
+
+/// Liquidate an obligation with a bid that can't cover its debt, covering
+/// the shortfall from the insurance fund and writing off the rest. Only the
+/// market's solvent executor may do this.
+pub fn bad_debt_handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteLiquidateBid<'info>>,
+    _bump: ExecuteLiquidateBidBumps,
+) -> Result<()> {
+    if !ctx.accounts.roles.has_role(MarketRole::SolventExecutor, ctx.accounts.keeper.key) {
+        return Err(ErrorCode::Unauthorized.into());
+    }
+
+    liquidate_escrowed(ctx.accounts, ctx.remaining_accounts, true)?;
 
     Ok(())
 }
 
-fn execute_liquidation(accounts: &mut ExecuteLiquidateBid) -> Result<()> {
+/// A programmable NFT is moved with Token Metadata, which takes its accounts
+/// as the trailing `nft_accounts`: the metadata, edition, collateral and
+/// receiver token records, Token Metadata program, instructions sysvar,
+/// authorization rules program and rule set. An NFT frozen in the
+/// borrower's wallet takes its edition and the Token Metadata program.
+/// Plain escrowed NFTs take none.
+fn liquidate_escrowed<'info>(
+    accounts: &mut ExecuteLiquidateBid<'info>,
+    nft_accounts: &[AccountInfo<'info>],
+    allow_shortfall: bool,
+) -> Result<()> {
+    let settlement = LiquidationSettlement {
+        market: &accounts.market,
+        market_authority: &accounts.market_authority,
+        obligation: &accounts.obligation,
+        reserve: &accounts.reserve,
+        vault: &accounts.vault,
+        insurance_fund: &accounts.insurance_fund,
+        loan_note_mint: &accounts.loan_note_mint,
+        loan_account: &accounts.loan_account,
+        bid: &mut accounts.bid,
+        bid_escrow: &accounts.bid_escrow,
+        bid_escrow_authority: &accounts.bid_escrow_authority,
+        keeper_reward_receiver: &accounts.keeper_reward_receiver,
+        protocol_fee_receiver: &accounts.protocol_fee_receiver,
+        keeper: &accounts.keeper,
+        token_program: &accounts.token_program,
+    };
+    let obligation_owner = accounts.obligation.load()?.owner;
+
+    execute_liquidation(
+        settlement,
+        accounts.nft_mint.key(),
+        allow_shortfall,
+        |market, custody| {
+            let holds_nft = match custody {
+                NftCustody::Escrow => {
+                    accounts.collateral_account.key()
+                        == get_associated_token_address(accounts.market_authority.key, &accounts.nft_mint.key())
+                }
+                NftCustody::Frozen => {
+                    accounts.collateral_account.owner == obligation_owner
+                        && accounts.collateral_account.delegate == COption::Some(accounts.market_authority.key())
+                }
+                NftCustody::Compressed => false,
+            };
+            if !holds_nft {
+                return Err(ErrorCode::NftCustodyMismatch.into());
+            }
+
+            let transfer_nft_context = CpiContext::new(accounts.token_program.to_account_info(), Transfer {
+                from: accounts.collateral_account.to_account_info(),
+                to: accounts.receiver_account.to_account_info(),
+                authority: accounts.market_authority.clone(),
+            });
+            let close_collateral_context = CpiContext::new(accounts.token_program.to_account_info(), CloseAccount {
+                account: accounts.collateral_account.to_account_info(),
+                destination: accounts.obligation_owner.clone(),
+                authority: accounts.market_authority.clone(),
+            });
+
+            match nft_accounts {
+                [edition, token_metadata_program] if custody == NftCustody::Frozen => {
+                    DelegatedNft {
+                        delegate: &accounts.market_authority,
+                        token_account: accounts.collateral_account.as_ref().as_ref(),
+                        edition,
+                        mint: accounts.nft_mint.as_ref().as_ref(),
+                        token_program: accounts.token_program.as_ref(),
+                        token_metadata_program,
+                    }
+                    .thaw(&[&market.authority_seeds()])?;
+
+                    // still the delegate, so the market authority can move it once thawed
+                    token::transfer(
+                        transfer_nft_context.with_signer(&[&market.authority_seeds()]),
+                        1
+                    )?;
+                }
+                [] if custody == NftCustody::Escrow => {
+                    token::transfer(
+                        transfer_nft_context.with_signer(&[&market.authority_seeds()]),
+                        1
+                    )?;
+                    token::close_account(close_collateral_context.with_signer(&[&market.authority_seeds()]))?;
+                }
+                [metadata, edition, collateral_token_record, receiver_token_record, token_metadata_program, sysvar_instructions, authorization_rules_program, authorization_rules]
+                    if custody == NftCustody::Escrow => {
+                    PnftTransfer {
+                        token: accounts.collateral_account.as_ref().as_ref(),
+                        token_owner: &accounts.market_authority,
+                        destination: accounts.receiver_account.as_ref().as_ref(),
+                        destination_owner: &accounts.bidder,
+                        mint: accounts.nft_mint.as_ref().as_ref(),
+                        metadata,
+                        edition,
+                        owner_token_record: collateral_token_record,
+                        destination_token_record: receiver_token_record,
+                        authority: &accounts.market_authority,
+                        payer: accounts.payer.as_ref(),
+                        system_program: accounts.system_program.as_ref(),
+                        sysvar_instructions,
+                        token_program: accounts.token_program.as_ref(),
+                        associated_token_program: accounts.associated_token_program.as_ref(),
+                        token_metadata_program,
+                        authorization_rules_program,
+                        authorization_rules,
+                    }
+                    .invoke_signed(&[&market.authority_seeds()])?;
+
+                    // Token Metadata leaves the emptied account thawed, in which
+                    // case its rent can go back to the borrower
+                    accounts.collateral_account.reload()?;
+                    if !accounts.collateral_account.is_frozen() {
+                        token::close_account(close_collateral_context.with_signer(&[&market.authority_seeds()]))?;
+                    }
+                }
+                _ => {
+                    msg!("the trailing accounts don't match how the nft is held");
+                    return Err(ErrorCode::InvalidParameter.into());
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+/// Settle a liquidation by bid, with `transfer_collateral` moving the nft
+/// out of however the market holds it once the debt is paid
+pub fn execute_liquidation<'info>(
+    accounts: LiquidationSettlement<'_, 'info>,
+    collateral: Pubkey,
+    allow_shortfall: bool,
+    transfer_collateral: impl FnOnce(&Market, NftCustody) -> Result<()>,
+) -> Result<()> {
     // 0. Gather the needed data
     msg!("Gathering data");
     let market = accounts.market.load()?;
     let mut reserve = accounts.reserve.load_mut()?;
     let mut obligation = accounts.obligation.load_mut()?;
     let clock = Clock::get().unwrap();
-    let bid = accounts.bid.as_ref();
+    let bid = &*accounts.bid;
+    let bid_authority_seeds = bid.authority_seeds();
 
     let market_reserves = market.reserves();
     let market_oracle = market.market_oracle();
-    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle);
+    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle)?;
 
     // preliquidation checks
+    market.verify_ability_liquidate(clock.unix_timestamp)?;
+    reserve.verify_ability_liquidate(clock.unix_timestamp)?;
+
     if reserve.token_mint != bid.bid_mint {
         return Err(ErrorCode::BidMintMismatch.into());
     }
 
+    bid.verify_executable(
+        clock.unix_timestamp,
+        &accounts.obligation.key(),
+        &collateral,
+    )?;
+
+    let custody = obligation.nft_custody(&collateral)?;
+
     // 1. Verify the obligation is unhealthy
-    if obligation.is_healthy(market_reserves, clock.slot) {
+    if obligation.is_healthy(market_reserves, clock.slot)? {
         return Err(ErrorCode::ObligationHealthy.into());
     }
 
     msg!("Determining the amount of collateral");
     // 3. Determine the amount of collateral to be liquidated
     let loan_account = &accounts.loan_account;
-    let reserve_info = market_reserves.get_cached(reserve.index, clock.slot);
-    let override_authority = accounts.payer.key.key() == crate::ROOT_AUTHORITY;
-    let bid_limit = token::accessor::amount(&accounts.bid_escrow.to_account_info())?;
+    let reserve_info = market_reserves.get_cached(reserve.index, clock.slot)?;
+    let escrow_balance = token::accessor::amount(&accounts.bid_escrow.to_account_info())?;
     let payoff_notes = token::accessor::amount(&loan_account.to_account_info())?;
     let payoff_tokens = std::cmp::min(
         reserve_info.loan_notes_to_tokens(payoff_notes, Rounding::Up),
-        reserve.unwrap_outstanding_debt(clock.slot).as_u64(0)
+        reserve.outstanding_debt(clock.slot)?.as_u64(0)
     );
 
     if payoff_notes == 0 {
         return Err(ErrorCode::InvalidParameter.into());
     }
-    if bid_limit < payoff_tokens && !override_authority {
-        return Err(ErrorCode::LiquidationLowCollateral.into());
+    if reserve_info.price == Number::ZERO {
+        return Err(ErrorCode::InvalidOracle.into());
     }
+
+    // the NFT's floor price, in the bid mint's tokens
+    let floor_price = (market_oracle.price / reserve_info.price).as_u64(reserve.exponent);
+    let price = reserve.liquidation_price(payoff_tokens)?;
+    let bid_limit = bid.limit(escrow_balance, floor_price);
+
+    // When the bid can't cover the debt, a bad debt liquidation takes all the
+    // bid can pay towards the debt, and the rest is a shortfall.
+    let (clearing_price, split, shortfall) = if allow_shortfall && bid_limit < price.total()? {
+        let repaid = std::cmp::min(bid_limit, payoff_tokens);
+        let split = LiquidationSplit::new(repaid, repaid, 0, 0, 0)?;
+
+        (repaid, split, payoff_tokens - repaid)
+    } else {
+        let clearing_price = bid.clearing_price(escrow_balance, floor_price, price.total()?)?;
+        price.verify(clearing_price)?;
+
+        (clearing_price, price, 0)
+    };
     msg!("Burning");
     // 4. Burn the debt that's being repaid
     token::burn(
@@ -240,49 +490,90 @@ fn execute_liquidation(accounts: &mut ExecuteLiquidateBid) -> Result<()> {
     msg!("Transfering");
     // 5. Transfer the payment tokens to the reserve's vault
     token::transfer(
-        accounts.transfer_context().with_signer(&[&bid.authority_seeds()]),
-        payoff_tokens
+        accounts.transfer_context().with_signer(&[&bid_authority_seeds]),
+        split.repay
     )?;
 
-    // Pay the liquidator a small bonus for their efforts
-    let liquidation_fee = Number::from_bps(reserve.config.liquidation_premium);
-    let leftovers = bid.bid_limit - payoff_tokens;
-    let liquidation_fee_tokens = leftovers * liquidation_fee.as_u64(0);
+    // Pay the keeper for executing the liquidation
     token::transfer(
-        accounts.liquidation_fee_transfer_context().with_signer(&[&bid.authority_seeds()]),
-        liquidation_fee_tokens
+        accounts.keeper_reward_transfer_context().with_signer(&[&bid_authority_seeds]),
+        split.premium
     )?;
 
-    // 6. Send the rest of the collateral to the original nft owner
-    let leftovers_after_fees = leftovers - liquidation_fee_tokens;
+    // 6. Pay the protocol its cut, leaving anything else with the bidder
     token::transfer(
-        accounts.leftovers_transfer_context().with_signer(&[&bid.authority_seeds()]),
-        leftovers_after_fees
+        accounts.protocol_fee_transfer_context().with_signer(&[&bid_authority_seeds]),
+        split.protocol_fee
     )?;
 
+    if split.insurance_fee > 0 {
+        token::transfer(
+            accounts.insurance_fee_transfer_context().with_signer(&[&bid_authority_seeds]),
+            split.insurance_fee
+        )?;
+    }
+
+    // The insurance fund covers what it can of any shortfall
+    let insurance_fund_balance = match reserve.has_insurance_fund() {
+        true => token::accessor::amount(accounts.insurance_fund)?,
+        false => 0,
+    };
+    let cover = BadDebtCover::new(shortfall, insurance_fund_balance);
+    if cover.insured > 0 {
+        token::transfer(
+            accounts.insurance_claim_transfer_context().with_signer(&[&market.authority_seeds()]),
+            cover.insured
+        )?;
+    }
+
     // 7. remove the NFT from the obligation
-    obligation.unregister_nft(accounts.nft_mint.key())?;
+    obligation.unregister_nft(collateral)?;
 
-    // 8. send the NFT to the bidder's new account
-    token::transfer(accounts.transfer_nft_context().with_signer(&[&market.authority_seeds()]), 1)?;
+    // 8. send the NFT to the bidder
+    transfer_collateral(&market, custody)?;
 
     // 9. Keep the reserve's borrow tracking updated
-    reserve.repay(clock.slot, payoff_tokens, payoff_notes);
+    reserve.repay(clock.slot, split.repay, payoff_notes)?;
+    if shortfall > 0 {
+        reserve.cover_bad_debt(clock.slot, &cover)?;
+    }
 
     // 10. record the repayment in the obligation which is used to determine the obligation's health
     obligation.repay(&loan_account.key(), reserve.amount(payoff_notes))?;
 
-    // close the liquidator bid account
-    // accounts.bid.close()?;
-    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle);
-    if !obligation.is_healthy(market_reserves, clock.slot) {
+    // anything left in escrow stays with the bid until it's revoked or expires
+    accounts.bid.bid_limit = escrow_balance.saturating_sub(clearing_price);
+
+    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle)?;
+    if !obligation.is_healthy(market_reserves, clock.slot)? {
         return Err(ErrorCode::ObligationUnhealthy.into());
     }
 
-    emit!(ExecuteLiquidateEvent {
-        bid: accounts.bid.key(),
-        owner: obligation.owner.key()
-    });
+    if allow_shortfall {
+        emit!(BadDebtLiquidationEvent {
+            bid: accounts.bid.key(),
+            obligation: accounts.obligation.key(),
+            owner: obligation.owner.key(),
+            executor: accounts.keeper.key(),
+            repaid: split.repay,
+            shortfall,
+        });
+
+        if shortfall > 0 {
+            emit!(BadDebtEvent {
+                reserve: accounts.reserve.key(),
+                obligation: accounts.obligation.key(),
+                debt: payoff_tokens,
+                insured: cover.insured,
+                written_off: cover.written_off,
+            });
+        }
+    } else {
+        emit!(ExecuteLiquidateEvent {
+            bid: accounts.bid.key(),
+            owner: obligation.owner.key()
+        });
+    }
 
     Ok(())
 }
\ No newline at end of file
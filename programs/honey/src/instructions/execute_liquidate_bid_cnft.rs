@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::cnft::{
+    account_compression_program, bubblegum_program, noop_program, BubblegumTransfer,
+    CompressedNft,
+};
+use crate::errors::ErrorCode;
+use crate::instructions::{execute_liquidation, LiquidationSettlement};
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct ExecuteLiquidateBidCNFT<'info> {
+    /// The relevant market this liquidation is for
+    #[account(has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The obligation with debt to be repaid
+    #[account(mut, 
+        has_one = market, 
+        constraint = obligation.load().unwrap().has_loan_custody(&loan_account.key()),
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    #[account(mut,
+        has_one = market,
+        has_one = vault,
+        has_one = loan_note_mint)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// The reserve's vault where the payment will be transferred to
+    #[account(mut)]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// The reserve's insurance fund, which receives a share of the protocol
+    /// fee and covers any shortfall. Until the reserve has a fund, this can
+    /// be any writable account, such as the vault.
+    /// CHECK: checked against the reserve, and only used once it has a fund
+    #[account(mut,
+              constraint = reserve.load()?.is_insurance_fund(insurance_fund.key) @ ErrorCode::InvalidParameter)]
+    pub insurance_fund: AccountInfo<'info>,
+
+    /// The mint for the debt/loan notes
+    #[account(mut)]
+    pub loan_note_mint: Box<Account<'info, Mint>>,
+
+    /// The account that holds the borrower's debt balance
+    #[account(mut)]
+    pub loan_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut,
+        seeds = [
+            b"bid".as_ref(),
+            market.key().as_ref(),
+            bidder.key.as_ref(),
+        ],
+        bump,
+        has_one = bidder,
+        has_one = bid_mint,
+        has_one = bid_escrow_authority
+    )]
+    pub bid: Box<Account<'info, Bid>>,
+
+    /// The bidder, which becomes the owner of the compressed nft
+    #[account(mut)]
+    /// CHECK: bidder checked against bid
+    pub bidder: AccountInfo<'info>,
+
+    /// The protocol fee would be burned into an account of the default
+    /// key, so liquidations wait until a fee collector is assigned
+    #[account(has_one = market,
+              has_one = fee_collector,
+              constraint = roles.holder(MarketRole::FeeCollector) != Pubkey::default() @ ErrorCode::FeeCollectorUnassigned)]
+    pub roles: Box<Account<'info, MarketRoles>>,
+
+    #[account(mut)]
+    /// CHECK: fee collector checked against the market roles
+    pub fee_collector: AccountInfo<'info>,
+
+    pub bid_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut,
+        seeds = [
+            b"escrow".as_ref(),
+            market.key().as_ref(),
+            bidder.key.as_ref()
+        ],
+        bump)]
+    pub bid_escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: bid_escrow_authority checked against bid
+    pub bid_escrow_authority: AccountInfo<'info>,
+
+    /// The keeper's account receiving the reward for executing the liquidation
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = bid_mint,
+        associated_token::authority = keeper
+    )]
+    pub keeper_reward_receiver: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = bid_mint,
+        associated_token::authority = fee_collector
+    )]
+    pub protocol_fee_receiver: Box<Account<'info, TokenAccount>>,
+
+    /// Whoever executes the liquidation, which anyone may do for an
+    /// unhealthy obligation
+    pub keeper: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Bubblegum checks the proof against this tree
+    #[account(mut)]
+    pub merkle_tree: AccountInfo<'info>,
+
+    /// CHECK: checked against the tree's Bubblegum authority
+    pub tree_authority: AccountInfo<'info>,
+
+    /// CHECK: must be the noop program
+    #[account(address = noop_program::ID)]
+    pub log_wrapper: AccountInfo<'info>,
+
+    /// CHECK: must be the account compression program
+    #[account(address = account_compression_program::ID)]
+    pub compression_program: AccountInfo<'info>,
+
+    /// CHECK: must be the Bubblegum program
+    #[account(address = bubblegum_program::ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteLiquidateBidCNFT<'info>>,
+    nft: CompressedNft,
+) -> Result<()> {
+    liquidate_compressed(ctx.accounts, ctx.remaining_accounts, &nft, false)
+}
+
+/// Liquidate an obligation holding a compressed nft with a bid that can't
+/// cover its debt. Only the market's solvent executor may do this.
+pub fn compressed_bad_debt_handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteLiquidateBidCNFT<'info>>,
+    nft: CompressedNft,
+) -> Result<()> {
+    if !ctx.accounts.roles.has_role(MarketRole::SolventExecutor, ctx.accounts.keeper.key) {
+        return Err(ErrorCode::Unauthorized.into());
+    }
+
+    liquidate_compressed(ctx.accounts, ctx.remaining_accounts, &nft, true)
+}
+
+/// Settle the liquidation like any other, then hand the leaf to the bidder.
+/// The proof nodes are the trailing accounts.
+fn liquidate_compressed<'info>(
+    accounts: &mut ExecuteLiquidateBidCNFT<'info>,
+    proof: &[AccountInfo<'info>],
+    nft: &CompressedNft,
+    allow_shortfall: bool,
+) -> Result<()> {
+    let asset_id = nft.asset_id(accounts.merkle_tree.key);
+    let settlement = LiquidationSettlement {
+        market: &accounts.market,
+        market_authority: &accounts.market_authority,
+        obligation: &accounts.obligation,
+        reserve: &accounts.reserve,
+        vault: &accounts.vault,
+        insurance_fund: &accounts.insurance_fund,
+        loan_note_mint: &accounts.loan_note_mint,
+        loan_account: &accounts.loan_account,
+        bid: &mut accounts.bid,
+        bid_escrow: &accounts.bid_escrow,
+        bid_escrow_authority: &accounts.bid_escrow_authority,
+        keeper_reward_receiver: &accounts.keeper_reward_receiver,
+        protocol_fee_receiver: &accounts.protocol_fee_receiver,
+        keeper: &accounts.keeper,
+        token_program: &accounts.token_program,
+    };
+
+    execute_liquidation(settlement, asset_id, allow_shortfall, |market, custody| {
+        if custody != NftCustody::Compressed {
+            return Err(ErrorCode::NftCustodyMismatch.into());
+        }
+
+        BubblegumTransfer {
+            tree_authority: &accounts.tree_authority,
+            leaf_owner: &accounts.market_authority,
+            leaf_delegate: &accounts.market_authority,
+            new_leaf_owner: &accounts.bidder,
+            merkle_tree: &accounts.merkle_tree,
+            log_wrapper: &accounts.log_wrapper,
+            compression_program: &accounts.compression_program,
+            system_program: accounts.system_program.as_ref(),
+            bubblegum_program: &accounts.bubblegum_program,
+            proof,
+        }
+        .invoke_signed(nft, &[&market.authority_seeds()])
+    })
+}
@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, Mint, Token, TokenAccount, Transfer};
+
+use crate::{instructions::refresh_reserve_if_stale, state::*};
+
+#[event]
+pub struct FillWithdrawalEvent {
+    depositor: Pubkey,
+    reserve: Pubkey,
+    notes: u64,
+    tokens: u64,
+    notes_remaining: u64,
+}
+
+#[derive(Accounts)]
+pub struct FillWithdrawal<'info> {
+    /// The relevant market this withdraw is for
+    #[account(mut, has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The reserve being withdrawn from
+    #[account(mut,
+              has_one = market,
+              has_one = vault,
+              has_one = deposit_note_mint)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// The reserve's vault where the withdrawn tokens will be transferred from
+    #[account(mut)]
+    pub vault: Account<'info, TokenAccount>,
+
+    /// The mint for the deposit notes
+    #[account(mut)]
+    pub deposit_note_mint: Account<'info, Mint>,
+
+    /// The request being filled
+    #[account(mut,
+              seeds = [
+                  b"withdrawal".as_ref(),
+                  reserve.key().as_ref(),
+                  depositor.key.as_ref()
+              ],
+              bump = withdrawal_request.bump,
+              has_one = reserve,
+              has_one = depositor,
+              has_one = receiver)]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    /// The user that queued the withdrawal
+    /// CHECK: checked against the withdrawal request
+    pub depositor: AccountInfo<'info>,
+
+    /// The account that stores the deposit notes
+    #[account(mut,
+        seeds = [
+            b"deposits".as_ref(),
+            reserve.key().as_ref(),
+            depositor.key.as_ref()
+        ],
+        bump)]
+    pub deposit_note_account: Account<'info, TokenAccount>,
+
+    /// The token account where to transfer withdrawn tokens to
+    #[account(mut)]
+    pub receiver: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> FillWithdrawal<'info> {
+    fn transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.vault.to_account_info(),
+                to: self.receiver.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+
+    fn note_burn_context(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Burn {
+                mint: self.deposit_note_mint.to_account_info(),
+                authority: self.market_authority.clone(),
+                from: self.deposit_note_account.to_account_info(),
+            },
+        )
+    }
+}
+
+/// Withdraw as much of a queued withdrawal as the reserve has liquidity
+/// for, which anyone may do. A filled request is left for `close_withdrawal`
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, FillWithdrawal<'info>>) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    let mut reserve = ctx.accounts.reserve.load_mut()?;
+
+    refresh_reserve_if_stale(
+        &mut market,
+        &mut reserve,
+        &ctx.accounts.market_authority,
+        &ctx.accounts.token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
+    let clock = Clock::get()?;
+    let reserve_info = market.reserves().get_cached(reserve.index, clock.slot)?;
+
+    market.verify_ability_deposit_withdraw()?;
+    reserve.verify_ability_deposit_withdraw()?;
+
+    let liquidity = reserve.available_liquidity(ctx.accounts.vault.amount);
+    let note_balance = ctx.accounts.deposit_note_account.amount;
+    let (note_amount, token_amount) =
+        ctx.accounts
+            .withdrawal_request
+            .fill(reserve_info, liquidity, note_balance)?;
+
+    reserve.withdraw(token_amount, note_amount)?;
+
+    token::transfer(
+        ctx.accounts
+            .transfer_context()
+            .with_signer(&[&market.authority_seeds()]),
+        token_amount,
+    )?;
+
+    token::burn(
+        ctx.accounts
+            .note_burn_context()
+            .with_signer(&[&market.authority_seeds()]),
+        note_amount,
+    )?;
+
+    let request = &ctx.accounts.withdrawal_request;
+    emit!(FillWithdrawalEvent {
+        depositor: request.depositor,
+        reserve: request.reserve,
+        notes: note_amount,
+        tokens: token_amount,
+        notes_remaining: request.notes_remaining,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,236 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_lang::InstructionData;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use solana_program::instruction::Instruction;
+use solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+
+use crate::errors::ErrorCode;
+use crate::state::*;
+
+/// The position of the reserve in the accounts of `flash_repay`
+const FLASH_REPAY_RESERVE_INDEX: usize = 2;
+
+#[event]
+pub struct FlashBorrowEvent {
+    reserve: Pubkey,
+    borrower: Pubkey,
+    amount: u64,
+}
+
+#[derive(Accounts)]
+pub struct FlashBorrow<'info> {
+    /// The relevant market this flash loan is from
+    #[account(has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The reserve being borrowed from
+    #[account(mut,
+              has_one = market,
+              has_one = vault)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// The reserve's vault where the borrowed tokens will be transferred from
+    #[account(mut)]
+    pub vault: Account<'info, TokenAccount>,
+
+    /// The token account that the borrowed funds will be transferred to
+    #[account(mut,
+        constraint = receiver_account.key() != vault.key())]
+    pub receiver_account: Account<'info, TokenAccount>,
+
+    /// The user/authority that is borrowing
+    pub borrower: Signer<'info>,
+
+    /// The instructions in the transaction, to check the loan is repaid
+    /// CHECK: address is the instructions sysvar
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> FlashBorrow<'info> {
+    fn transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.vault.to_account_info(),
+                to: self.receiver_account.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+
+    /// Verify the transaction repays the flash loan in a later instruction
+    fn verify_repaid_later(&self) -> Result<()> {
+        let instructions = &self.instructions;
+        let current_index = load_current_index_checked(instructions)? as usize;
+
+        verify_repaid_later(current_index, &self.reserve.key(), |index| {
+            load_instruction_at_checked(index, instructions)
+        })
+    }
+}
+
+/// Check that the instruction at `current_index` was issued by the transaction
+/// itself, and that a `flash_repay` for `reserve` follows it
+fn verify_repaid_later(
+    current_index: usize,
+    reserve: &Pubkey,
+    load_instruction: impl Fn(usize) -> std::result::Result<Instruction, ProgramError>,
+) -> Result<()> {
+    let current = load_instruction(current_index)?;
+
+    // the loan must be taken by the transaction itself, since a repay
+    // inside the calling program can't be seen here
+    if current.program_id != crate::ID {
+        return err!(ErrorCode::FlashLoanNotRepaid);
+    }
+
+    // flash_repay takes no arguments, so its data is just the discriminator
+    let repay_data = crate::instruction::FlashRepay {}.data();
+
+    let mut index = current_index + 1;
+    while let Ok(ix) = load_instruction(index) {
+        let is_repay = ix.program_id == crate::ID
+            && ix.data == repay_data
+            && ix
+                .accounts
+                .get(FLASH_REPAY_RESERVE_INDEX)
+                .map_or(false, |meta| meta.pubkey == *reserve);
+
+        if is_repay {
+            return Ok(());
+        }
+
+        index += 1;
+    }
+
+    err!(ErrorCode::FlashLoanNotRepaid)
+}
+
+/// Borrow tokens from a reserve, to be repaid with a fee by `flash_repay`
+/// later in the same transaction
+pub fn handler(ctx: Context<FlashBorrow>, amount: u64) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    let mut reserve = ctx.accounts.reserve.load_mut()?;
+
+    market.verify_ability_borrow()?;
+    reserve.verify_ability_borrow()?;
+
+    if amount > reserve.available_liquidity(ctx.accounts.vault.amount) {
+        return err!(ErrorCode::InsufficientLiquidity);
+    }
+
+    ctx.accounts.verify_repaid_later()?;
+    reserve.flash_borrow(amount)?;
+
+    token::transfer(
+        ctx.accounts
+            .transfer_context()
+            .with_signer(&[&market.authority_seeds()]),
+        amount,
+    )?;
+
+    emit!(FlashBorrowEvent {
+        reserve: ctx.accounts.reserve.key(),
+        borrower: ctx.accounts.borrower.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::instruction::AccountMeta;
+
+    fn flash_borrow_ix() -> Instruction {
+        Instruction::new_with_bytes(crate::ID, &crate::instruction::FlashBorrow { amount: 1 }.data(), vec![])
+    }
+
+    fn flash_repay_ix(reserve: Pubkey) -> Instruction {
+        let mut accounts: Vec<AccountMeta> = (0..6)
+            .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+            .collect();
+        accounts[FLASH_REPAY_RESERVE_INDEX] = AccountMeta::new(reserve, false);
+
+        Instruction::new_with_bytes(crate::ID, &crate::instruction::FlashRepay {}.data(), accounts)
+    }
+
+    fn verify(instructions: &[Instruction], current_index: usize, reserve: &Pubkey) -> Result<()> {
+        verify_repaid_later(current_index, reserve, |index| {
+            instructions
+                .get(index)
+                .cloned()
+                .ok_or(ProgramError::InvalidArgument)
+        })
+    }
+
+    #[test]
+    fn accepts_later_repay() {
+        let reserve = Pubkey::new_unique();
+        let other = Instruction::new_with_bytes(Pubkey::new_unique(), &[1, 2, 3], vec![]);
+        let instructions = [flash_borrow_ix(), other, flash_repay_ix(reserve)];
+
+        assert!(verify(&instructions, 0, &reserve).is_ok());
+    }
+
+    #[test]
+    fn rejects_missing_repay() {
+        let reserve = Pubkey::new_unique();
+        let instructions = [flash_borrow_ix()];
+
+        assert_eq!(
+            crate::test_utils::error_code(verify(&instructions, 0, &reserve).unwrap_err()),
+            ErrorCode::FlashLoanNotRepaid.into()
+        );
+    }
+
+    #[test]
+    fn rejects_repay_before_borrow() {
+        let reserve = Pubkey::new_unique();
+        let instructions = [flash_repay_ix(reserve), flash_borrow_ix()];
+
+        assert!(verify(&instructions, 1, &reserve).is_err());
+    }
+
+    #[test]
+    fn rejects_repay_to_wrong_reserve() {
+        let reserve = Pubkey::new_unique();
+        let instructions = [flash_borrow_ix(), flash_repay_ix(Pubkey::new_unique())];
+
+        assert!(verify(&instructions, 0, &reserve).is_err());
+    }
+
+    #[test]
+    fn rejects_borrow_through_cpi() {
+        let reserve = Pubkey::new_unique();
+        let caller = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+        let instructions = [caller, flash_repay_ix(reserve)];
+
+        assert!(verify(&instructions, 0, &reserve).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, Transfer};
+
+use crate::errors::ErrorCode;
+use crate::instructions::refresh_reserve_if_stale;
+use crate::state::*;
+
+#[event]
+pub struct FlashRepayEvent {
+    reserve: Pubkey,
+    amount: u64,
+    fee: u64,
+}
+
+#[derive(Accounts)]
+pub struct FlashRepay<'info> {
+    /// The relevant market this flash loan is from
+    #[account(mut, has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The reserve the flash loan is from, which `flash_borrow` expects
+    /// to be the third account
+    #[account(mut,
+              has_one = market,
+              has_one = vault)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// The reserve's vault where the payment will be transferred to
+    /// CHECK: checked against the reserve
+    #[account(mut)]
+    pub vault: AccountInfo<'info>,
+
+    /// The token account that the payment funds will be transferred from
+    /// CHECK: the token program checks the payer owns the account
+    #[account(mut)]
+    pub payer_account: AccountInfo<'info>,
+
+    /// The account repaying the loan
+    pub payer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> FlashRepay<'info> {
+    fn transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.payer_account.clone(),
+                to: self.vault.clone(),
+                authority: self.payer.to_account_info(),
+            },
+        )
+    }
+}
+
+/// Repay a reserve's outstanding flash loan along with its fee
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, FlashRepay<'info>>) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    let mut reserve = ctx.accounts.reserve.load_mut()?;
+
+    refresh_reserve_if_stale(
+        &mut market,
+        &mut reserve,
+        &ctx.accounts.market_authority,
+        &ctx.accounts.token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
+    let clock = Clock::get()?;
+    let (amount, fee) = reserve.flash_repay()?;
+
+    let total = amount.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+
+    token::transfer(ctx.accounts.transfer_context(), total)?;
+    reserve.add_uncollected_fees(clock.slot, fee)?;
+
+    emit!(FlashRepayEvent {
+        reserve: ctx.accounts.reserve.key(),
+        amount,
+        fee,
+    });
+
+    Ok(())
+}
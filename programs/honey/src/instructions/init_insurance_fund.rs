@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::errors::ErrorCode;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct InitializeInsuranceFund<'info> {
+    /// The market the reserve belongs to
+    #[account(has_one = owner,
+              has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account, which owns the fund
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The reserve the fund insures
+    #[account(mut,
+              has_one = market,
+              has_one = token_mint)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// The account to hold tokens set aside to cover bad debt
+    #[account(init,
+              seeds = [
+                  b"insurance-fund".as_ref(),
+                  reserve.key().as_ref()
+              ],
+              bump,
+              token::mint = token_mint,
+              token::authority = market_authority,
+              payer = owner)]
+    pub insurance_fund: Box<Account<'info, TokenAccount>>,
+
+    /// The mint for the token being stored in the reserve
+    pub token_mint: Box<Account<'info, Mint>>,
+
+    /// The market owner, which must sign to make this change to the market.
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Create the insurance fund for a reserve initialized before reserves had one
+pub fn handler(ctx: Context<InitializeInsuranceFund>) -> Result<()> {
+    let mut reserve = ctx.accounts.reserve.load_mut()?;
+
+    if reserve.insurance_fund != Pubkey::default() {
+        return Err(ErrorCode::InsuranceFundExists.into());
+    }
+
+    reserve.insurance_fund = ctx.accounts.insurance_fund.key();
+
+    Ok(())
+}
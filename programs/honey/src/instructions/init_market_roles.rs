@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct InitializeMarketRoles<'info> {
+    #[account(has_one = owner)]
+    pub market: AccountLoader<'info, Market>,
+
+    #[account(init,
+        seeds = [
+            b"roles".as_ref(),
+            market.key().as_ref(),
+        ],
+        bump,
+        space = 8 + std::mem::size_of::<MarketRoles>(),
+        payer = owner)]
+    pub roles: Account<'info, MarketRoles>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create the account holding the delegated roles for a market, with
+/// every role unassigned.
+pub fn handler(ctx: Context<InitializeMarketRoles>) -> Result<()> {
+    let roles = &mut ctx.accounts.roles;
+    roles.market = ctx.accounts.market.key();
+    roles.bump = *ctx.bumps.get("roles").unwrap();
+
+    Ok(())
+}
@@ -22,7 +22,6 @@ use anchor_spl::token::Token;
 use crate::state::*;
 
 #[derive(Accounts)]
-#[instruction(bump: u8)]
 pub struct InitializeObligation<'info> {
     /// The relevant market
     #[account(has_one = market_authority)]
@@ -37,14 +36,9 @@ pub struct InitializeObligation<'info> {
     pub borrower: Signer<'info>,
 
     /// The new account to track information about the borrower's loan,
-    /// such as the collateral put up.
+    /// such as the collateral put up. Its address isn't derived from the
+    /// borrower, since the obligation may later be transferred.
     #[account(init,
-              seeds = [
-                  b"obligation".as_ref(),
-                  market.key().as_ref(),
-                  borrower.key.as_ref()
-              ],
-              bump,
               space = 8 + std::mem::size_of::<Obligation>(),
               payer = borrower)]
     pub obligation: AccountLoader<'info, Obligation>,
@@ -54,7 +48,7 @@ pub struct InitializeObligation<'info> {
 }
 
 /// Initialize an account that tracks a portfolio of collateral deposits and loans.
-pub fn handler(ctx: Context<InitializeObligation>, _bump: u8) -> Result<()> {
+pub fn handler(ctx: Context<InitializeObligation>) -> Result<()> {
     let mut obligation = ctx.accounts.obligation.load_init()?;
 
     obligation.market = ctx.accounts.market.key();
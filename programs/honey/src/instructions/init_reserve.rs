@@ -30,6 +30,7 @@ pub struct InitReserveBumpSeeds {
     pub vault: u8,
     pub fee_note_vault: u8,
     pub protocol_fee_note_vault: u8,
+    pub insurance_fund: u8,
     pub deposit_note_mint: u8,
     pub loan_note_mint: u8
 }
@@ -104,6 +105,18 @@ pub struct InitializeReserve<'info> {
         )]
     pub protocol_fee_note_vault: Box<Account<'info, TokenAccount>>,
 
+    /// The account holding tokens set aside to cover bad debt
+    #[account(init,
+              seeds = [
+                  b"insurance-fund".as_ref(),
+                  reserve.key().as_ref()
+              ],
+              bump,
+              token::mint = token_mint,
+              token::authority = market_authority,
+              payer = owner)]
+    pub insurance_fund: Box<Account<'info, TokenAccount>>,
+
     /// The mint for the token being stored in this reserve.
     pub token_mint: Box<Account<'info, Mint>>,
 
@@ -153,6 +166,7 @@ impl<'info> InitializeReserve<'info> {
         reserve.vault = self.vault.key();
         reserve.fee_note_vault = self.fee_note_vault.key();
         reserve.protocol_fee_note_vault = self.protocol_fee_note_vault.key();
+        reserve.insurance_fund = self.insurance_fund.key();
 
         reserve.exponent = -(token_mint.decimals as i32);
         reserve.token_mint = token_mint.key();
@@ -177,6 +191,8 @@ pub fn handler(
     _bump: InitReserveBumpSeeds,
     config: ReserveConfig,
 ) -> Result<()> {
+    config.validate()?;
+
     // Initialize the reserve data
     ctx.accounts.register_with_market(config)?;
 
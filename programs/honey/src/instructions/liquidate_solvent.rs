@@ -18,6 +18,7 @@
 use anchor_lang::prelude::*;
 use crate::errors::ErrorCode;
 use crate::state::*;
+use crate::common::AmountBasis;
 use crate::{Amount, Rounding};
 use anchor_spl::token::{self, Burn, Mint, Token, TokenAccount};
 use jet_math::Number;
@@ -66,8 +67,11 @@ pub struct LiquidateSolvent<'info> {
 
     pub nft_mint: Account<'info, Mint>,
 
-    /// The admin/authority that has permission to execute solvent liquidation
-    #[cfg_attr(not(feature = "testing"), account(address = crate::ROOT_AUTHORITY))]
+    #[account(has_one = market,
+        constraint = roles.has_role(MarketRole::SolventExecutor, executor.key) @ ErrorCode::Unauthorized)]
+    pub roles: Account<'info, MarketRoles>,
+
+    /// The account holding the solvent executor role for the market
     #[account(mut)]
     pub executor: Signer<'info>,
 
@@ -95,18 +99,24 @@ pub fn handler(ctx: Context<LiquidateSolvent>, amount: Amount) -> Result<()> {
     let mut obligation = ctx.accounts.obligation.load_mut()?;
     let clock = Clock::get().unwrap();
 
+    market.verify_ability_liquidate(clock.unix_timestamp)?;
+    reserve.verify_ability_liquidate(clock.unix_timestamp)?;
+
     let market_reserves = market.reserves();
-    let reserve_info = market_reserves.get_cached(reserve.index, clock.slot);
+    let reserve_info = market_reserves.get_cached(reserve.index, clock.slot)?;
     let loan_account = &ctx.accounts.loan_account;
+    let basis = AmountBasis::Loan {
+        notes: token::accessor::amount(&loan_account.to_account_info())?,
+    };
     let new_oracle = &MarketOracle {
-        price: Number::from_decimal(amount.as_tokens(reserve_info, Rounding::Down), 0),
+        price: Number::from_decimal(amount.as_tokens(reserve_info, Rounding::Down, basis)?, 0),
     };
 
-    obligation.cache_calculations(market.reserves(), clock.slot, new_oracle);
+    obligation.cache_calculations(market.reserves(), clock.slot, new_oracle)?;
 
     // 1. Check that the obligation is unhealthy
     // this can liquidate any nft later add some rarity based stuff
-    if obligation.is_healthy(market_reserves, clock.slot) {
+    if obligation.is_healthy(market_reserves, clock.slot)? {
         return Err(ErrorCode::ObligationHealthy.into());
     }
 
@@ -114,7 +124,7 @@ pub fn handler(ctx: Context<LiquidateSolvent>, amount: Amount) -> Result<()> {
     let _nft_collateral_value = obligation.nft_collateral_value(new_oracle.price);
 
     // 3. Determine the amount of collateral to be liquidated
-    let _loan_value = obligation.loan_value(market.reserves(), clock.slot);
+    let _loan_value = obligation.loan_value(market.reserves(), clock.slot)?;
 
     // 4. unregister collateral and nft
     // let nft_mint = ctx.accounts.nft_mint.key();
@@ -123,14 +133,14 @@ pub fn handler(ctx: Context<LiquidateSolvent>, amount: Amount) -> Result<()> {
     // some portion of loan is repaid due to obligation
     market.verify_ability_repay()?;
 
-    let payoff_notes = amount.as_loan_notes(reserve_info, Rounding::Down)?;
+    let payoff_notes = amount.as_loan_notes(reserve_info, Rounding::Down, basis)?;
     let payoff_notes = std::cmp::min(
         payoff_notes,
         token::accessor::amount(&loan_account.to_account_info())?,
     );
     let payoff_tokens = std::cmp::min(
         reserve_info.loan_notes_to_tokens(payoff_notes, Rounding::Up),
-        reserve.unwrap_outstanding_debt(clock.slot).as_u64(0),
+        reserve.outstanding_debt(clock.slot)?.as_u64(0),
     );
     msg!(
         "payoff_notes after comparing outstanding debt{}",
@@ -147,7 +157,7 @@ pub fn handler(ctx: Context<LiquidateSolvent>, amount: Amount) -> Result<()> {
     )?;
 
     // Keep the reserve's borrow tracking updated
-    reserve.repay(clock.slot, payoff_tokens, payoff_notes);
+    reserve.repay(clock.slot, payoff_tokens, payoff_notes)?;
 
     msg!("recoriding repay");
     // 6. record the repayment in the obligation which is used to determine the obligation's health
@@ -0,0 +1,76 @@
+use crate::errors::ErrorCode;
+use crate::state::Bid;
+use crate::Market;
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+
+#[event]
+pub struct MigrateBidEvent {
+    bid: Pubkey,
+    bidder: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct MigrateBid<'info> {
+    pub market: AccountLoader<'info, Market>,
+
+    /// The bid still in the legacy layout, which is grown to fit a `Bid`
+    /// CHECK: the handler checks this is a legacy bid of the market's
+    #[account(mut,
+        seeds = [
+            b"bid".as_ref(),
+            market.key().as_ref(),
+            bidder.key.as_ref(),
+        ],
+        bump,
+        owner = crate::ID @ ErrorCode::InvalidParameter)]
+    pub bid: AccountInfo<'info>,
+
+    /// CHECK: only used to derive the bid's address
+    pub bidder: AccountInfo<'info>,
+
+    /// Pays the rent for the bid's extra space
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateBid<'info> {
+    fn rent_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.system_program.to_account_info(),
+            Transfer {
+                from: self.payer.to_account_info(),
+                to: self.bid.clone(),
+            },
+        )
+    }
+}
+
+/// Upgrade a bid placed before bids had an expiry, max price or target, so
+/// it can be executed, revoked or refunded again (permissionless)
+pub fn handler(ctx: Context<MigrateBid>) -> Result<()> {
+    let legacy = Bid::load_legacy(&ctx.accounts.bid.try_borrow_data()?)?;
+
+    if legacy.market != ctx.accounts.market.key() || legacy.bidder != ctx.accounts.bidder.key() {
+        return Err(ErrorCode::InvalidParameter.into());
+    }
+
+    let rent = Rent::get()?.minimum_balance(Bid::SPACE);
+    let shortfall = rent.saturating_sub(ctx.accounts.bid.lamports());
+    if shortfall > 0 {
+        system_program::transfer(ctx.accounts.rent_transfer_context(), shortfall)?;
+    }
+    ctx.accounts.bid.realloc(Bid::SPACE, true)?;
+
+    let bid = Bid::from(legacy);
+    bid.try_serialize(&mut &mut ctx.accounts.bid.try_borrow_mut_data()?[..])?;
+
+    emit!(MigrateBidEvent {
+        bid: ctx.accounts.bid.key(),
+        bidder: bid.bidder,
+    });
+
+    Ok(())
+}
@@ -15,48 +15,120 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+pub mod accept_market_owner;
+pub mod accept_obligation_transfer;
 pub mod borrow;
+pub mod cancel_market_owner;
+pub mod cancel_obligation_transfer;
+pub mod cancel_withdrawal;
+pub mod close_deposit_account;
+pub mod close_loan_account;
+pub mod close_obligation;
+pub mod close_reserve;
+pub mod close_withdrawal;
+pub mod deposit_cnft;
 pub mod deposit_nft;
+pub mod deposit_nft_escrowless;
+pub mod deposit_pnft;
 pub mod deposit_tokens;
+pub mod fill_withdrawal;
+pub mod flash_borrow;
+pub mod flash_repay;
 pub mod init_deposit_account;
+pub mod init_insurance_fund;
 pub mod init_loan_account;
 pub mod init_market;
+pub mod init_market_roles;
 pub mod init_obligation;
 pub mod init_reserve;
 pub mod liquidate_solvent;
+pub mod pause_market;
+pub mod pause_reserve;
+pub mod propose_market_owner;
+pub mod queue_withdrawal;
 pub mod withdraw_nft_solvent;
+pub mod refinance;
 pub mod refresh_reserve;
 pub mod repay;
+pub mod repay_and_withdraw_nft;
 pub mod set_market_flags;
-pub mod set_market_owner;
+pub mod set_market_role;
+pub mod set_obligation_delegate;
+pub mod set_reserve_cache_ttl;
+pub mod set_reserve_flags;
+pub mod swap_nft_collateral;
+pub mod transfer_obligation;
 pub mod update_reserve_config;
+pub mod withdraw_cnft;
 pub mod withdraw_nft;
+pub mod withdraw_pnft;
 pub mod withdraw_tokens;
+pub mod write_off_bad_debt;
 
 pub mod place_liquidate_bid;
 pub mod revoke_liquidate_bid;
+pub mod revoke_obligation_delegate;
+pub mod crank_expired_bid;
+pub mod migrate_bid;
 pub mod execute_liquidate_bid;
+pub mod execute_liquidate_bid_cnft;
 pub mod increase_liquidate_bid;
 
+pub use accept_market_owner::*;
+pub use accept_obligation_transfer::*;
 pub use borrow::*;
+pub use cancel_market_owner::*;
+pub use cancel_obligation_transfer::*;
+pub use cancel_withdrawal::*;
+pub use close_deposit_account::*;
+pub use close_loan_account::*;
+pub use close_obligation::*;
+pub use close_reserve::*;
+pub use close_withdrawal::*;
+pub use deposit_cnft::*;
 pub use deposit_nft::*;
+pub use deposit_nft_escrowless::*;
+pub use deposit_pnft::*;
 pub use deposit_tokens::*;
+pub use fill_withdrawal::*;
+pub use flash_borrow::*;
+pub use flash_repay::*;
 pub use init_deposit_account::*;
+pub use init_insurance_fund::*;
 pub use init_loan_account::*;
 pub use init_market::*;
+pub use init_market_roles::*;
 pub use init_obligation::*;
 pub use init_reserve::*;
 pub use liquidate_solvent::*;
+pub use pause_market::*;
+pub use pause_reserve::*;
+pub use propose_market_owner::*;
+pub use queue_withdrawal::*;
 pub use withdraw_nft_solvent::*;
+pub use refinance::*;
 pub use refresh_reserve::*;
 pub use repay::*;
+pub use repay_and_withdraw_nft::*;
 pub use set_market_flags::*;
-pub use set_market_owner::*;
+pub use set_market_role::*;
+pub use set_obligation_delegate::*;
+pub use set_reserve_cache_ttl::*;
+pub use set_reserve_flags::*;
+pub use swap_nft_collateral::*;
+pub use transfer_obligation::*;
 pub use update_reserve_config::*;
+pub use withdraw_cnft::*;
 pub use withdraw_nft::*;
+pub use withdraw_pnft::*;
 pub use withdraw_tokens::*;
+pub use write_off_bad_debt::*;
 
 pub use place_liquidate_bid::*;
 pub use revoke_liquidate_bid::*;
+pub use revoke_obligation_delegate::*;
+pub use crank_expired_bid::*;
+pub use migrate_bid::*;
 pub use execute_liquidate_bid::*;
+pub use execute_liquidate_bid_cnft::*;
 pub use increase_liquidate_bid::*;
\ No newline at end of file
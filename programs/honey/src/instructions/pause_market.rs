@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::errors::ErrorCode;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct PauseMarketEvent {
+    market: Pubkey,
+    authority: Pubkey,
+    flags: u64,
+}
+
+#[derive(Accounts)]
+pub struct PauseMarket<'info> {
+    #[account(mut)]
+    pub market: AccountLoader<'info, Market>,
+
+    #[account(has_one = market,
+        constraint = roles.is_pause_authority(&market.load()?.owner, authority.key) @ ErrorCode::Unauthorized)]
+    pub roles: Account<'info, MarketRoles>,
+
+    /// The market owner or guardian halting operations
+    pub authority: Signer<'info>,
+}
+
+/// Halt operations on a market, without resuming any already halted
+pub fn handler(ctx: Context<PauseMarket>, flags: u64) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    let flags = match MarketFlags::from_bits(flags) {
+        Some(f) => f,
+        None => return err!(ErrorCode::InvalidParameter),
+    };
+
+    market.pause(flags);
+
+    emit!(PauseMarketEvent {
+        market: ctx.accounts.market.key(),
+        authority: ctx.accounts.authority.key(),
+        flags: market.flags,
+    });
+
+    Ok(())
+}
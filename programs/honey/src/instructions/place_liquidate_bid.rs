@@ -10,6 +10,9 @@ pub struct PlaceBidEvent {
     bid: Pubkey,
     bidder: Pubkey,
     bid_limit: u64,
+    expires_at: i64,
+    max_price_bps: Option<u16>,
+    target: Option<Pubkey>,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -34,7 +37,7 @@ pub struct PlaceLiquidateBid<'info> {
             bidder.key.as_ref(),
         ],
         bump,
-        space = 8 + std::mem::size_of::<Bid>(),
+        space = Bid::SPACE,
         payer = bidder)]
     pub bid: Account<'info, Bid>,
 
@@ -84,6 +87,9 @@ pub fn handler(
     ctx: Context<PlaceLiquidateBid>,
     _bump: PlaceLiquidateBidBumps,
     bid_limit: u64,
+    expires_at: i64,
+    max_price_bps: Option<u16>,
+    target: Option<Pubkey>,
 ) -> Result<()> {
     let initial_seeds = &[ctx.accounts.bid_escrow.to_account_info().key.as_ref()];
     let (authority, authority_seed) = Pubkey::find_program_address(initial_seeds, ctx.program_id);
@@ -93,6 +99,10 @@ pub fn handler(
         return Err(ErrorCode::ObligationHealthy.into());
     }
 
+    if expires_at <= Clock::get()?.unix_timestamp || max_price_bps == Some(0) {
+        return Err(ErrorCode::InvalidParameter.into());
+    }
+
     let bid = &mut ctx.accounts.bid;
     bid.market = ctx.accounts.market.key();
     bid.bid_escrow = ctx.accounts.bid_escrow.key();
@@ -102,6 +112,9 @@ pub fn handler(
     bid.authority_seed = bid_escrow_address;
     bid.bidder = ctx.accounts.bidder.key();
     bid.bid_limit = bid_limit;
+    bid.expires_at = expires_at;
+    bid.max_price_bps = max_price_bps;
+    bid.target = target;
 
     token::transfer(ctx.accounts.transfer_context(), bid_limit)?;
 
@@ -109,6 +122,9 @@ pub fn handler(
         bid: ctx.accounts.bid.key(),
         bidder: ctx.accounts.bidder.key(),
         bid_limit,
+        expires_at,
+        max_price_bps,
+        target,
     });
 
     Ok(())
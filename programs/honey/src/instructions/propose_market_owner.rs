@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[event]
+pub struct ProposeMarketOwnerEvent {
+    market: Pubkey,
+    owner: Pubkey,
+    pending_owner: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct ProposeMarketOwner<'info> {
+    #[account(mut, has_one = owner)]
+    pub market: AccountLoader<'info, Market>,
+
+    pub owner: Signer<'info>,
+}
+
+/// Propose a new owner for a market, which takes effect once accepted
+pub fn handler(ctx: Context<ProposeMarketOwner>, new_owner: Pubkey) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.propose_owner(new_owner)?;
+
+    emit!(ProposeMarketOwnerEvent {
+        market: ctx.accounts.market.key(),
+        owner: market.owner,
+        pending_owner: new_owner,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::errors::ErrorCode;
+use crate::state::*;
+
+#[event]
+pub struct QueueWithdrawalEvent {
+    depositor: Pubkey,
+    reserve: Pubkey,
+    notes: u64,
+}
+
+#[derive(Accounts)]
+pub struct QueueWithdrawal<'info> {
+    /// The relevant market the deposit is in
+    pub market: AccountLoader<'info, Market>,
+
+    /// The reserve being withdrawn from
+    #[account(has_one = market)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// The user/authority that owns the deposit
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    /// The account that stores the deposit notes
+    #[account(seeds = [
+                  b"deposits".as_ref(),
+                  reserve.key().as_ref(),
+                  depositor.key.as_ref()
+              ],
+              bump)]
+    pub deposit_note_account: Account<'info, TokenAccount>,
+
+    /// The request tracking the queued withdrawal
+    #[account(init,
+              seeds = [
+                  b"withdrawal".as_ref(),
+                  reserve.key().as_ref(),
+                  depositor.key.as_ref()
+              ],
+              bump,
+              space = 8 + std::mem::size_of::<WithdrawalRequest>(),
+              payer = depositor)]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    /// The token account where to transfer withdrawn tokens to
+    #[account(constraint = receiver.mint == reserve.load()?.token_mint @ ErrorCode::InvalidParameter)]
+    pub receiver: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Queue a withdrawal of deposit notes to be filled as the reserve
+/// regains liquidity
+pub fn handler(ctx: Context<QueueWithdrawal>, notes: u64) -> Result<()> {
+    if notes == 0 || notes > ctx.accounts.deposit_note_account.amount {
+        return Err(ErrorCode::InvalidParameter.into());
+    }
+
+    let request = &mut ctx.accounts.withdrawal_request;
+    request.reserve = ctx.accounts.reserve.key();
+    request.depositor = ctx.accounts.depositor.key();
+    request.receiver = ctx.accounts.receiver.key();
+    request.notes_remaining = notes;
+    request.bump = *ctx.bumps.get("withdrawal_request").unwrap();
+
+    emit!(QueueWithdrawalEvent {
+        depositor: request.depositor,
+        reserve: request.reserve,
+        notes,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,290 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, Mint, MintTo, Token, TokenAccount, Transfer};
+
+use crate::common::Rounding;
+use crate::errors::ErrorCode;
+use crate::instructions::{refresh_reserve_if_stale, RESERVE_REFRESH_ACCOUNTS};
+use crate::state::*;
+
+#[event]
+pub struct RefinanceEvent {
+    obligation: Pubkey,
+    from_reserve: Pubkey,
+    to_reserve: Pubkey,
+    repaid: u64,
+    debt: u64,
+}
+
+#[derive(Accounts)]
+pub struct Refinance<'info> {
+    /// The relevant market the loans are in
+    #[account(mut, has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The obligation whose loan is being refinanced
+    #[account(mut,
+              has_one = market,
+              constraint = obligation.load()?.is_authorized(owner.key, ObligationPermissions::REFINANCE) @ ErrorCode::Unauthorized,
+              constraint = obligation.load()?.has_loan_custody(&from_loan_account.key()),
+              constraint = obligation.load()?.has_loan_custody(&to_loan_account.key()))]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The obligation owner, or a delegate allowed to refinance
+    pub owner: Signer<'info>,
+
+    /// The reserve the loan is repaid to
+    #[account(mut,
+              has_one = market,
+              constraint = from_reserve.key() != to_reserve.key() @ ErrorCode::InvalidParameter,
+              constraint = from_reserve.load()?.vault == from_vault.key() @ ErrorCode::InvalidParameter,
+              constraint = from_reserve.load()?.loan_note_mint == from_loan_note_mint.key() @ ErrorCode::InvalidParameter)]
+    pub from_reserve: AccountLoader<'info, Reserve>,
+
+    /// The vault of the reserve the loan is repaid to
+    #[account(mut)]
+    pub from_vault: Box<Account<'info, TokenAccount>>,
+
+    /// The loan note mint of the reserve the loan is repaid to
+    #[account(mut)]
+    pub from_loan_note_mint: Box<Account<'info, Mint>>,
+
+    /// The account holding the loan notes being repaid
+    #[account(mut)]
+    pub from_loan_account: Box<Account<'info, TokenAccount>>,
+
+    /// The reserve the new loan is borrowed from
+    #[account(mut,
+              has_one = market,
+              constraint = to_reserve.load()?.vault == to_vault.key() @ ErrorCode::InvalidParameter,
+              constraint = to_reserve.load()?.loan_note_mint == to_loan_note_mint.key() @ ErrorCode::InvalidParameter)]
+    pub to_reserve: AccountLoader<'info, Reserve>,
+
+    /// The vault of the reserve the new loan is borrowed from
+    #[account(mut)]
+    pub to_vault: Box<Account<'info, TokenAccount>>,
+
+    /// The loan note mint of the reserve the new loan is borrowed from
+    #[account(mut)]
+    pub to_loan_note_mint: Box<Account<'info, Mint>>,
+
+    /// The account receiving the new loan notes
+    #[account(mut)]
+    pub to_loan_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> Refinance<'info> {
+    fn note_burn_context(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Burn {
+                from: self.from_loan_account.to_account_info(),
+                mint: self.from_loan_note_mint.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+
+    fn note_mint_context(&self) -> CpiContext<'_, '_, '_, 'info, MintTo<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            MintTo {
+                to: self.to_loan_account.to_account_info(),
+                mint: self.to_loan_note_mint.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+
+    fn transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.to_vault.to_account_info(),
+                to: self.from_vault.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+}
+
+/// Split the trailing accounts of `refinance` into the refresh accounts of
+/// the old reserve and those of the new one.
+///
+/// The trailing accounts are one of:
+/// * none, when both reserves are fresh
+/// * the 4 refresh accounts of the old reserve, when only it may be stale
+/// * the 4 refresh accounts of the old reserve followed by the 4 of the new one
+fn split_refresh_accounts<T>(refresh_accounts: &[T]) -> Result<(&[T], &[T])> {
+    match refresh_accounts.len() {
+        0 | RESERVE_REFRESH_ACCOUNTS => Ok((refresh_accounts, &[])),
+        len if len == 2 * RESERVE_REFRESH_ACCOUNTS => {
+            Ok(refresh_accounts.split_at(RESERVE_REFRESH_ACCOUNTS))
+        }
+        len => {
+            msg!(
+                "expected 0, {} or {} refresh accounts, got {}",
+                RESERVE_REFRESH_ACCOUNTS,
+                2 * RESERVE_REFRESH_ACCOUNTS,
+                len
+            );
+            err!(ErrorCode::InvalidParameter)
+        }
+    }
+}
+
+/// Repay the whole of a loan by borrowing the same tokens from another
+/// reserve, leaving the obligation's collateral in place.
+///
+/// The refresh accounts of the old reserve, then those of the new one, may be
+/// passed as trailing accounts, as laid out by `split_refresh_accounts`.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, Refinance<'info>>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let mut market = accounts.market.load_mut()?;
+    let mut from_reserve = accounts.from_reserve.load_mut()?;
+    let mut to_reserve = accounts.to_reserve.load_mut()?;
+
+    let (from_refresh, to_refresh) = split_refresh_accounts(ctx.remaining_accounts)?;
+    for (reserve, refresh) in [(&mut from_reserve, from_refresh), (&mut to_reserve, to_refresh)] {
+        refresh_reserve_if_stale(
+            &mut market,
+            reserve,
+            &accounts.market_authority,
+            &accounts.token_program.to_account_info(),
+            refresh,
+        )?;
+    }
+
+    let clock = Clock::get()?;
+    let from_info = market.reserves().get_cached(from_reserve.index, clock.slot)?;
+    let to_info = market.reserves().get_cached(to_reserve.index, clock.slot)?;
+
+    market.verify_ability_repay()?;
+    market.verify_ability_borrow()?;
+    from_reserve.verify_ability_repay()?;
+    to_reserve.verify_ability_borrow()?;
+
+    if from_reserve.token_mint != to_reserve.token_mint {
+        msg!("a loan can only be refinanced with the same token");
+        return err!(ErrorCode::InvalidParameter);
+    }
+
+    // Repay the whole of the old loan
+    let payoff_notes = accounts.from_loan_account.amount;
+    let payoff_tokens = std::cmp::min(
+        from_info.loan_notes_to_tokens(payoff_notes, Rounding::Up),
+        from_reserve.outstanding_debt(clock.slot)?.as_u64(0),
+    );
+
+    // Borrow what it takes to repay it, plus fees, from the new reserve
+    let fees = to_reserve.borrow_fee(payoff_tokens);
+    let protocol_fees = to_reserve.protocol_fee(payoff_tokens);
+    let debt = to_reserve.borrow_debt(payoff_tokens);
+    let new_notes = to_info.loan_notes_from_tokens(debt, Rounding::Up);
+    let obligation_debt = to_info
+        .loan_notes_to_tokens(accounts.to_loan_account.amount, Rounding::Up)
+        .checked_add(debt)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    if payoff_tokens > to_reserve.available_liquidity(accounts.to_vault.amount) {
+        return err!(ErrorCode::InsufficientLiquidity);
+    }
+    to_reserve.verify_borrow_limits(clock.slot, payoff_tokens, debt, obligation_debt)?;
+
+    token::burn(
+        accounts
+            .note_burn_context()
+            .with_signer(&[&market.authority_seeds()]),
+        payoff_notes,
+    )?;
+
+    token::mint_to(
+        accounts
+            .note_mint_context()
+            .with_signer(&[&market.authority_seeds()]),
+        new_notes,
+    )?;
+
+    token::transfer(
+        accounts
+            .transfer_context()
+            .with_signer(&[&market.authority_seeds()]),
+        payoff_tokens,
+    )?;
+
+    to_reserve.borrow(clock.slot, payoff_tokens, new_notes, fees, protocol_fees)?;
+    from_reserve.repay(clock.slot, payoff_tokens, payoff_notes)?;
+
+    let mut obligation = accounts.obligation.load_mut()?;
+    obligation.refinance(
+        &accounts.from_loan_account.key(),
+        from_reserve.amount(payoff_notes),
+        &accounts.to_loan_account.key(),
+        to_reserve.amount(new_notes),
+    )?;
+
+    // The new loan carries fees, so it must leave the obligation healthy
+    obligation.cache_calculations(market.reserves(), clock.slot, market.market_oracle())?;
+    if !obligation.is_healthy(market.reserves(), clock.slot)? {
+        return err!(ErrorCode::InsufficientCollateral);
+    }
+
+    emit!(RefinanceEvent {
+        obligation: accounts.obligation.key(),
+        from_reserve: accounts.from_reserve.key(),
+        to_reserve: accounts.to_reserve.key(),
+        repaid: payoff_tokens,
+        debt,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::error_code;
+
+    #[test]
+    fn refresh_accounts_split_per_reserve() {
+        let accounts: Vec<u8> = (0..8).collect();
+
+        assert_eq!(split_refresh_accounts(&accounts[..0]).unwrap(), (&[][..], &[][..]));
+        assert_eq!(split_refresh_accounts(&accounts[..4]).unwrap(), (&accounts[..4], &[][..]));
+        assert_eq!(split_refresh_accounts(&accounts).unwrap(), (&accounts[..4], &accounts[4..]));
+    }
+
+    #[test]
+    fn partial_refresh_accounts_are_rejected() {
+        let accounts: Vec<u8> = (0..12).collect();
+
+        for len in [1, 3, 5, 7, 9, 12] {
+            assert_eq!(
+                error_code(split_refresh_accounts(&accounts[..len]).unwrap_err()),
+                ErrorCode::InvalidParameter.into()
+            );
+        }
+    }
+}
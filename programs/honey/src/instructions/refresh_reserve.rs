@@ -70,12 +70,35 @@ pub struct RefreshReserve<'info> {
 }
 
 impl<'info> RefreshReserve<'info> {
+    fn refresh_accounts(&self) -> ReserveRefreshAccounts<'_, 'info> {
+        ReserveRefreshAccounts {
+            market_authority: &self.market_authority,
+            switchboard_price_aggregator: &self.switchboard_price_aggregator,
+            fee_note_vault: self.fee_note_vault.to_account_info(),
+            protocol_fee_note_vault: self.protocol_fee_note_vault.to_account_info(),
+            deposit_note_mint: self.deposit_note_mint.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        }
+    }
+}
+
+/// The accounts needed to bring a reserve's cached state up to date
+pub struct ReserveRefreshAccounts<'a, 'info> {
+    pub market_authority: &'a AccountInfo<'info>,
+    pub switchboard_price_aggregator: &'a AccountInfo<'info>,
+    pub fee_note_vault: AccountInfo<'info>,
+    pub protocol_fee_note_vault: AccountInfo<'info>,
+    pub deposit_note_mint: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+}
+
+impl<'a, 'info> ReserveRefreshAccounts<'a, 'info> {
     fn fee_note_mint_context(&self) -> CpiContext<'_, '_, '_, 'info, MintTo<'info>> {
         CpiContext::new(
-            self.token_program.to_account_info().clone(),
+            self.token_program.clone(),
             MintTo {
-                to: self.fee_note_vault.to_account_info().clone(),
-                mint: self.deposit_note_mint.to_account_info(),
+                to: self.fee_note_vault.clone(),
+                mint: self.deposit_note_mint.clone(),
                 authority: self.market_authority.clone(),
             },
         )
@@ -83,10 +106,10 @@ impl<'info> RefreshReserve<'info> {
 
     fn protocol_fee_note_mint_context(&self) -> CpiContext<'_, '_, '_, 'info, MintTo<'info>> {
         CpiContext::new(
-            self.token_program.to_account_info().clone(),
+            self.token_program.clone(),
             MintTo {
-                to: self.protocol_fee_note_vault.to_account_info().clone(),
-                mint: self.deposit_note_mint.to_account_info(),
+                to: self.protocol_fee_note_vault.clone(),
+                mint: self.deposit_note_mint.clone(),
                 authority: self.market_authority.clone(),
             },
         )
@@ -108,8 +131,23 @@ pub fn handler(ctx: Context<RefreshReserve>) -> Result<()> {
     let nft_price: Decimal = nft_price_decimal.try_into()?;
     msg!("updated nft oracle price {}", nft_price);
 
+    refresh_reserve_state(&mut market, &mut reserve, &ctx.accounts.refresh_accounts())?;
+
+    Ok(())
+}
+
+/// Refresh a reserve's token price and accrue its interest, minting any collected
+/// fees as deposit notes.
+///
+/// If the reserve is extremely stale only a partial update is performed, and
+/// the cached reserve info is left invalidated until it catches up.
+pub fn refresh_reserve_state(
+    market: &mut Market,
+    reserve: &mut Reserve,
+    accounts: &ReserveRefreshAccounts,
+) -> Result<JobCompletion> {
     let aggregator_account_data =
-        AggregatorAccountData::new(&ctx.accounts.switchboard_price_aggregator)?;
+        AggregatorAccountData::new(accounts.switchboard_price_aggregator)?;
     let price_decimal = aggregator_account_data.get_result()?;
 
     let price: Decimal = price_decimal.try_into()?;
@@ -119,8 +157,13 @@ pub fn handler(ctx: Context<RefreshReserve>) -> Result<()> {
         return Err(ErrorCode::InvalidOraclePrice.into());
     }
 
+    // keep both caches fresh for as long as the market allows
+    let cache_ttl = market.reserve_cache_ttl();
+    reserve.set_cache_ttl(cache_ttl);
+
     let market_reserves = market.reserves_mut();
     let reserve_info = market_reserves.get_mut(reserve.index);
+    reserve_info.set_time_to_live(cache_ttl as u32);
 
     let clock = Clock::get()?;
 
@@ -129,7 +172,8 @@ pub fn handler(ctx: Context<RefreshReserve>) -> Result<()> {
     let loan_note_mint_supply = reserve.total_loan_notes();
 
     // apply the interest for outstanding debt on this reserve
-    match reserve.try_accrue_interest(vault_amount, clock.unix_timestamp, clock.slot) {
+    let completion = reserve.try_accrue_interest(vault_amount, clock.unix_timestamp, clock.slot);
+    match completion {
         JobCompletion::Partial => {
             msg!("performing partial reserve refresh: additional iterations required");
             reserve_info.invalidate();
@@ -143,9 +187,9 @@ pub fn handler(ctx: Context<RefreshReserve>) -> Result<()> {
                 clock.slot,
                 vault_amount,
                 deposit_note_mint_supply,
-            );
+            )?;
             let loan_note_exchange_rate =
-                reserve.loan_note_exchange_rate(clock.slot, loan_note_mint_supply);
+                reserve.loan_note_exchange_rate(clock.slot, loan_note_mint_supply)?;
 
             reserve_cache.price = Number::from_decimal(
                 price_decimal.mantissa as u128,
@@ -163,14 +207,14 @@ pub fn handler(ctx: Context<RefreshReserve>) -> Result<()> {
 
             // Collect any fees that need to be minted to notes
             let notes_to_mint =
-                reserve.collect_accrued_fees(clock.slot, deposit_note_exchange_rate);
+                reserve.collect_accrued_fees(clock.slot, deposit_note_exchange_rate)?;
 
             let notes_to_mint_protocol =
-                reserve.collect_accrued_protocol_fees(clock.slot, deposit_note_exchange_rate);
+                reserve.collect_accrued_protocol_fees(clock.slot, deposit_note_exchange_rate)?;
 
             if notes_to_mint > 0 {
                 token::mint_to(
-                    ctx.accounts
+                    accounts
                         .fee_note_mint_context()
                         .with_signer(&[&market.authority_seeds()]),
                     notes_to_mint,
@@ -179,7 +223,7 @@ pub fn handler(ctx: Context<RefreshReserve>) -> Result<()> {
 
             if notes_to_mint_protocol > 0 {
                 token::mint_to(
-                    ctx.accounts
+                    accounts
                         .protocol_fee_note_mint_context()
                         .with_signer(&[&market.authority_seeds()]),
                     notes_to_mint_protocol,
@@ -189,5 +233,247 @@ pub fn handler(ctx: Context<RefreshReserve>) -> Result<()> {
             msg!("reserve refreshed");
         }
     }
-    Ok(())
+
+    Ok(completion)
+}
+
+/// The number of trailing accounts `refresh_reserve_if_stale` takes to refresh a reserve
+pub const RESERVE_REFRESH_ACCOUNTS: usize = 4;
+
+/// Refresh a reserve inside another instruction when its cached data is stale.
+///
+/// The refresh accounts are the reserve's switchboard price aggregator, fee
+/// note vault, protocol fee note vault and deposit note mint, in that order.
+/// The caller passes either none of them or exactly all four, so a slice of
+/// any other length is an error. Without them a stale reserve is an error,
+/// and the caller is expected to send a `refresh_reserve` instruction first.
+///
+/// Instructions that refresh more than one reserve take one set of refresh
+/// accounts per reserve, in the order the instruction documents (see
+/// `refinance`). No instruction combines the refresh accounts with trailing
+/// accounts of another kind, such as programmable NFT accounts, frozen NFT
+/// accounts or compressed NFT proofs; an instruction that ever does must take
+/// the refresh accounts first.
+pub fn refresh_reserve_if_stale<'info>(
+    market: &mut Market,
+    reserve: &mut Reserve,
+    market_authority: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let refresh_accounts = match_refresh_accounts(reserve, remaining_accounts)?;
+
+    if !needs_refresh(market, reserve, clock.slot) {
+        return Ok(());
+    }
+
+    let [switchboard_price_aggregator, fee_note_vault, protocol_fee_note_vault, deposit_note_mint] =
+        match refresh_accounts {
+            Some(accounts) => accounts,
+            None => {
+                // no refresh accounts given, so report why the reserve can't be used
+                market.reserves().get_cached(reserve.index, clock.slot)?;
+                return err!(ErrorCode::StaleReserve);
+            }
+        };
+
+    let accounts = ReserveRefreshAccounts {
+        market_authority,
+        switchboard_price_aggregator,
+        fee_note_vault: fee_note_vault.clone(),
+        protocol_fee_note_vault: protocol_fee_note_vault.clone(),
+        deposit_note_mint: deposit_note_mint.clone(),
+        token_program: token_program.clone(),
+    };
+
+    match refresh_reserve_state(market, reserve, &accounts)? {
+        JobCompletion::Full => Ok(()),
+        JobCompletion::Partial => err!(ErrorCode::ExceptionalReserveState),
+    }
+}
+
+/// Whether the reserve's state, or the market's cache of it, is too old to use
+fn needs_refresh(market: &Market, reserve: &Reserve, current_slot: u64) -> bool {
+    let reserve_info = market.reserves().get(reserve.index);
+
+    reserve_info.try_get(current_slot).is_err() || reserve.is_stale(current_slot)
+}
+
+/// Check the refresh accounts passed for a reserve, giving none when they're left out
+fn match_refresh_accounts<'a, 'info>(
+    reserve: &Reserve,
+    refresh_accounts: &'a [AccountInfo<'info>],
+) -> Result<Option<[&'a AccountInfo<'info>; RESERVE_REFRESH_ACCOUNTS]>> {
+    let (switchboard_price_aggregator, fee_note_vault, protocol_fee_note_vault, deposit_note_mint) =
+        match refresh_accounts {
+            [] => return Ok(None),
+            [aggregator, fee_vault, protocol_fee_vault, deposit_note_mint] => {
+                (aggregator, fee_vault, protocol_fee_vault, deposit_note_mint)
+            }
+            _ => {
+                msg!(
+                    "expected 0 or {} refresh accounts, got {}",
+                    RESERVE_REFRESH_ACCOUNTS,
+                    refresh_accounts.len()
+                );
+                return err!(ErrorCode::InvalidParameter);
+            }
+        };
+
+    if switchboard_price_aggregator.key() != reserve.switchboard_price_aggregator {
+        return err!(ErrorCode::InvalidOracle);
+    }
+    if fee_note_vault.key() != reserve.fee_note_vault
+        || protocol_fee_note_vault.key() != reserve.protocol_fee_note_vault
+        || deposit_note_mint.key() != reserve.deposit_note_mint
+    {
+        msg!("refresh accounts don't match the reserve");
+        return err!(ErrorCode::InvalidParameter);
+    }
+
+    Ok(Some([
+        switchboard_price_aggregator,
+        fee_note_vault,
+        protocol_fee_note_vault,
+        deposit_note_mint,
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::error_code;
+    use bytemuck::Zeroable;
+
+    struct TestAccount {
+        key: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        owner: Pubkey,
+    }
+
+    impl TestAccount {
+        fn new(key: Pubkey) -> Self {
+            Self {
+                key,
+                lamports: 0,
+                data: vec![],
+                owner: Pubkey::default(),
+            }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(
+                &self.key,
+                false,
+                false,
+                &mut self.lamports,
+                &mut self.data,
+                &self.owner,
+                false,
+                0,
+            )
+        }
+    }
+
+    fn reserve() -> Reserve {
+        let mut reserve = Reserve::zeroed();
+        reserve.switchboard_price_aggregator = Pubkey::new_unique();
+        reserve.fee_note_vault = Pubkey::new_unique();
+        reserve.protocol_fee_note_vault = Pubkey::new_unique();
+        reserve.deposit_note_mint = Pubkey::new_unique();
+        reserve
+    }
+
+    fn refresh_accounts(reserve: &Reserve) -> Vec<TestAccount> {
+        [
+            reserve.switchboard_price_aggregator,
+            reserve.fee_note_vault,
+            reserve.protocol_fee_note_vault,
+            reserve.deposit_note_mint,
+        ]
+        .into_iter()
+        .map(TestAccount::new)
+        .collect()
+    }
+
+    fn market_with(reserve: &mut Reserve, current_slot: u64) -> Market {
+        let mut market = Market::zeroed();
+        reserve.index = market.reserves_mut().register(&Pubkey::new_unique()).unwrap();
+        market.reserves_mut().get_mut(reserve.index).refresh_to(current_slot);
+        reserve.try_accrue_interest(0, 0, current_slot);
+        market
+    }
+
+    #[test]
+    fn refresh_accounts_are_optional() {
+        assert!(match_refresh_accounts(&reserve(), &[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn refresh_accounts_match_the_reserve() {
+        let reserve = reserve();
+        let mut accounts = refresh_accounts(&reserve);
+        let infos: Vec<_> = accounts.iter_mut().map(TestAccount::info).collect();
+
+        let matched = match_refresh_accounts(&reserve, &infos).unwrap().unwrap();
+        assert_eq!(*matched[0].key, reserve.switchboard_price_aggregator);
+        assert_eq!(*matched[3].key, reserve.deposit_note_mint);
+    }
+
+    #[test]
+    fn refresh_accounts_must_be_complete() {
+        let reserve = reserve();
+        let mut accounts = refresh_accounts(&reserve);
+        accounts.push(TestAccount::new(Pubkey::new_unique()));
+        let infos: Vec<_> = accounts.iter_mut().map(TestAccount::info).collect();
+
+        for len in [1, 3, 5] {
+            assert_eq!(
+                error_code(match_refresh_accounts(&reserve, &infos[..len]).unwrap_err()),
+                ErrorCode::InvalidParameter.into()
+            );
+        }
+    }
+
+    #[test]
+    fn refresh_accounts_must_be_in_order() {
+        let reserve = reserve();
+        let mut accounts = refresh_accounts(&reserve);
+        accounts.swap(1, 2);
+        let infos: Vec<_> = accounts.iter_mut().map(TestAccount::info).collect();
+
+        assert_eq!(
+            error_code(match_refresh_accounts(&reserve, &infos).unwrap_err()),
+            ErrorCode::InvalidParameter.into()
+        );
+
+        accounts.swap(0, 1);
+        let infos: Vec<_> = accounts.iter_mut().map(TestAccount::info).collect();
+        assert_eq!(
+            error_code(match_refresh_accounts(&reserve, &infos).unwrap_err()),
+            ErrorCode::InvalidOracle.into()
+        );
+    }
+
+    #[test]
+    fn stale_reserve_needs_refresh() {
+        let mut reserve = reserve();
+        let market = market_with(&mut reserve, 10);
+
+        assert!(!needs_refresh(&market, &reserve, 10));
+        assert!(needs_refresh(&market, &reserve, 12));
+    }
+
+    #[test]
+    fn stale_market_cache_needs_refresh() {
+        let mut reserve = reserve();
+        let mut market = market_with(&mut reserve, 10);
+        reserve.set_cache_ttl(100);
+
+        assert!(!needs_refresh(&market, &reserve, 10));
+        market.reserves_mut().get_mut(reserve.index).invalidate();
+        assert!(needs_refresh(&market, &reserve, 10));
+    }
 }
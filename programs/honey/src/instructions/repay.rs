@@ -20,8 +20,9 @@ use anchor_lang::Key;
 use anchor_spl::token::Token;
 use anchor_spl::token::{self, Burn, Transfer};
 
-use crate::common::Amount;
+use crate::common::{Amount, AmountBasis};
 use crate::common::Rounding;
+use crate::instructions::refresh_reserve_if_stale;
 use crate::state::*;
 
 #[event]
@@ -34,7 +35,7 @@ pub struct RepayEvent {
 #[derive(Accounts)]
 pub struct Repay<'info> {
     /// The relevant market this repayment is for
-    #[account(has_one = market_authority)]
+    #[account(mut, has_one = market_authority)]
     pub market: AccountLoader<'info, Market>,
 
     /// The market's authority account
@@ -74,8 +75,8 @@ pub struct Repay<'info> {
     #[account(mut)]
     pub payer_account: AccountInfo<'info>,
 
-    /// The account repaying the loan
-    /// CHECK: TODO what should we be checking
+    /// The account repaying the loan, which may be anyone
+    /// CHECK: any signer may repay a loan with its own tokens
     #[account(signer)]
     pub payer: AccountInfo<'info>,
 
@@ -158,27 +159,42 @@ pub(crate) use implement_repay_context;
 implement_repay_context! {Repay<'info>}
 
 /// Repay tokens for a loan
-pub fn handler(ctx: Context<Repay>, amount: Amount) -> Result<()> {
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, Repay<'info>>, amount: Amount) -> Result<()> {
     repay(&ctx, amount)?;
     Ok(())
 }
 
-pub fn repay<'info, T: RepayContext<'info>>(ctx: &Context<T>, amount: Amount) -> Result<()> {
+pub fn repay<'info, T: RepayContext<'info>>(
+    ctx: &Context<'_, '_, '_, 'info, T>,
+    amount: Amount,
+) -> Result<()> {
     let clock = Clock::get().unwrap();
-    let market = ctx.accounts.market().load()?;
+    let mut market = ctx.accounts.market().load_mut()?;
     let mut reserve = ctx.accounts.reserve().load_mut()?;
+
+    refresh_reserve_if_stale(
+        &mut market,
+        &mut reserve,
+        ctx.accounts.market_authority(),
+        ctx.accounts.token_program(),
+        ctx.remaining_accounts,
+    )?;
+
     let mut obligation = ctx.accounts.obligation().load_mut()?;
     let loan_account = ctx.accounts.loan_account();
-    let reserve_info = market.reserves().get_cached(reserve.index, clock.slot);
+    let reserve_info = market.reserves().get_cached(reserve.index, clock.slot)?;
 
     market.verify_ability_repay()?;
+    reserve.verify_ability_repay()?;
 
     // Calculate the number of tokens and notes that match the value being repaid
-    let payoff_notes = amount.as_loan_notes(reserve_info, Rounding::Down)?;
-    let payoff_notes = std::cmp::min(payoff_notes, token::accessor::amount(loan_account)?);
+    let loan_notes = token::accessor::amount(loan_account)?;
+    let basis = AmountBasis::Loan { notes: loan_notes };
+    let payoff_notes = amount.as_loan_notes(reserve_info, Rounding::Down, basis)?;
+    let payoff_notes = std::cmp::min(payoff_notes, loan_notes);
     let payoff_tokens = std::cmp::min(
         reserve_info.loan_notes_to_tokens(payoff_notes, Rounding::Up),
-        reserve.unwrap_outstanding_debt(clock.slot).as_u64(0),
+        reserve.outstanding_debt(clock.slot)?.as_u64(0),
     );
 
     // Burn the debt that's being repaid
@@ -193,7 +209,7 @@ pub fn repay<'info, T: RepayContext<'info>>(ctx: &Context<T>, amount: Amount) ->
     token::transfer(ctx.accounts.transfer_context(), payoff_tokens)?;
 
     // Keep the reserve's borrow tracking updated
-    reserve.repay(clock.slot, payoff_tokens, payoff_notes);
+    reserve.repay(clock.slot, payoff_tokens, payoff_notes)?;
 
     // record the repayment in the obligation which is used to determine the obligation's health
     obligation.repay(&loan_account.key(), reserve.amount(payoff_notes))?;
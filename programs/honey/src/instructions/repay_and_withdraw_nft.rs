@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_lang::Key;
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+
+use crate::common::{Amount, AmountUnits};
+use crate::errors::ErrorCode;
+use crate::instructions::{implement_repay_context, repay, RepayContext};
+use crate::state::*;
+
+#[event]
+pub struct RepayAndWithdrawNFTEvent {
+    owner: Pubkey,
+    obligation: Pubkey,
+    reserve: Pubkey,
+    nft_mint: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct RepayAndWithdrawNFT<'info> {
+    /// The relevant market the loan and collateral are in
+    #[account(mut, has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The obligation with the debt being repaid and the NFT being withdrawn
+    #[account(mut,
+              has_one = market,
+              constraint = obligation.load()?.owner == payer.key() @ ErrorCode::Unauthorized,
+              constraint = obligation.load()?.has_loan_custody(&loan_account.key()))]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The reserve that the debt is from
+    #[account(mut,
+              has_one = market,
+              has_one = vault,
+              has_one = loan_note_mint)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// The reserve's vault where the payment will be transferred to
+    /// CHECK: checked against the reserve
+    #[account(mut)]
+    pub vault: AccountInfo<'info>,
+
+    /// The mint for the debt/loan notes
+    /// CHECK: checked against the reserve
+    #[account(mut)]
+    pub loan_note_mint: AccountInfo<'info>,
+
+    /// The account that holds the borrower's debt balance, which is closed
+    /// once repaid
+    /// CHECK: the obligation must have custody of the loan account
+    #[account(mut)]
+    pub loan_account: AccountInfo<'info>,
+
+    /// The token account that the payment funds will be transferred from
+    /// CHECK: the token program checks the payer owns the account
+    #[account(mut)]
+    pub payer_account: AccountInfo<'info>,
+
+    /// The obligation owner, which repays the loan, receives the NFT and
+    /// the rent from the closed accounts
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The mint of the NFT being withdrawn
+    pub nft_mint: Account<'info, Mint>,
+
+    /// The account that contains the NFT, which is closed once emptied
+    #[account(mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = market_authority)]
+    pub collateral_account: Account<'info, TokenAccount>,
+
+    /// The owner's account receiving the NFT
+    #[account(mut,
+        constraint = nft_receiver.mint == nft_mint.key() @ ErrorCode::InvalidParameter)]
+    pub nft_receiver: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+implement_repay_context! {RepayAndWithdrawNFT<'info>}
+
+impl<'info> RepayAndWithdrawNFT<'info> {
+    fn transfer_nft_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.collateral_account.to_account_info(),
+                to: self.nft_receiver.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+
+    fn close_context(
+        &self,
+        account: AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account,
+                destination: self.payer.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+}
+
+/// Repay the whole of a loan, then withdraw an NFT from the obligation and
+/// close the emptied loan and collateral accounts, all at once so no
+/// interest can accrue in between.
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, RepayAndWithdrawNFT<'info>>,
+) -> Result<()> {
+    repay(&ctx, Amount { units: AmountUnits::All, value: 0 })?;
+
+    let accounts = &ctx.accounts;
+    let market = accounts.market.load()?;
+    let mut obligation = accounts.obligation.load_mut()?;
+    let nft_mint = accounts.nft_mint.key();
+
+    market.verify_ability_withdraw_nft()?;
+
+    obligation.unregister_loan(&accounts.loan_account.key())?;
+    obligation.unregister_escrowed_nft(nft_mint)?;
+
+    token::transfer(
+        accounts
+            .transfer_nft_context()
+            .with_signer(&[&market.authority_seeds()]),
+        1,
+    )?;
+
+    for account in [
+        accounts.loan_account.to_account_info(),
+        accounts.collateral_account.to_account_info(),
+    ] {
+        token::close_account(
+            accounts
+                .close_context(account)
+                .with_signer(&[&market.authority_seeds()]),
+        )?;
+    }
+
+    // Verify this doesn't leave any other loan subject to liquidation
+    let clock = Clock::get()?;
+    obligation.cache_calculations(market.reserves(), clock.slot, market.market_oracle())?;
+    if !obligation.is_healthy(market.reserves(), clock.slot)? {
+        return Err(ErrorCode::ObligationUnhealthy.into());
+    }
+
+    emit!(RepayAndWithdrawNFTEvent {
+        owner: accounts.payer.key(),
+        obligation: accounts.obligation.key(),
+        reserve: accounts.reserve.key(),
+        nft_mint,
+    });
+
+    Ok(())
+}
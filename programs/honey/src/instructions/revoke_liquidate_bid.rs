@@ -100,11 +100,12 @@ pub fn handler(
 ) -> Result<()> {
     let bid = &ctx.accounts.bid;
 
+    // refund whatever is left after any liquidations the bid paid for
     token::transfer(
         ctx.accounts
             .transfer_context()
             .with_signer(&[&bid.authority_seeds()]),
-        bid.bid_limit,
+        ctx.accounts.bid_escrow.amount,
     )?;
 
     token::close_account(
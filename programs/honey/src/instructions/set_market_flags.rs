@@ -35,7 +35,7 @@ pub fn handler(ctx: Context<SetMarketFlags>, flags: u64) -> Result<()> {
         None => return err!(ErrorCode::InvalidParameter),
     };
 
-    market.reset_flags(flags);
+    market.reset_flags(flags, Clock::get()?.unix_timestamp);
 
     Ok(())
 }
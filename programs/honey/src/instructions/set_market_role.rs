@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[event]
+pub struct SetMarketRoleEvent {
+    market: Pubkey,
+    role: MarketRole,
+    holder: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketRole<'info> {
+    #[account(has_one = owner)]
+    pub market: AccountLoader<'info, Market>,
+
+    #[account(mut, has_one = market)]
+    pub roles: Account<'info, MarketRoles>,
+
+    pub owner: Signer<'info>,
+}
+
+/// Assign a role on a market, or unassign it with the default key
+pub fn handler(ctx: Context<SetMarketRole>, role: MarketRole, holder: Pubkey) -> Result<()> {
+    ctx.accounts.roles.set_holder(role, holder);
+
+    emit!(SetMarketRoleEvent {
+        market: ctx.accounts.market.key(),
+        role,
+        holder,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[event]
+pub struct SetObligationDelegateEvent {
+    obligation: Pubkey,
+    delegate: Pubkey,
+    permissions: u64,
+}
+
+#[derive(Accounts)]
+pub struct SetObligationDelegate<'info> {
+    #[account(mut, has_one = owner)]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The owner of the obligation
+    pub owner: Signer<'info>,
+}
+
+/// Allow an address to manage the obligation on the owner's behalf, with
+/// `permissions` as the bits of `ObligationPermissions` it may use
+pub fn handler(
+    ctx: Context<SetObligationDelegate>,
+    delegate: Pubkey,
+    permissions: u64,
+) -> Result<()> {
+    let mut obligation = ctx.accounts.obligation.load_mut()?;
+    obligation.set_delegate(delegate, permissions)?;
+
+    emit!(SetObligationDelegateEvent {
+        obligation: ctx.accounts.obligation.key(),
+        delegate,
+        permissions,
+    });
+
+    Ok(())
+}
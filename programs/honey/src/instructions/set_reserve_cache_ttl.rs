@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::errors::ErrorCode;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetReserveCacheTtl<'info> {
+    #[account(mut)]
+    pub market: AccountLoader<'info, Market>,
+
+    #[account(has_one = market,
+        constraint = roles.is_risk_authority(&market.load()?.owner, authority.key) @ ErrorCode::Unauthorized)]
+    pub roles: Account<'info, MarketRoles>,
+
+    /// The market owner or risk admin
+    pub authority: Signer<'info>,
+}
+
+/// Change the number of slots cached reserve data stays fresh for
+pub fn handler(ctx: Context<SetReserveCacheTtl>, ttl: u64) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.set_reserve_cache_ttl(ttl)?;
+
+    msg!("reserve cache ttl set to {} slots", market.reserve_cache_ttl());
+    Ok(())
+}
@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::errors::ErrorCode;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetReserveFlags<'info> {
+    #[account(has_one = owner)]
+    pub market: AccountLoader<'info, Market>,
+
+    #[account(mut, has_one = market)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    pub owner: Signer<'info>,
+}
+
+/// Change the flags on a reserve
+pub fn handler(ctx: Context<SetReserveFlags>, flags: u64) -> Result<()> {
+    let mut reserve = ctx.accounts.reserve.load_mut()?;
+    let flags = match ReserveFlags::from_bits(flags) {
+        Some(f) => f,
+        None => return err!(ErrorCode::InvalidParameter),
+    };
+
+    reserve.reset_flags(flags, Clock::get()?.unix_timestamp);
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_lang::Key;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+
+use crate::errors::ErrorCode;
+use crate::state::*;
+use crate::utils::{validate, verify_valid_metadata};
+
+#[event]
+pub struct SwapNFTCollateralEvent {
+    owner: Pubkey,
+    obligation: Pubkey,
+    old_nft_mint: Pubkey,
+    new_nft_mint: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct SwapNFTCollateral<'info> {
+    /// The relevant market the collateral is in
+    #[account(mut,
+              has_one = market_authority,
+              has_one = nft_collection_creator)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The obligation whose collateral is being swapped
+    #[account(mut,
+              has_one = market,
+              has_one = owner)]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The user/authority that owns the obligation, which receives the
+    /// old NFT and the rent from its emptied collateral account
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// verified collection creator
+    /// CHECK: market must have a nft_collection_creator account
+    pub nft_collection_creator: AccountInfo<'info>,
+
+    /// The mint of the NFT being deposited
+    #[account(constraint = new_nft_mint.key() != old_nft_mint.key() @ ErrorCode::InvalidParameter)]
+    pub new_nft_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: metadata validated with validate check
+    pub new_nft_metadata: AccountInfo<'info>,
+
+    /// The owner's account holding the NFT being deposited
+    #[account(mut)]
+    pub new_nft_source: Box<Account<'info, TokenAccount>>,
+
+    /// The account that will store the new NFT as collateral
+    #[account(init_if_needed,
+        associated_token::mint = new_nft_mint,
+        associated_token::authority = market_authority,
+        payer = owner)]
+    pub new_collateral_account: Box<Account<'info, TokenAccount>>,
+
+    /// The mint of the NFT being released
+    pub old_nft_mint: Box<Account<'info, Mint>>,
+
+    /// The account that stores the NFT being released
+    #[account(mut,
+        associated_token::mint = old_nft_mint,
+        associated_token::authority = market_authority)]
+    pub old_collateral_account: Box<Account<'info, TokenAccount>>,
+
+    /// The owner's account receiving the released NFT
+    #[account(mut,
+        constraint = old_nft_receiver.mint == old_nft_mint.key() @ ErrorCode::InvalidParameter)]
+    pub old_nft_receiver: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> SwapNFTCollateral<'info> {
+    fn deposit_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.new_nft_source.to_account_info(),
+                to: self.new_collateral_account.to_account_info(),
+                authority: self.owner.to_account_info(),
+            },
+        )
+    }
+
+    fn release_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.old_collateral_account.to_account_info(),
+                to: self.old_nft_receiver.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+
+    fn close_collateral_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.old_collateral_account.to_account_info(),
+                destination: self.owner.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+}
+
+/// Deposit a new NFT as collateral in place of one already pledged,
+/// releasing the old NFT without repaying the loan
+#[access_control(validate(metadata_bump, &ctx.accounts.new_nft_metadata, &ctx.accounts.new_nft_mint))]
+pub fn handler(ctx: Context<SwapNFTCollateral>, metadata_bump: u8) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let market = accounts.market.load()?;
+
+    verify_valid_metadata(&accounts.new_nft_metadata, &accounts.nft_collection_creator)?;
+
+    market.verify_ability_deposit_nft()?;
+    market.verify_ability_withdraw_nft()?;
+
+    let mut obligation = accounts.obligation.load_mut()?;
+    obligation.unregister_escrowed_nft(accounts.old_nft_mint.key())?;
+    obligation.register_nft(accounts.new_nft_mint.key())?;
+
+    token::transfer(accounts.deposit_context(), 1)?;
+
+    token::transfer(
+        accounts
+            .release_context()
+            .with_signer(&[&market.authority_seeds()]),
+        1,
+    )?;
+
+    token::close_account(
+        accounts
+            .close_collateral_context()
+            .with_signer(&[&market.authority_seeds()]),
+    )?;
+
+    // Verify the new collateral still covers the loan
+    let clock = Clock::get()?;
+    obligation.cache_calculations(market.reserves(), clock.slot, market.market_oracle())?;
+    if !obligation.is_healthy(market.reserves(), clock.slot)? {
+        return Err(ErrorCode::ObligationUnhealthy.into());
+    }
+
+    emit!(SwapNFTCollateralEvent {
+        owner: accounts.owner.key(),
+        obligation: accounts.obligation.key(),
+        old_nft_mint: accounts.old_nft_mint.key(),
+        new_nft_mint: accounts.new_nft_mint.key(),
+    });
+
+    Ok(())
+}
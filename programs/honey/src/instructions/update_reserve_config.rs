@@ -1,18 +1,26 @@
+use crate::errors::ErrorCode;
 use crate::state::*;
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
 pub struct UpdateReserveConfig<'info> {
-    #[account(has_one = owner)]
     pub market: AccountLoader<'info, Market>,
 
+    #[account(has_one = market,
+        constraint = roles.is_risk_authority(&market.load()?.owner, owner.key) @ ErrorCode::Unauthorized)]
+    pub roles: Account<'info, MarketRoles>,
+
     #[account(mut, has_one = market)]
     pub reserve: AccountLoader<'info, Reserve>,
 
+    /// The market owner or risk admin. Named `owner` as it was before the
+    /// risk admin role existed, so existing clients keep working.
     pub owner: Signer<'info>,
 }
 
 pub fn handler(ctx: Context<UpdateReserveConfig>, new_config: ReserveConfig) -> Result<()> {
+    new_config.validate()?;
+
     let mut reserve = ctx.accounts.reserve.load_mut()?;
     reserve.config = new_config;
     Ok(())
@@ -18,12 +18,13 @@
 use anchor_lang::prelude::*;
 use anchor_lang::Key;
 use anchor_spl::token::Token;
-use anchor_spl::token::{ self, Mint, TokenAccount, Transfer };
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{ self, CloseAccount, Mint, Revoke, TokenAccount, Transfer };
 use crate::utils::validate;
 
 use crate::errors::ErrorCode;
 use crate::state::*;
-use crate::utils::verify_valid_metadata;
+use crate::utils::{ verify_valid_metadata, DelegatedNft };
 
 #[event]
 pub struct WithdrawCollateralEvent {
@@ -44,18 +45,20 @@ pub struct WithdrawNFT<'info> {
     /// CHECK: market must have a market_authority account
     pub market_authority: AccountInfo<'info>,
 
-    /// The obligation the collateral is being withdrawn from
-    /// todo verify depositor?
+    /// The obligation the collateral is being withdrawn from, which only
+    /// its owner may withdraw from, never a delegate
     #[account(mut,
               has_one = market,
               has_one = owner)]
     pub obligation: AccountLoader<'info, Obligation>,
 
-    /// The user/authority that owns the deposited collateral (depositor)
+    /// The user/authority that owns the deposited collateral (depositor),
+    /// which receives the rent from the emptied collateral account
+    #[account(mut)]
     pub owner: Signer<'info>,
 
     /// The account that stores the user's deposit notes, where
-    /// the collateral will be returned to.
+    /// the collateral will be returned to. Unused for nfts frozen in place.
     #[account(mut)]
     pub deposit_to: Account<'info, TokenAccount>,
 
@@ -69,10 +72,11 @@ pub struct WithdrawNFT<'info> {
     #[account(mut)]
     pub deposit_nft_mint: Account<'info, Mint>,
 
-    /// The account that contains the collateral to be withdrawn
+    /// The account that contains the collateral to be withdrawn, which is the
+    /// market authority's account for escrowed nfts, or the owner's account
+    /// for nfts frozen in place
     #[account(mut,
-        associated_token::mint = deposit_nft_mint,
-        associated_token::authority = market_authority)]
+        constraint = collateral_account.mint == deposit_nft_mint.key() @ ErrorCode::InvalidParameter)]
     pub collateral_account: Account<'info, TokenAccount>,
 
     pub token_program: Program<'info, Token>,
@@ -86,11 +90,56 @@ impl<'info> WithdrawNFT<'info> {
             authority: self.market_authority.clone(),
         })
     }
+
+    fn close_collateral_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        CpiContext::new(self.token_program.to_account_info().clone(), CloseAccount {
+            account: self.collateral_account.to_account_info(),
+            destination: self.owner.to_account_info(),
+            authority: self.market_authority.clone(),
+        })
+    }
+
+    fn revoke_context(&self) -> CpiContext<'_, '_, '_, 'info, Revoke<'info>> {
+        CpiContext::new(self.token_program.to_account_info().clone(), Revoke {
+            source: self.collateral_account.to_account_info(),
+            authority: self.owner.to_account_info(),
+        })
+    }
+
+    /// Release an nft frozen in the owner's wallet, rather than escrowed
+    fn thaw_in_place(&self, trailing: &[AccountInfo<'info>], market: &Market) -> Result<()> {
+        let (edition, token_metadata_program) = match trailing {
+            [edition, token_metadata_program] => (edition, token_metadata_program),
+            _ => {
+                msg!("an nft frozen in place needs its edition and the Token Metadata program");
+                return err!(ErrorCode::InvalidParameter);
+            }
+        };
+
+        if self.collateral_account.owner != self.owner.key() {
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        DelegatedNft {
+            delegate: &self.market_authority,
+            token_account: self.collateral_account.as_ref(),
+            edition,
+            mint: self.deposit_nft_mint.as_ref(),
+            token_program: self.token_program.as_ref(),
+            token_metadata_program,
+        }
+        .thaw(&[&market.authority_seeds()])?;
+
+        token::revoke(self.revoke_context())
+    }
 }
 
 /// Withdraw reserve notes previously deposited as collateral for an obligation
 #[access_control(validate(metadata_bump, &ctx.accounts.metadata, &ctx.accounts.deposit_nft_mint))]
-pub fn handler(ctx: Context<WithdrawNFT>, metadata_bump: u8) -> Result<()> {
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, WithdrawNFT<'info>>,
+    metadata_bump: u8,
+) -> Result<()> {
     // Transfer the notes from the collateral account back to the
     // regular deposit account.
     let market = ctx.accounts.market.load()?;
@@ -98,21 +147,39 @@ pub fn handler(ctx: Context<WithdrawNFT>, metadata_bump: u8) -> Result<()> {
 
     verify_valid_metadata(&ctx.accounts.metadata, &ctx.accounts.nft_collection_creator)?;
 
-    market.verify_ability_deposit_withdraw()?;
+    market.verify_ability_withdraw_nft()?;
 
     let note_amount = 1;
 
-    token::transfer(
-        ctx.accounts.transfer_context().with_signer(&[&market.authority_seeds()]),
-        note_amount
-    )?;
-
     // Also update the collateral values stored in the obligation account
     let mut obligation = ctx.accounts.obligation.load_mut()?;
-    let _collateral_account = ctx.accounts.collateral_account.key();
+
+    match obligation.nft_custody(&deposit_nft_mint)? {
+        NftCustody::Escrow => {
+            let escrow = get_associated_token_address(
+                ctx.accounts.market_authority.key,
+                &deposit_nft_mint
+            );
+            if ctx.accounts.collateral_account.key() != escrow {
+                return err!(ErrorCode::NftCustodyMismatch);
+            }
+
+            token::transfer(
+                ctx.accounts.transfer_context().with_signer(&[&market.authority_seeds()]),
+                note_amount
+            )?;
+
+            // The collateral account only ever holds the one NFT, so it can be
+            // closed and its rent refunded to the depositor
+            token::close_account(
+                ctx.accounts.close_collateral_context().with_signer(&[&market.authority_seeds()])
+            )?;
+        }
+        NftCustody::Frozen => ctx.accounts.thaw_in_place(ctx.remaining_accounts, &market)?,
+        NftCustody::Compressed => return err!(ErrorCode::NftCustodyMismatch),
+    }
 
     // unregister the collateral from the init_nft_account
-    // TODO: this also means we need to close and refund the account
     obligation.unregister_nft(deposit_nft_mint)?;
 
     // Verify this doesn't leave the loan subject to liquidation
@@ -120,8 +187,8 @@ pub fn handler(ctx: Context<WithdrawNFT>, metadata_bump: u8) -> Result<()> {
     let market_info = market.reserves();
     let market_oracle = market.market_oracle();
 
-    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle);
-    if !obligation.is_healthy(market_info, clock.slot) {
+    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle)?;
+    if !obligation.is_healthy(market_info, clock.slot)? {
         return Err(ErrorCode::ObligationUnhealthy.into());
     }
 
@@ -18,7 +18,8 @@
 use anchor_lang::prelude::*;
 use anchor_lang::Key;
 use anchor_spl::token::Token;
-use anchor_spl::token::{self, Mint, TokenAccount, Transfer};
+use anchor_spl::token::{self, CloseAccount, Mint, TokenAccount, Transfer};
+use crate::errors::ErrorCode;
 use crate::utils::validate;
 use crate::state::*;
 use crate::utils::verify_valid_metadata;
@@ -53,8 +54,18 @@ pub struct WithdrawNFTSolvent<'info> {
     )]
     pub obligation: AccountLoader<'info, Obligation>,
 
-    /// The admin who will own the nft
-    #[cfg_attr(not(feature = "testing"), account(address = crate::ROOT_AUTHORITY))]
+    /// The obligation's owner, which paid for the collateral account and
+    /// receives its rent once it's closed
+    /// CHECK: checked against the obligation
+    #[account(mut,
+        constraint = obligation.load()?.owner == obligation_owner.key() @ ErrorCode::InvalidParameter)]
+    pub obligation_owner: AccountInfo<'info>,
+
+    #[account(has_one = market,
+        constraint = roles.has_role(MarketRole::SolventExecutor, withdrawer.key) @ ErrorCode::Unauthorized)]
+    pub roles: Account<'info, MarketRoles>,
+
+    /// The solvent executor who will own the nft
     pub withdrawer: Signer<'info>,
     /// The account that stores the withdrawer's deposit notes, where
     /// the collateral will be transferred to.
@@ -91,6 +102,14 @@ impl<'info> WithdrawNFTSolvent<'info> {
             },
         )
     }
+
+    fn close_collateral_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        CpiContext::new(self.token_program.to_account_info().clone(), CloseAccount {
+            account: self.collateral_account.to_account_info(),
+            destination: self.obligation_owner.clone(),
+            authority: self.market_authority.clone(),
+        })
+    }
 }
 
 /// Withdraw reserve notes previously deposited as collateral for an obligation
@@ -114,7 +133,7 @@ pub fn handler(ctx: Context<WithdrawNFTSolvent>, metadata_bump: u8) -> Result<()
 
     verify_valid_metadata(&ctx.accounts.metadata, &ctx.accounts.nft_collection_creator)?;
 
-    market.verify_ability_deposit_withdraw()?;
+    market.verify_ability_liquidate(clock.unix_timestamp)?;
 
     let note_amount = 1;
 
@@ -125,12 +144,17 @@ pub fn handler(ctx: Context<WithdrawNFTSolvent>, metadata_bump: u8) -> Result<()
         note_amount,
     )?;
 
+    // The collateral account only ever holds the one NFT, so it can be
+    // closed and its rent refunded to the obligation's owner
+    token::close_account(
+        ctx.accounts.close_collateral_context().with_signer(&[&market.authority_seeds()])
+    )?;
+
     // 3. Also update the collateral values stored in the obligation account
     // unregister the collateral from the init_nft_account
-    // TODO: this also means we need to close and refund the account
-    obligation.unregister_nft(deposit_nft_mint)?;
+    obligation.unregister_escrowed_nft(deposit_nft_mint)?;
 
-    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle);
+    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle)?;
 
     //market.total_tokens_deposited = total_tokens_deposited.checked_sub(1).unwrap();
 
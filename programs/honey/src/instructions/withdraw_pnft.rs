@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount};
+use mpl_token_metadata::ID as metadata_program_id;
+
+use crate::errors::ErrorCode;
+use crate::pnft::PnftTransfer;
+use crate::state::*;
+use crate::utils::{validate, verify_valid_metadata};
+
+#[event]
+pub struct WithdrawProgrammableCollateralEvent {
+    depositor: Pubkey,
+    market: Pubkey,
+    mint: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawPNFT<'info> {
+    /// The relevant market the collateral is in
+    #[account(mut,
+              has_one = market_authority,
+              has_one = nft_collection_creator)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    /// The obligation the collateral is being withdrawn from, which only
+    /// its owner may withdraw from, never a delegate
+    #[account(mut,
+              has_one = market,
+              has_one = owner)]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The owner of the obligation, which receives the pNFT and pays for
+    /// its new token record
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// The owner's account receiving the pNFT, which Token Metadata creates if needed
+    /// CHECK: must be the owner's associated token account
+    #[account(mut,
+              address = get_associated_token_address(owner.key, &deposit_nft_mint.key()))]
+    pub deposit_to: AccountInfo<'info>,
+
+    /// verified collection creator
+    /// CHECK: market must have a nft_collection_creator account
+    pub nft_collection_creator: AccountInfo<'info>,
+
+    /// CHECK: metadata validated with validate check
+    #[account(mut)]
+    pub metadata: AccountInfo<'info>,
+
+    /// CHECK: checked against the pNFT's master edition address
+    pub edition: AccountInfo<'info>,
+
+    /// CHECK: checked against the token record for the collateral account
+    #[account(mut)]
+    pub collateral_token_record: AccountInfo<'info>,
+
+    /// CHECK: checked against the token record for the receiving account
+    #[account(mut)]
+    pub owner_token_record: AccountInfo<'info>,
+
+    pub deposit_nft_mint: Box<Account<'info, Mint>>,
+
+    /// The account that contains the collateral to be withdrawn
+    #[account(mut,
+              associated_token::mint = deposit_nft_mint,
+              associated_token::authority = market_authority)]
+    pub collateral_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: must be the Token Metadata program
+    #[account(address = metadata_program_id)]
+    pub token_metadata_program: AccountInfo<'info>,
+
+    /// CHECK: must be the instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: AccountInfo<'info>,
+
+    /// CHECK: checked against the pNFT's rule set, when it has one
+    pub authorization_rules_program: AccountInfo<'info>,
+
+    /// CHECK: checked against the pNFT's rule set, when it has one
+    pub authorization_rules: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> WithdrawPNFT<'info> {
+    fn pnft_transfer(&self) -> PnftTransfer<'_, 'info> {
+        PnftTransfer {
+            token: self.collateral_account.as_ref().as_ref(),
+            token_owner: &self.market_authority,
+            destination: &self.deposit_to,
+            destination_owner: self.owner.as_ref(),
+            mint: self.deposit_nft_mint.as_ref().as_ref(),
+            metadata: &self.metadata,
+            edition: &self.edition,
+            owner_token_record: &self.collateral_token_record,
+            destination_token_record: &self.owner_token_record,
+            authority: &self.market_authority,
+            payer: self.owner.as_ref(),
+            system_program: self.system_program.as_ref(),
+            sysvar_instructions: &self.sysvar_instructions,
+            token_program: self.token_program.as_ref(),
+            associated_token_program: self.associated_token_program.as_ref(),
+            token_metadata_program: &self.token_metadata_program,
+            authorization_rules_program: &self.authorization_rules_program,
+            authorization_rules: &self.authorization_rules,
+        }
+    }
+
+    fn close_collateral_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        CpiContext::new(self.token_program.to_account_info(), CloseAccount {
+            account: self.collateral_account.to_account_info(),
+            destination: self.owner.to_account_info(),
+            authority: self.market_authority.clone(),
+        })
+    }
+}
+
+/// Withdraw a programmable NFT previously deposited as collateral for an obligation
+#[access_control(validate(metadata_bump, &ctx.accounts.metadata, &ctx.accounts.deposit_nft_mint))]
+pub fn handler(ctx: Context<WithdrawPNFT>, metadata_bump: u8) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    let deposit_nft_mint = ctx.accounts.deposit_nft_mint.key();
+
+    verify_valid_metadata(&ctx.accounts.metadata, &ctx.accounts.nft_collection_creator)?;
+
+    market.verify_ability_withdraw_nft()?;
+
+    ctx.accounts.pnft_transfer().invoke_signed(&[&market.authority_seeds()])?;
+
+    // Token Metadata leaves the emptied account thawed, in which case its
+    // rent can go back to the owner
+    ctx.accounts.collateral_account.reload()?;
+    if !ctx.accounts.collateral_account.is_frozen() {
+        token::close_account(
+            ctx.accounts.close_collateral_context().with_signer(&[&market.authority_seeds()])
+        )?;
+    }
+
+    let mut obligation = ctx.accounts.obligation.load_mut()?;
+    obligation.unregister_escrowed_nft(deposit_nft_mint)?;
+
+    // Verify this doesn't leave the loan subject to liquidation
+    let clock = Clock::get()?;
+    let market_oracle = market.market_oracle();
+
+    obligation.cache_calculations(market.reserves(), clock.slot, market_oracle)?;
+    if !obligation.is_healthy(market.reserves(), clock.slot)? {
+        return err!(ErrorCode::ObligationUnhealthy);
+    }
+
+    emit!(WithdrawProgrammableCollateralEvent {
+        depositor: ctx.accounts.owner.key(),
+        market: ctx.accounts.market.key(),
+        mint: deposit_nft_mint,
+    });
+
+    Ok(())
+}
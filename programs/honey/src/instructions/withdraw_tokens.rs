@@ -19,7 +19,9 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Burn, Token, Mint, TokenAccount, Transfer};
 
 use crate::{
-    common::{Amount, Rounding},
+    common::{Amount, AmountBasis, Rounding},
+    errors::ErrorCode,
+    instructions::refresh_reserve_if_stale,
     state::*,
 };
 
@@ -27,7 +29,7 @@ use crate::{
 #[instruction(bump: u8)]
 pub struct WithdrawTokens<'info> {
     /// The relevant market this withdraw is for
-    #[account(has_one = market_authority)]
+    #[account(mut, has_one = market_authority)]
     pub market: AccountLoader<'info, Market>,
 
     /// The market's authority account
@@ -94,19 +96,37 @@ impl<'info> WithdrawTokens<'info> {
 }
 
 /// Withdraw tokens from a reserve
-pub fn handler(ctx: Context<WithdrawTokens>, _bump: u8, amount: Amount) -> Result<()> {
-    let market = ctx.accounts.market.load()?;
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, WithdrawTokens<'info>>,
+    _bump: u8,
+    amount: Amount,
+) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
     let mut reserve = ctx.accounts.reserve.load_mut()?;
+
+    refresh_reserve_if_stale(
+        &mut market,
+        &mut reserve,
+        &ctx.accounts.market_authority,
+        &ctx.accounts.token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
     let clock = Clock::get().unwrap();
-    let reserve_info = market.reserves().get_cached(reserve.index, clock.slot);
+    let reserve_info = market.reserves().get_cached(reserve.index, clock.slot)?;
 
     market.verify_ability_deposit_withdraw()?;
+    reserve.verify_ability_deposit_withdraw()?;
+
+    let liquidity = reserve.available_liquidity(ctx.accounts.vault.amount);
+    let (token_amount, note_amount) = withdrawal_amounts(
+        amount,
+        reserve_info,
+        liquidity,
+        ctx.accounts.deposit_note_account.amount,
+    )?;
 
-    // Calculate the number of tokens that the request amount is worth
-    let token_amount = amount.as_tokens(reserve_info, Rounding::Down);
-    let note_amount = amount.as_deposit_notes(reserve_info, Rounding::Up)?;
-
-    reserve.withdraw(token_amount, note_amount);
+    reserve.withdraw(token_amount, note_amount)?;
 
     // Transfer the tokens from the reserve, and burn the deposit notes
     token::transfer(
@@ -121,3 +141,70 @@ pub fn handler(ctx: Context<WithdrawTokens>, _bump: u8, amount: Amount) -> Resul
 
     Ok(())
 }
+
+/// The tokens paid out and deposit notes burned to withdraw `amount` from a
+/// deposit of `notes`, when the reserve has `liquidity` tokens available
+pub fn withdrawal_amounts(
+    amount: Amount,
+    reserve_info: &CachedReserveInfo,
+    liquidity: u64,
+    notes: u64,
+) -> Result<(u64, u64)> {
+    let basis = AmountBasis::Deposit {
+        notes,
+        available_notes: reserve_info.deposit_notes_from_tokens(liquidity, Rounding::Down),
+    };
+
+    // Calculate the number of tokens that the request amount is worth
+    let token_amount = amount.as_tokens(reserve_info, Rounding::Down, basis)?;
+    let note_amount = amount.as_deposit_notes(reserve_info, Rounding::Up, basis)?;
+
+    if token_amount > liquidity {
+        msg!("requested {} tokens with {} available", token_amount, liquidity);
+        return err!(ErrorCode::InsufficientLiquidity);
+    }
+
+    Ok((token_amount, note_amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+    use jet_math::Number;
+
+    use super::*;
+    use crate::common::AmountUnits;
+    use crate::test_utils::error_code;
+
+    #[test]
+    fn max_withdrawable_fits_the_liquidity_and_the_deposit() {
+        let max = Amount { units: AmountUnits::MaxWithdrawable, value: 0 };
+        let all = Amount { units: AmountUnits::All, value: 0 };
+
+        for deposit_note_rate in [Number::ONE, Number::from_bps(10_003), Number::from_bps(17_777)] {
+            let mut market = MarketReserves::zeroed();
+            market.register(&Pubkey::new_unique()).unwrap();
+            market.get_mut(0).cache.get_stale_mut().deposit_note_exchange_rate = deposit_note_rate;
+            let reserve_info = market.get_cached(0, 0).unwrap();
+
+            for (liquidity, notes) in [(1_000_000, 999_999), (333_333, 1_000_000), (0, 1_000), (7, 0)] {
+                let (tokens, burned) = withdrawal_amounts(max, reserve_info, liquidity, notes).unwrap();
+
+                assert!(tokens <= liquidity);
+                assert!(burned <= notes);
+                assert_eq!(tokens, reserve_info.deposit_notes_to_tokens(burned, Rounding::Down));
+
+                // one more note would be more than the reserve can pay
+                assert!(
+                    burned == notes
+                        || deposit_note_rate * Number::from(burned + 1) > Number::from(liquidity)
+                );
+            }
+
+            assert_eq!(
+                error_code(withdrawal_amounts(all, reserve_info, 333_333, 1_000_000).unwrap_err()),
+                ErrorCode::InsufficientLiquidity.into()
+            );
+        }
+    }
+}
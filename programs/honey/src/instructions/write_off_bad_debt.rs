@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, Mint, Token, TokenAccount, Transfer};
+
+use crate::common::Rounding;
+use crate::errors::ErrorCode;
+use crate::instructions::refresh_reserve_if_stale;
+use crate::state::*;
+
+#[event]
+pub struct BadDebtEvent {
+    pub reserve: Pubkey,
+    pub obligation: Pubkey,
+    pub debt: u64,
+    pub insured: u64,
+    pub written_off: u64,
+}
+
+#[derive(Accounts)]
+pub struct WriteOffBadDebt<'info> {
+    /// The market the bad debt is in
+    #[account(mut, has_one = market_authority)]
+    pub market: AccountLoader<'info, Market>,
+
+    /// The market's authority account
+    /// CHECK: market must have a market_authority account
+    pub market_authority: AccountInfo<'info>,
+
+    #[account(has_one = market,
+        constraint = roles.is_risk_authority(&market.load()?.owner, authority.key) @ ErrorCode::Unauthorized)]
+    pub roles: Box<Account<'info, MarketRoles>>,
+
+    /// The obligation with the debt being written off
+    #[account(mut,
+              has_one = market,
+              constraint = obligation.load()?.has_loan_custody(&loan_account.key()))]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    /// The reserve that the debt is from
+    #[account(mut,
+              has_one = market,
+              has_one = vault,
+              has_one = loan_note_mint)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// The reserve's vault, which the insurance fund pays into
+    #[account(mut)]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// The reserve's insurance fund, which covers the debt first. Until the
+    /// reserve has a fund, this can be any writable account, such as the
+    /// vault, and the whole debt is written off.
+    /// CHECK: checked against the reserve, and only used once it has a fund
+    #[account(mut,
+              constraint = reserve.load()?.is_insurance_fund(insurance_fund.key) @ ErrorCode::InvalidParameter)]
+    pub insurance_fund: AccountInfo<'info>,
+
+    /// The mint for the debt/loan notes
+    #[account(mut)]
+    pub loan_note_mint: Box<Account<'info, Mint>>,
+
+    /// The account that holds the borrower's debt balance
+    #[account(mut)]
+    pub loan_account: Box<Account<'info, TokenAccount>>,
+
+    /// The market owner or risk admin
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> WriteOffBadDebt<'info> {
+    fn note_burn_context(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Burn {
+                from: self.loan_account.to_account_info(),
+                mint: self.loan_note_mint.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+
+    fn insurance_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.insurance_fund.clone(),
+                to: self.vault.to_account_info(),
+                authority: self.market_authority.clone(),
+            },
+        )
+    }
+}
+
+/// Write off the debt of an obligation that has no collateral left to
+/// liquidate. The reserve's insurance fund covers as much of it as it can,
+/// and the rest is written down against the reserve's depositors.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, WriteOffBadDebt<'info>>) -> Result<()> {
+    let clock = Clock::get()?;
+    let accounts = &ctx.accounts;
+    let mut market = accounts.market.load_mut()?;
+    let mut reserve = accounts.reserve.load_mut()?;
+
+    refresh_reserve_if_stale(
+        &mut market,
+        &mut reserve,
+        &accounts.market_authority,
+        &accounts.token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
+    let mut obligation = accounts.obligation.load_mut()?;
+
+    if obligation.has_nft_collateral() {
+        return Err(ErrorCode::ObligationHasCollateral.into());
+    }
+
+    let reserve_info = market.reserves().get_cached(reserve.index, clock.slot)?;
+    let debt_notes = accounts.loan_account.amount;
+    let debt = std::cmp::min(
+        reserve_info.loan_notes_to_tokens(debt_notes, Rounding::Up),
+        reserve.outstanding_debt(clock.slot)?.as_u64(0),
+    );
+
+    if debt_notes == 0 {
+        return Err(ErrorCode::InvalidParameter.into());
+    }
+
+    let insurance_fund_balance = match reserve.has_insurance_fund() {
+        true => token::accessor::amount(&accounts.insurance_fund)?,
+        false => 0,
+    };
+    let cover = BadDebtCover::new(debt, insurance_fund_balance);
+
+    token::burn(
+        accounts
+            .note_burn_context()
+            .with_signer(&[&market.authority_seeds()]),
+        debt_notes,
+    )?;
+
+    if cover.insured > 0 {
+        token::transfer(
+            accounts
+                .insurance_transfer_context()
+                .with_signer(&[&market.authority_seeds()]),
+            cover.insured,
+        )?;
+    }
+
+    // The notes are gone without any tokens repaid for them, which the
+    // cover then accounts for.
+    reserve.repay(clock.slot, 0, debt_notes)?;
+    reserve.cover_bad_debt(clock.slot, &cover)?;
+
+    obligation.repay(&accounts.loan_account.key(), reserve.amount(debt_notes))?;
+
+    emit!(BadDebtEvent {
+        reserve: accounts.reserve.key(),
+        obligation: accounts.obligation.key(),
+        debt,
+        insured: cover.insured,
+        written_off: cover.written_off,
+    });
+
+    Ok(())
+}
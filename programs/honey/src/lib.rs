@@ -18,18 +18,22 @@
 #![cfg_attr(feature = "no-entrypoint", allow(dead_code))]
 
 use anchor_lang::prelude::*;
-#[cfg(not(feature = "testing"))]
-use anchor_lang::solana_program::pubkey;
 
 extern crate jet_proc_macros;
 extern crate static_assertions;
 
+pub mod cnft;
 pub mod common;
 pub mod errors;
 pub mod instructions;
+pub mod pnft;
 pub mod state;
 pub mod utils;
 
+#[cfg(test)]
+mod test_utils;
+
+use cnft::CompressedNft;
 use common::Amount;
 use common::Rounding;
 use instructions::*;
@@ -37,9 +41,6 @@ use state::*;
 
 declare_id!("F1PypuidC78bosb7cHfU2ERZSd1RWLdbsq82nR9Tdgkh");
 
-#[cfg(not(feature = "testing"))]
-static ROOT_AUTHORITY: Pubkey = pubkey!("2J2K1wHK3U8bsow1shUZJvEx1L2og2h5T5JGPqBS1uKA");
-
 #[derive(Clone)]
 pub struct Honey;
 
@@ -49,6 +50,16 @@ impl anchor_lang::Id for Honey {
     }
 }
 
+/// Instructions that can refresh a stale reserve themselves (`deposit_tokens`,
+/// `withdraw_tokens`, `fill_withdrawal`, `borrow`, `repay`, `flash_repay`,
+/// `write_off_bad_debt` and `refinance`) take the reserve's price aggregator,
+/// fee note vault, protocol fee note vault and deposit note mint as trailing
+/// accounts, in that order, or no trailing accounts at all. `refinance` takes
+/// one such set per reserve.
+///
+/// The trailing accounts of the NFT instructions (the Token Metadata accounts
+/// of programmable and frozen NFTs, and the proofs of compressed NFTs) belong
+/// to instructions that never refresh reserves, so the layouts don't combine.
 #[program]
 mod honey {
     use super::*;
@@ -87,6 +98,17 @@ mod honey {
         instructions::update_reserve_config::handler(ctx, new_config)
     }
 
+    /// Close a reserve that has been wound down and emptied, along with its
+    /// vault, fee note vaults and insurance fund, freeing its slot in the market
+    pub fn close_reserve(ctx: Context<CloseReserve>) -> Result<()> {
+        instructions::close_reserve::handler(ctx)
+    }
+
+    /// Create the insurance fund for a reserve that was initialized without one
+    pub fn init_insurance_fund(ctx: Context<InitializeInsuranceFund>) -> Result<()> {
+        instructions::init_insurance_fund::handler(ctx)
+    }
+
     /// Initialize an account that can be used to store deposit notes
     pub fn init_deposit_account(ctx: Context<InitializeDepositAccount>, bump: u8) -> Result<()> {
         instructions::init_deposit_account::handler(ctx, bump)
@@ -98,13 +120,67 @@ mod honey {
     }
 
     /// Initialize an account that can be used to borrow from a reserve
-    pub fn init_obligation(ctx: Context<InitializeObligation>, bump: u8) -> Result<()> {
-        instructions::init_obligation::handler(ctx, bump)
+    pub fn init_obligation(ctx: Context<InitializeObligation>) -> Result<()> {
+        instructions::init_obligation::handler(ctx)
+    }
+
+    /// Offer an obligation, with its collateral and debt, to a new owner
+    pub fn transfer_obligation(ctx: Context<TransferObligation>, new_owner: Pubkey) -> Result<()> {
+        instructions::transfer_obligation::handler(ctx, new_owner)
+    }
+
+    /// Accept an obligation offered by its owner
+    pub fn accept_obligation_transfer(ctx: Context<AcceptObligationTransfer>) -> Result<()> {
+        instructions::accept_obligation_transfer::handler(ctx)
+    }
+
+    /// Withdraw an offer to transfer an obligation
+    pub fn cancel_obligation_transfer(ctx: Context<CancelObligationTransfer>) -> Result<()> {
+        instructions::cancel_obligation_transfer::handler(ctx)
+    }
+
+    /// Allow an address to manage an obligation on the owner's behalf
+    pub fn set_obligation_delegate(
+        ctx: Context<SetObligationDelegate>,
+        delegate: Pubkey,
+        permissions: u64,
+    ) -> Result<()> {
+        instructions::set_obligation_delegate::handler(ctx, delegate, permissions)
+    }
+
+    /// Remove an obligation's delegate
+    pub fn revoke_obligation_delegate(ctx: Context<RevokeObligationDelegate>) -> Result<()> {
+        instructions::revoke_obligation_delegate::handler(ctx)
+    }
+
+    /// Propose a new owner for a market, which must accept before taking control
+    pub fn propose_market_owner(ctx: Context<ProposeMarketOwner>, new_owner: Pubkey) -> Result<()> {
+        instructions::propose_market_owner::handler(ctx, new_owner)
+    }
+
+    /// Accept ownership of a market as the proposed owner
+    pub fn accept_market_owner(ctx: Context<AcceptMarketOwner>) -> Result<()> {
+        instructions::accept_market_owner::handler(ctx)
+    }
+
+    /// Withdraw a pending ownership change on a market
+    pub fn cancel_market_owner(ctx: Context<CancelMarketOwner>) -> Result<()> {
+        instructions::cancel_market_owner::handler(ctx)
+    }
+
+    /// Close an obligation with no loans or collateral left registered
+    pub fn close_obligation(ctx: Context<CloseObligation>) -> Result<()> {
+        instructions::close_obligation::handler(ctx)
     }
 
-    /// Change the owner on a market
-    pub fn set_market_owner(ctx: Context<SetMarketOwner>, new_owner: Pubkey) -> Result<()> {
-        instructions::set_market_owner::handler(ctx, new_owner)
+    /// Close a repaid loan account, unregistering it from its obligation
+    pub fn close_loan_account(ctx: Context<CloseLoanAccount>) -> Result<()> {
+        instructions::close_loan_account::handler(ctx)
+    }
+
+    /// Close an emptied deposit account
+    pub fn close_deposit_account(ctx: Context<CloseDepositAccount>) -> Result<()> {
+        instructions::close_deposit_account::handler(ctx)
     }
 
     /// Change the flags on a market
@@ -112,36 +188,174 @@ mod honey {
         instructions::set_market_flags::handler(ctx, flags)
     }
 
+    /// Change the flags on a reserve
+    pub fn set_reserve_flags(ctx: Context<SetReserveFlags>, flags: u64) -> Result<()> {
+        instructions::set_reserve_flags::handler(ctx, flags)
+    }
+
+    /// Initialize the account holding the delegated roles for a market
+    pub fn init_market_roles(ctx: Context<InitializeMarketRoles>) -> Result<()> {
+        instructions::init_market_roles::handler(ctx)
+    }
+
+    /// Assign a delegated role on a market
+    pub fn set_market_role(
+        ctx: Context<SetMarketRole>,
+        role: MarketRole,
+        holder: Pubkey,
+    ) -> Result<()> {
+        instructions::set_market_role::handler(ctx, role, holder)
+    }
+
+    /// Halt operations on a market (owner or guardian)
+    pub fn pause_market(ctx: Context<PauseMarket>, flags: u64) -> Result<()> {
+        instructions::pause_market::handler(ctx, flags)
+    }
+
+    /// Halt operations on a reserve (owner or guardian)
+    pub fn pause_reserve(ctx: Context<PauseReserve>, flags: u64) -> Result<()> {
+        instructions::pause_reserve::handler(ctx, flags)
+    }
+
+    /// Change how many slots cached reserve data stays fresh for
+    pub fn set_reserve_cache_ttl(ctx: Context<SetReserveCacheTtl>, ttl: u64) -> Result<()> {
+        instructions::set_reserve_cache_ttl::handler(ctx, ttl)
+    }
+
     /// Deposit tokens into a reserve (unmanaged)
-    pub fn deposit_tokens(ctx: Context<DepositTokens>, bump: u8, amount: Amount) -> Result<()> {
+    pub fn deposit_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, DepositTokens<'info>>,
+        bump: u8,
+        amount: Amount,
+    ) -> Result<()> {
         instructions::deposit_tokens::handler(ctx, bump, amount)
     }
 
     /// Withdraw tokens from a reserve (unmanaged)
-    pub fn withdraw_tokens(ctx: Context<WithdrawTokens>, bump: u8, amount: Amount) -> Result<()> {
+    pub fn withdraw_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawTokens<'info>>,
+        bump: u8,
+        amount: Amount,
+    ) -> Result<()> {
         instructions::withdraw_tokens::handler(ctx, bump, amount)
     }
 
+    /// Queue a withdrawal to be filled as the reserve regains liquidity
+    pub fn queue_withdrawal(ctx: Context<QueueWithdrawal>, notes: u64) -> Result<()> {
+        instructions::queue_withdrawal::handler(ctx, notes)
+    }
+
+    /// Fill as much of a queued withdrawal as the reserve has liquidity for
+    pub fn fill_withdrawal<'info>(
+        ctx: Context<'_, '_, '_, 'info, FillWithdrawal<'info>>,
+    ) -> Result<()> {
+        instructions::fill_withdrawal::handler(ctx)
+    }
+
+    /// Close a filled withdrawal request, returning its rent to the depositor (permissionless)
+    pub fn close_withdrawal(ctx: Context<CloseWithdrawal>) -> Result<()> {
+        instructions::close_withdrawal::handler(ctx)
+    }
+
+    /// Cancel the rest of a queued withdrawal
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
+        instructions::cancel_withdrawal::handler(ctx)
+    }
+
     /// Deposit notes as collateral in an obligation
     pub fn deposit_nft(ctx: Context<DepositNFT>, metadata_bump: u8) -> Result<()> {
         instructions::deposit_nft::handler(ctx, metadata_bump)
     }
 
-    /// Withdraw notes previously deposited as collateral in an obligation
-    pub fn withdraw_nft(ctx: Context<WithdrawNFT>, metadata_bump: u8) -> Result<()> {
+    /// Pledge an NFT as collateral while leaving it frozen in the owner's wallet
+    pub fn deposit_nft_escrowless(
+        ctx: Context<DepositNFTEscrowless>,
+        metadata_bump: u8,
+    ) -> Result<()> {
+        instructions::deposit_nft_escrowless::handler(ctx, metadata_bump)
+    }
+
+    /// Withdraw notes previously deposited as collateral in an obligation.
+    /// NFTs frozen in the owner's wallet take the edition and Token Metadata
+    /// program as trailing accounts.
+    pub fn withdraw_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawNFT<'info>>,
+        metadata_bump: u8,
+    ) -> Result<()> {
         instructions::withdraw_nft::handler(ctx, metadata_bump)
     }
 
+    /// Deposit a compressed NFT as collateral in an obligation, with the
+    /// leaf's proof nodes as trailing accounts
+    pub fn deposit_cnft<'info>(
+        ctx: Context<'_, '_, '_, 'info, DepositCNFT<'info>>,
+        nft: CompressedNft,
+    ) -> Result<()> {
+        instructions::deposit_cnft::handler(ctx, nft)
+    }
+
+    /// Withdraw a compressed NFT previously deposited as collateral in an
+    /// obligation, with the leaf's proof nodes as trailing accounts
+    pub fn withdraw_cnft<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawCNFT<'info>>,
+        nft: CompressedNft,
+    ) -> Result<()> {
+        instructions::withdraw_cnft::handler(ctx, nft)
+    }
+
+    /// Deposit a programmable NFT as collateral in an obligation
+    pub fn deposit_pnft(ctx: Context<DepositPNFT>, metadata_bump: u8) -> Result<()> {
+        instructions::deposit_pnft::handler(ctx, metadata_bump)
+    }
+
+    /// Withdraw a programmable NFT previously deposited as collateral in an obligation
+    pub fn withdraw_pnft(ctx: Context<WithdrawPNFT>, metadata_bump: u8) -> Result<()> {
+        instructions::withdraw_pnft::handler(ctx, metadata_bump)
+    }
+
+    /// Replace an NFT pledged as collateral with another without repaying
+    pub fn swap_nft_collateral(ctx: Context<SwapNFTCollateral>, metadata_bump: u8) -> Result<()> {
+        instructions::swap_nft_collateral::handler(ctx, metadata_bump)
+    }
+
     /// Borrow tokens from a reserve
-    pub fn borrow(ctx: Context<Borrow>, bump: BorrowBumpSeeds, amount: Amount) -> Result<()> {
-        instructions::borrow::handler(ctx, bump, amount)
+    pub fn borrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, Borrow<'info>>,
+        amount: Amount,
+    ) -> Result<()> {
+        instructions::borrow::handler(ctx, amount)
     }
 
     /// Repay a loan
-    pub fn repay(ctx: Context<Repay>, amount: Amount) -> Result<()> {
+    pub fn repay<'info>(ctx: Context<'_, '_, '_, 'info, Repay<'info>>, amount: Amount) -> Result<()> {
         instructions::repay::handler(ctx, amount)
     }
 
+    /// Borrow tokens to be repaid by `flash_repay` later in the same transaction
+    pub fn flash_borrow(ctx: Context<FlashBorrow>, amount: u64) -> Result<()> {
+        instructions::flash_borrow::handler(ctx, amount)
+    }
+
+    /// Repay a reserve's flash loan along with its fee
+    pub fn flash_repay<'info>(ctx: Context<'_, '_, '_, 'info, FlashRepay<'info>>) -> Result<()> {
+        instructions::flash_repay::handler(ctx)
+    }
+
+    /// Move a loan into another reserve of the same token, keeping the
+    /// obligation's collateral in place. Takes no trailing accounts, the
+    /// 4 refresh accounts of the old reserve, or those followed by the 4
+    /// refresh accounts of the new reserve.
+    pub fn refinance<'info>(ctx: Context<'_, '_, '_, 'info, Refinance<'info>>) -> Result<()> {
+        instructions::refinance::handler(ctx)
+    }
+
+    /// Repay a loan in full and withdraw an NFT from the obligation in one go
+    pub fn repay_and_withdraw_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, RepayAndWithdrawNFT<'info>>,
+    ) -> Result<()> {
+        instructions::repay_and_withdraw_nft::handler(ctx)
+    }
+
     /// liquidate through solvent droplets
     pub fn liquidate_solvent(ctx: Context<LiquidateSolvent>, amount: Amount) -> Result<()> {
         instructions::liquidate_solvent::handler(ctx, amount)
@@ -156,8 +370,18 @@ mod honey {
         ctx: Context<PlaceLiquidateBid>,
         bump: PlaceLiquidateBidBumps,
         bid_limit: u64,
+        expires_at: i64,
+        max_price_bps: Option<u16>,
+        target: Option<Pubkey>,
     ) -> Result<()> {
-        instructions::place_liquidate_bid::handler(ctx, bump, bid_limit)
+        instructions::place_liquidate_bid::handler(
+            ctx,
+            bump,
+            bid_limit,
+            expires_at,
+            max_price_bps,
+            target,
+        )
     }
 
     pub fn increase_liquidate_bid(
@@ -175,18 +399,69 @@ mod honey {
         instructions::revoke_liquidate_bid::handler(ctx, bump)
     }
 
-    pub fn execute_liquidate_bid(
-        ctx: Context<ExecuteLiquidateBid>,
+    /// Refund an expired bid to its bidder (permissionless)
+    pub fn crank_expired_bid(ctx: Context<CrankExpiredBid>) -> Result<()> {
+        instructions::crank_expired_bid::handler(ctx)
+    }
+
+    /// Upgrade a bid placed before bids had an expiry, max price or target
+    /// (permissionless)
+    pub fn migrate_bid(ctx: Context<MigrateBid>) -> Result<()> {
+        instructions::migrate_bid::handler(ctx)
+    }
+
+    /// Liquidate an unhealthy obligation with a bid. Programmable NFTs take
+    /// their Token Metadata accounts as trailing accounts.
+    pub fn execute_liquidate_bid<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteLiquidateBid<'info>>,
         bump: ExecuteLiquidateBidBumps,
     ) -> Result<()> {
         instructions::execute_liquidate_bid::handler(ctx, bump)
     }
 
+    /// Liquidate with a bid that can't cover the debt, writing off the
+    /// shortfall (solvent executor only)
+    pub fn liquidate_bad_debt<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteLiquidateBid<'info>>,
+        bump: ExecuteLiquidateBidBumps,
+    ) -> Result<()> {
+        instructions::execute_liquidate_bid::bad_debt_handler(ctx, bump)
+    }
+
+    /// Liquidate an unhealthy obligation holding a compressed NFT with a
+    /// bid, with the leaf's proof nodes as trailing accounts
+    pub fn execute_liquidate_bid_cnft<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteLiquidateBidCNFT<'info>>,
+        nft: CompressedNft,
+    ) -> Result<()> {
+        instructions::execute_liquidate_bid_cnft::handler(ctx, nft)
+    }
+
+    /// Liquidate an obligation holding a compressed NFT with a bid that
+    /// can't cover the debt (solvent executor only)
+    pub fn liquidate_bad_debt_cnft<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteLiquidateBidCNFT<'info>>,
+        nft: CompressedNft,
+    ) -> Result<()> {
+        instructions::execute_liquidate_bid_cnft::compressed_bad_debt_handler(ctx, nft)
+    }
+
+    /// Write off the debt of an obligation with no collateral left, covering
+    /// it from the insurance fund before writing it down against depositors
+    pub fn write_off_bad_debt<'info>(
+        ctx: Context<'_, '_, '_, 'info, WriteOffBadDebt<'info>>,
+    ) -> Result<()> {
+        instructions::write_off_bad_debt::handler(ctx)
+    }
+
     /// Refresh a reserve's market price and interest owed
     ///
     /// If the reserve is extremely stale, only a partial update will be
     /// performed. It may be necessary to call refresh_reserve multiple
     /// times to get the reserve up to date.
+    ///
+    /// `deposit_tokens`, `withdraw_tokens`, `borrow` and `repay` perform this
+    /// refresh themselves when given the reserve's refresh accounts.
     pub fn refresh_reserve(ctx: Context<RefreshReserve>) -> Result<()> {
         instructions::refresh_reserve::handler(ctx)
     }
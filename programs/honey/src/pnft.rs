@@ -0,0 +1,329 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Support for Metaplex programmable NFTs, whose token accounts stay frozen
+//! so they can only be moved by Token Metadata's `Transfer` instruction.
+//!
+//! The vendored `mpl-token-metadata` crate predates pNFTs, so the parts of the
+//! metadata and instruction layouts needed here are decoded by hand.
+//!
+//! pNFTs are always escrowed with the market authority. Keeping them in the
+//! owner's wallet through Token Metadata's `Delegate` and `Lock`, the way
+//! `deposit_nft_escrowless` freezes regular NFTs in place, isn't supported,
+//! so a pNFT whose rule set won't let it move to the market can't be
+//! deposited.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use mpl_token_metadata::state::{Collection, CollectionDetails, Data, Key, Uses};
+use mpl_token_metadata::ID as metadata_program_id;
+
+use crate::errors::ErrorCode;
+
+/// The Metaplex program that evaluates the rule sets pNFT transfers are checked against
+pub mod authorization_rules_program {
+    anchor_lang::declare_id!("auth9SigNpDKz4sJJ1DfCTuZrZNSAgh9sFD3rboVmgg");
+}
+
+/// The `TokenStandard` Token Metadata gives programmable NFTs
+const PROGRAMMABLE_NON_FUNGIBLE: u8 = 4;
+
+/// The index of `Transfer` in Token Metadata's instruction enum
+const TRANSFER_INSTRUCTION: u8 = 49;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+enum ProgrammableConfigRecord {
+    V1 { rule_set: Option<Pubkey> },
+}
+
+/// The parts of a programmable NFT's metadata that govern its transfers
+#[derive(Debug, PartialEq)]
+pub struct ProgrammableConfig {
+    /// The mint the metadata is for
+    pub mint: Pubkey,
+
+    /// The rule set every transfer must satisfy, if the NFT has one
+    pub rule_set: Option<Pubkey>,
+}
+
+impl ProgrammableConfig {
+    /// Read the config from a metadata account, failing unless it's
+    /// a Token Metadata account for a programmable NFT of the mint
+    pub fn load(metadata: &AccountInfo, mint: &Pubkey) -> Result<Self> {
+        if *metadata.owner != metadata_program_id {
+            return err!(ErrorCode::InvalidMetadata);
+        }
+
+        let config = Self::parse(&metadata.try_borrow_data()?)?;
+        if config.mint != *mint {
+            return err!(ErrorCode::InvalidMetadata);
+        }
+
+        Ok(config)
+    }
+
+    fn parse(mut data: &[u8]) -> Result<Self> {
+        let buf = &mut data;
+        let read = |buf: &mut &[u8]| -> Result<Option<u8>> {
+            Option::<u8>::deserialize(buf).map_err(|_| error!(ErrorCode::InvalidMetadata))
+        };
+
+        let key = Key::deserialize(buf).map_err(|_| error!(ErrorCode::InvalidMetadata))?;
+        if key != Key::MetadataV1 {
+            return err!(ErrorCode::InvalidMetadata);
+        }
+
+        let (_update_authority, mint, _data, _primary_sale_happened, _is_mutable) =
+            <(Pubkey, Pubkey, Data, bool, bool)>::deserialize(buf)
+                .map_err(|_| error!(ErrorCode::InvalidMetadata))?;
+        let _edition_nonce = read(buf)?;
+
+        if read(buf)? != Some(PROGRAMMABLE_NON_FUNGIBLE) {
+            return err!(ErrorCode::NotProgrammableNft);
+        }
+
+        let (_collection, _uses, _collection_details, programmable_config) = <(
+            Option<Collection>,
+            Option<Uses>,
+            Option<CollectionDetails>,
+            Option<ProgrammableConfigRecord>,
+        )>::deserialize(buf)
+        .map_err(|_| error!(ErrorCode::InvalidMetadata))?;
+
+        let rule_set = match programmable_config {
+            Some(ProgrammableConfigRecord::V1 { rule_set }) => rule_set,
+            None => None,
+        };
+
+        Ok(Self { mint, rule_set })
+    }
+}
+
+/// The accounts for Token Metadata to move a programmable NFT between token accounts
+pub struct PnftTransfer<'a, 'info> {
+    pub token: &'a AccountInfo<'info>,
+    pub token_owner: &'a AccountInfo<'info>,
+    pub destination: &'a AccountInfo<'info>,
+    pub destination_owner: &'a AccountInfo<'info>,
+    pub mint: &'a AccountInfo<'info>,
+    pub metadata: &'a AccountInfo<'info>,
+    pub edition: &'a AccountInfo<'info>,
+    pub owner_token_record: &'a AccountInfo<'info>,
+    pub destination_token_record: &'a AccountInfo<'info>,
+    pub authority: &'a AccountInfo<'info>,
+    pub payer: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub sysvar_instructions: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub associated_token_program: &'a AccountInfo<'info>,
+    pub token_metadata_program: &'a AccountInfo<'info>,
+    pub authorization_rules_program: &'a AccountInfo<'info>,
+    pub authorization_rules: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> PnftTransfer<'a, 'info> {
+    /// Move the NFT, after checking the accounts are the ones Token Metadata
+    /// derives for it and that the rule set is the one the NFT requires
+    pub fn invoke_signed(&self, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let config = ProgrammableConfig::load(self.metadata, self.mint.key)?;
+        self.verify_accounts()?;
+
+        let uses_rule_set = match config.rule_set {
+            Some(rule_set) => {
+                if *self.authorization_rules.key != rule_set
+                    || *self.authorization_rules_program.key != authorization_rules_program::ID
+                {
+                    return err!(ErrorCode::RuleSetMismatch);
+                }
+                true
+            }
+            None => false,
+        };
+
+        // Token Metadata takes its own id in place of optional accounts left out
+        let optional = |account: &AccountInfo, present: bool| match present {
+            true => AccountMeta::new_readonly(*account.key, false),
+            false => AccountMeta::new_readonly(metadata_program_id, false),
+        };
+
+        let accounts = vec![
+            AccountMeta::new(*self.token.key, false),
+            AccountMeta::new_readonly(*self.token_owner.key, false),
+            AccountMeta::new(*self.destination.key, false),
+            AccountMeta::new_readonly(*self.destination_owner.key, false),
+            AccountMeta::new_readonly(*self.mint.key, false),
+            AccountMeta::new(*self.metadata.key, false),
+            AccountMeta::new_readonly(*self.edition.key, false),
+            AccountMeta::new(*self.owner_token_record.key, false),
+            AccountMeta::new(*self.destination_token_record.key, false),
+            AccountMeta::new_readonly(*self.authority.key, true),
+            AccountMeta::new(*self.payer.key, true),
+            AccountMeta::new_readonly(*self.system_program.key, false),
+            AccountMeta::new_readonly(*self.sysvar_instructions.key, false),
+            AccountMeta::new_readonly(*self.token_program.key, false),
+            AccountMeta::new_readonly(*self.associated_token_program.key, false),
+            optional(self.authorization_rules_program, uses_rule_set),
+            optional(self.authorization_rules, uses_rule_set),
+        ];
+
+        invoke_signed(
+            &Instruction {
+                program_id: metadata_program_id,
+                accounts,
+                data: transfer_instruction_data(1),
+            },
+            &[
+                self.token.clone(),
+                self.token_owner.clone(),
+                self.destination.clone(),
+                self.destination_owner.clone(),
+                self.mint.clone(),
+                self.metadata.clone(),
+                self.edition.clone(),
+                self.owner_token_record.clone(),
+                self.destination_token_record.clone(),
+                self.authority.clone(),
+                self.payer.clone(),
+                self.system_program.clone(),
+                self.sysvar_instructions.clone(),
+                self.token_program.clone(),
+                self.associated_token_program.clone(),
+                self.token_metadata_program.clone(),
+                self.authorization_rules_program.clone(),
+                self.authorization_rules.clone(),
+            ],
+            signer_seeds,
+        )?;
+
+        Ok(())
+    }
+
+    fn verify_accounts(&self) -> Result<()> {
+        if *self.token_metadata_program.key != metadata_program_id
+            || *self.sysvar_instructions.key != anchor_lang::solana_program::sysvar::instructions::ID
+        {
+            msg!("the token metadata program or instructions sysvar is wrong");
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        let mint = self.mint.key;
+        if *self.edition.key != edition_address(mint)
+            || *self.owner_token_record.key != token_record_address(mint, self.token.key)
+            || *self.destination_token_record.key != token_record_address(mint, self.destination.key)
+        {
+            msg!("the edition or token records are not the ones for the nft");
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        Ok(())
+    }
+}
+
+/// The master edition Token Metadata keeps for an NFT
+pub fn edition_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", metadata_program_id.as_ref(), mint.as_ref(), b"edition"],
+        &metadata_program_id,
+    )
+    .0
+}
+
+/// The record Token Metadata keeps of a pNFT's state in one token account
+pub fn token_record_address(mint: &Pubkey, token: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            metadata_program_id.as_ref(),
+            mint.as_ref(),
+            b"token_record",
+            token.as_ref(),
+        ],
+        &metadata_program_id,
+    )
+    .0
+}
+
+/// `Transfer(TransferArgs::V1 { amount, authorization_data: None })`
+fn transfer_instruction_data(amount: u64) -> Vec<u8> {
+    let mut data = vec![TRANSFER_INSTRUCTION, 0];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(0);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::error_code;
+
+    fn metadata(token_standard: Option<u8>, rule_set: Option<Option<Pubkey>>) -> (Pubkey, Vec<u8>) {
+        let mint = Pubkey::new_unique();
+        let mut data = Vec::new();
+
+        (Key::MetadataV1, Pubkey::new_unique(), mint, Data::default(), true, true, Some(255u8))
+            .serialize(&mut data)
+            .unwrap();
+        token_standard.serialize(&mut data).unwrap();
+        (None::<Collection>, None::<Uses>, None::<CollectionDetails>)
+            .serialize(&mut data)
+            .unwrap();
+        rule_set
+            .map(|rule_set| ProgrammableConfigRecord::V1 { rule_set })
+            .serialize(&mut data)
+            .unwrap();
+
+        // metadata accounts are allocated with room to spare
+        data.resize(data.len() + 64, 0);
+
+        (mint, data)
+    }
+
+
+    #[test]
+    fn reads_the_rule_set_of_programmable_nfts() {
+        let rule_set = Pubkey::new_unique();
+        let (mint, data) = metadata(Some(PROGRAMMABLE_NON_FUNGIBLE), Some(Some(rule_set)));
+        assert_eq!(
+            ProgrammableConfig::parse(&data).unwrap(),
+            ProgrammableConfig { mint, rule_set: Some(rule_set) }
+        );
+
+        let (mint, data) = metadata(Some(PROGRAMMABLE_NON_FUNGIBLE), None);
+        assert_eq!(
+            ProgrammableConfig::parse(&data).unwrap(),
+            ProgrammableConfig { mint, rule_set: None }
+        );
+    }
+
+    #[test]
+    fn rejects_other_token_standards() {
+        for token_standard in [None, Some(0), Some(3)] {
+            let (_, data) = metadata(token_standard, None);
+            let error = ProgrammableConfig::parse(&data).unwrap_err();
+            assert_eq!(error_code(error), ErrorCode::NotProgrammableNft.into());
+        }
+    }
+
+    #[test]
+    fn encodes_transfers_of_one_token() {
+        assert_eq!(
+            transfer_instruction_data(1),
+            vec![49, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+}
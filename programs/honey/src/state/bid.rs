@@ -1,5 +1,8 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use jet_math::Number;
 
+use crate::errors::ErrorCode;
 
 #[account]
 pub struct Bid {
@@ -10,7 +13,18 @@ pub struct Bid {
     pub authority_bump_seed: [u8; 1],
     pub authority_seed: Pubkey,
     pub bidder: Pubkey,
-    pub bid_limit: u64
+    pub bid_limit: u64,
+
+    /// The time from which the bid can no longer be executed, and anyone
+    /// may refund it to the bidder
+    pub expires_at: i64,
+
+    /// The most the bidder will pay for an NFT, in basis points of the
+    /// oracle floor price
+    pub max_price_bps: Option<u16>,
+
+    /// The only obligation or NFT mint the bid may be used to liquidate
+    pub target: Option<Pubkey>
 }
 
 impl Default for Bid {
@@ -23,15 +37,226 @@ impl Default for Bid {
             authority_bump_seed: [0; 1],
             authority_seed: Pubkey::default(),
             bidder: Pubkey::default(),
-            bid_limit: 0
+            bid_limit: 0,
+            expires_at: 0,
+            max_price_bps: None,
+            target: None
+        }
+    }
+}
+
+/// The layout of bids placed before they had an expiry, max price or
+/// target, whose accounts are too small to hold a `Bid` until `migrate_bid`
+/// upgrades them
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyBid {
+    pub market: Pubkey,
+    pub bid_escrow: Pubkey,
+    pub bid_escrow_authority: Pubkey,
+    pub bid_mint: Pubkey,
+    pub authority_bump_seed: [u8; 1],
+    pub authority_seed: Pubkey,
+    pub bidder: Pubkey,
+    pub bid_limit: u64
+}
+
+impl From<LegacyBid> for Bid {
+    /// Legacy bids never expired and could liquidate any NFT at any price,
+    /// which the upgraded bid keeps doing
+    fn from(legacy: LegacyBid) -> Self {
+        Bid {
+            market: legacy.market,
+            bid_escrow: legacy.bid_escrow,
+            bid_escrow_authority: legacy.bid_escrow_authority,
+            bid_mint: legacy.bid_mint,
+            authority_bump_seed: legacy.authority_bump_seed,
+            authority_seed: legacy.authority_seed,
+            bidder: legacy.bidder,
+            bid_limit: legacy.bid_limit,
+            expires_at: i64::MAX,
+            max_price_bps: None,
+            target: None
         }
     }
 }
 
 impl Bid {
+    /// The space allocated for a bid account
+    pub const SPACE: usize = 8 + std::mem::size_of::<Bid>();
+
+    /// Read a bid still in the legacy layout from its account data. Fails
+    /// for any other account, including bids that were already upgraded.
+    pub fn load_legacy(data: &[u8]) -> Result<LegacyBid> {
+        if data.len() < 8 || data[..8] != Bid::discriminator() {
+            return err!(ErrorCode::InvalidParameter);
+        }
+        if data.len() >= Bid::SPACE {
+            msg!("the bid is already upgraded");
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        LegacyBid::deserialize(&mut &data[8..]).map_err(|_| error!(ErrorCode::InvalidParameter))
+    }
+
     /// Gets the authority seeds for signing requests with the
     /// market authority address.
     pub fn authority_seeds(&self) -> [&[u8]; 2] {
         [self.authority_seed.as_ref(), &self.authority_bump_seed]
     }
-}
\ No newline at end of file
+
+    pub fn is_expired(&self, current_time: i64) -> bool {
+        current_time >= self.expires_at
+    }
+
+    /// Verify the bid can be used to liquidate the NFT in an obligation
+    pub fn verify_executable(
+        &self,
+        current_time: i64,
+        obligation: &Pubkey,
+        nft_mint: &Pubkey,
+    ) -> Result<()> {
+        if self.is_expired(current_time) {
+            msg!("the bid expired at {}", self.expires_at);
+            return err!(ErrorCode::BidExpired);
+        }
+
+        match self.target {
+            Some(target) if target != *obligation && target != *nft_mint => {
+                msg!("the bid is restricted to {}", target);
+                err!(ErrorCode::BidTargetMismatch)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The most the bidder can pay for an NFT with the given floor price
+    pub fn limit(&self, escrow_balance: u64, floor_price: u64) -> u64 {
+        let max_price = match self.max_price_bps {
+            Some(bps) => (Number::from_bps(bps) * floor_price).as_u64(0),
+            None => u64::MAX,
+        };
+
+        std::cmp::min(escrow_balance, max_price)
+    }
+
+    /// The price the bidder pays for an NFT, which is the liquidation price
+    /// as long as the bidder is willing and able to pay it. All amounts are
+    /// in the bid mint's tokens.
+    pub fn clearing_price(
+        &self,
+        escrow_balance: u64,
+        floor_price: u64,
+        liquidation_price: u64,
+    ) -> Result<u64> {
+        let limit = self.limit(escrow_balance, floor_price);
+
+        if limit < liquidation_price {
+            msg!("the bid limit {} can't cover the price {}", limit, liquidation_price);
+            return err!(ErrorCode::LiquidationLowCollateral);
+        }
+
+        Ok(liquidation_price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::error_code;
+
+    #[test]
+    fn bids_expire() {
+        let bid = Bid {
+            expires_at: 100,
+            ..Bid::default()
+        };
+        let obligation = Pubkey::new_unique();
+        let nft_mint = Pubkey::new_unique();
+
+        assert!(bid.verify_executable(99, &obligation, &nft_mint).is_ok());
+        assert_eq!(
+            error_code(bid.verify_executable(100, &obligation, &nft_mint).unwrap_err()),
+            ErrorCode::BidExpired.into()
+        );
+        assert!(bid.is_expired(100));
+    }
+
+    #[test]
+    fn bids_only_execute_against_their_target() {
+        let obligation = Pubkey::new_unique();
+        let nft_mint = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        for target in [obligation, nft_mint] {
+            let bid = Bid {
+                expires_at: i64::MAX,
+                target: Some(target),
+                ..Bid::default()
+            };
+
+            assert!(bid.verify_executable(0, &obligation, &nft_mint).is_ok());
+            assert_eq!(
+                error_code(bid.verify_executable(0, &other, &other).unwrap_err()),
+                ErrorCode::BidTargetMismatch.into()
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_bids_upgrade_without_expiry_or_limits() {
+        let bidder = Pubkey::new_unique();
+        let legacy = LegacyBid {
+            market: Pubkey::new_unique(),
+            bid_escrow: Pubkey::new_unique(),
+            bid_escrow_authority: Pubkey::new_unique(),
+            bid_mint: Pubkey::new_unique(),
+            authority_bump_seed: [254],
+            authority_seed: Pubkey::new_unique(),
+            bidder,
+            bid_limit: 1_000,
+        };
+
+        // accounts were sized for the legacy struct, with its padding
+        let mut data = vec![0; 8 + 208];
+        data[..8].copy_from_slice(&Bid::discriminator());
+        legacy.serialize(&mut &mut data[8..]).unwrap();
+        assert!(Bid::try_deserialize(&mut data.as_slice()).is_err());
+
+        let bid = Bid::from(Bid::load_legacy(&data).unwrap());
+        assert_eq!((bid.bidder, bid.bid_limit, bid.authority_bump_seed), (bidder, 1_000, [254]));
+        assert!(!bid.is_expired(i64::MAX - 1));
+        assert_eq!(bid.limit(500, 1), 500);
+        assert!(bid.target.is_none());
+
+        // once upgraded, the bid can't be migrated again
+        let mut upgraded = vec![0; Bid::SPACE];
+        bid.try_serialize(&mut upgraded.as_mut_slice()).unwrap();
+        assert!(Bid::try_deserialize(&mut upgraded.as_slice()).is_ok());
+        assert!(Bid::load_legacy(&upgraded).is_err());
+    }
+
+    #[test]
+    fn clearing_price_leaves_the_rest_of_the_escrow() {
+        let bid = Bid::default();
+
+        // the floor price doesn't change what is charged
+        assert_eq!(bid.clearing_price(1_000, 600, 400).unwrap(), 400);
+        assert_eq!(bid.clearing_price(1_000, 300, 400).unwrap(), 400);
+        assert_eq!(bid.clearing_price(400, 600, 400).unwrap(), 400);
+        assert!(bid.clearing_price(399, 600, 400).is_err());
+    }
+
+    #[test]
+    fn clearing_price_respects_max_price() {
+        let bid = Bid {
+            max_price_bps: Some(8_000),
+            ..Bid::default()
+        };
+
+        assert_eq!(bid.clearing_price(1_000, 600, 480).unwrap(), 480);
+        assert_eq!(
+            error_code(bid.clearing_price(1_000, 600, 481).unwrap_err()),
+            ErrorCode::LiquidationLowCollateral.into()
+        );
+    }
+}
@@ -17,6 +17,8 @@
 
 use bytemuck::{Pod, Zeroable};
 
+use crate::errors::ErrorCode;
+
 static_assertions::const_assert_eq!(16, std::mem::size_of::<Cache<[u8; 0], 0>>());
 static_assertions::const_assert_eq!(0, std::mem::size_of::<Cache<[u8; 0], 0>>() % 8);
 #[derive(Clone, Copy)]
@@ -31,7 +33,10 @@ pub struct Cache<T, const TTL: u64> {
     /// Whether the value has been manually invalidated
     invalidated: u8,
 
-    _reserved: [u8; 7],
+    _reserved: [u8; 3],
+
+    /// Number of slots the value stays fresh for, overriding `TTL` when non-zero
+    time_to_live: u32,
 }
 
 // Since the `Cache` type uses generic parameters we can't use the derive macros
@@ -50,17 +55,31 @@ impl<T, const TTL: u64> Cache<T, TTL> {
             value,
             invalidated: 0,
             last_updated: current_slot,
-            _reserved: [0; 7],
+            _reserved: [0; 3],
+            time_to_live: 0,
+        }
+    }
+
+    /// The number of slots a value stays fresh for after being updated
+    pub fn time_to_live(&self) -> u64 {
+        match self.time_to_live {
+            0 => TTL,
+            ttl => ttl as u64,
         }
     }
 
+    /// Override the number of slots a value stays fresh for, zero restores the default
+    pub fn set_time_to_live(&mut self, time_to_live: u32) {
+        self.time_to_live = time_to_live;
+    }
+
     pub fn validate_fresh(&self, current_slot: u64) -> Result<(), CacheInvalidError> {
         let elapsed = current_slot.checked_sub(self.last_updated);
         if elapsed.is_none() {
             return Err(CacheInvalidError::MathOverflow);
         }
 
-        if elapsed.unwrap() > TTL {
+        if elapsed.unwrap() > self.time_to_live() {
             return Err(CacheInvalidError::Expired {
                 msg: self.time_msg(current_slot),
             });
@@ -79,7 +98,9 @@ impl<T, const TTL: u64> Cache<T, TTL> {
     fn time_msg(&self, current_slot: u64) -> String {
         format!(
             "last_updated = {}, time_to_live = {}, current_slot = {}",
-            self.last_updated, TTL, current_slot
+            self.last_updated,
+            self.time_to_live(),
+            current_slot
         )
     }
 
@@ -169,3 +190,13 @@ pub enum CacheInvalidError {
 
     MathOverflow
 }
+
+impl From<CacheInvalidError> for ErrorCode {
+    fn from(error: CacheInvalidError) -> ErrorCode {
+        match error {
+            // A partial refresh leaves the cache invalidated until it catches up
+            CacheInvalidError::Invalidated => ErrorCode::ExceptionalReserveState,
+            _ => ErrorCode::StaleReserve,
+        }
+    }
+}
@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+use jet_math::Number;
+
+use crate::common::Rounding;
+use crate::errors::ErrorCode;
+
+/// How the tokens taken from a liquidator's escrow are paid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidationSplit {
+    /// Paid to the reserve to repay the loan
+    pub repay: u64,
+
+    /// Paid as a reward to the keeper executing the liquidation, rounded down
+    pub premium: u64,
+
+    /// Paid to the protocol, rounded up
+    pub protocol_fee: u64,
+
+    /// The share of the protocol fee paid into the reserve's insurance
+    /// fund, rounded down
+    pub insurance_fee: u64,
+}
+
+impl LiquidationSplit {
+    /// The least a liquidator must pay to repay `debt` tokens of a loan
+    pub fn price(
+        debt: u64,
+        premium_bps: u16,
+        protocol_fee_bps: u16,
+        insurance_share_bps: u16,
+    ) -> Result<Self> {
+        let fees = bps_of(debt, protocol_fee_bps, Rounding::Up);
+        let insurance_fee = std::cmp::min(fees, bps_of(fees, insurance_share_bps, Rounding::Down));
+
+        let split = Self {
+            repay: debt,
+            premium: bps_of(debt, premium_bps, Rounding::Down),
+            protocol_fee: fees - insurance_fee,
+            insurance_fee,
+        };
+
+        // make sure the price can be represented at all
+        split.total()?;
+        Ok(split)
+    }
+
+    /// Split `consumed` tokens taken from the liquidator's escrow to repay
+    /// `debt` tokens, which must be exactly the liquidation price. Bids are
+    /// only ever charged the liquidation price, so nothing is left over to
+    /// refund to the borrower.
+    pub fn new(
+        consumed: u64,
+        debt: u64,
+        premium_bps: u16,
+        protocol_fee_bps: u16,
+        insurance_share_bps: u16,
+    ) -> Result<Self> {
+        let split = Self::price(debt, premium_bps, protocol_fee_bps, insurance_share_bps)?;
+
+        if consumed < split.total()? {
+            msg!("consumed {} can't cover the liquidation price", consumed);
+            return err!(ErrorCode::LiquidationLowCollateral);
+        }
+
+        split.verify(consumed)?;
+        Ok(split)
+    }
+
+    /// The sum of all payouts
+    pub fn total(&self) -> Result<u64> {
+        [self.premium, self.protocol_fee, self.insurance_fee]
+            .iter()
+            .try_fold(self.repay, |total, amount| total.checked_add(*amount))
+            .ok_or_else(|| error!(ErrorCode::MathOverflow))
+    }
+
+    /// Verify the payouts account for exactly the tokens consumed
+    pub fn verify(&self, consumed: u64) -> Result<()> {
+        if self.total()? != consumed {
+            msg!("liquidation payouts don't sum to the {} tokens consumed", consumed);
+            return err!(ErrorCode::LiquidationSplitMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+/// How a shortfall in repaying a loan is absorbed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BadDebtCover {
+    /// Paid from the reserve's insurance fund
+    pub insured: u64,
+
+    /// Written down against the reserve's depositors
+    pub written_off: u64,
+}
+
+impl BadDebtCover {
+    /// Cover as much of the shortfall as possible from the insurance fund
+    pub fn new(shortfall: u64, insurance_fund_balance: u64) -> Self {
+        let insured = std::cmp::min(shortfall, insurance_fund_balance);
+
+        Self {
+            insured,
+            written_off: shortfall - insured,
+        }
+    }
+}
+
+fn bps_of(amount: u64, bps: u16, rounding: Rounding) -> u64 {
+    let value = Number::from_bps(bps) * amount;
+
+    match rounding {
+        Rounding::Up => value.as_u64_ceil(0),
+        Rounding::Down => value.as_u64(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Bid;
+    use crate::test_utils::error_code;
+
+    #[test]
+    fn price_splits_debt_premium_and_fee() {
+        let split = LiquidationSplit::price(10_000, 500, 100, 0).unwrap();
+
+        assert_eq!(
+            split,
+            LiquidationSplit {
+                repay: 10_000,
+                premium: 500,
+                protocol_fee: 100,
+                insurance_fee: 0,
+            }
+        );
+        assert_eq!(split.total().unwrap(), 10_600);
+    }
+
+    #[test]
+    fn premium_rounds_down_and_fee_rounds_up() {
+        let split = LiquidationSplit::price(999, 50, 50, 0).unwrap();
+
+        // 0.5% of 999 is 4.995
+        assert_eq!(split.premium, 4);
+        assert_eq!(split.protocol_fee, 5);
+
+        let split = LiquidationSplit::price(1, 1, 1, 0).unwrap();
+        assert_eq!(split.premium, 0);
+        assert_eq!(split.protocol_fee, 1);
+    }
+
+    #[test]
+    fn edge_premiums() {
+        let zero = LiquidationSplit::price(10_000, 0, 0, 0).unwrap();
+        assert_eq!(zero.total().unwrap(), 10_000);
+
+        let full = LiquidationSplit::price(10_000, 10_000, 0, 0).unwrap();
+        assert_eq!(full.premium, 10_000);
+
+        let max = LiquidationSplit::price(10_000, u16::MAX, u16::MAX, u16::MAX).unwrap();
+        assert_eq!(max.premium, 65_535);
+        assert_eq!(max.protocol_fee + max.insurance_fee, 65_535);
+        assert_eq!(max.insurance_fee, 65_535);
+
+        assert!(LiquidationSplit::price(u64::MAX, 1, 0, 0).is_err());
+        assert!(LiquidationSplit::price(u64::MAX, 0, 0, 0).is_ok());
+    }
+
+    #[test]
+    fn consumed_escrow_is_fully_accounted_for() {
+        let split = LiquidationSplit::new(10_600, 10_000, 500, 100, 0).unwrap();
+        split.verify(10_600).unwrap();
+        assert!(split.verify(10_599).is_err());
+
+        assert_eq!(
+            error_code(LiquidationSplit::new(10_599, 10_000, 500, 100, 0).unwrap_err()),
+            ErrorCode::LiquidationLowCollateral.into()
+        );
+        assert_eq!(
+            error_code(LiquidationSplit::new(11_000, 10_000, 500, 100, 0).unwrap_err()),
+            ErrorCode::LiquidationSplitMismatch.into()
+        );
+    }
+
+    #[test]
+    fn bids_consume_exactly_the_split() {
+        let price = LiquidationSplit::price(9_999, 250, 75, 3_333).unwrap();
+        let bid = Bid {
+            max_price_bps: Some(9_000),
+            ..Bid::default()
+        };
+
+        // a bid worth more than the price still only pays the price
+        for (escrow_balance, floor_price) in [(20_000, 20_000), (10_350, 12_000), (50_000, 11_500)] {
+            let consumed = bid
+                .clearing_price(escrow_balance, floor_price, price.total().unwrap())
+                .unwrap();
+            let split = LiquidationSplit::new(consumed, 9_999, 250, 75, 3_333).unwrap();
+
+            assert_eq!(split, price);
+            assert_eq!(split.total().unwrap(), consumed);
+        }
+    }
+
+    #[test]
+    fn insurance_fund_takes_a_share_of_protocol_fees() {
+        let split = LiquidationSplit::new(10_600, 10_000, 500, 100, 2_500).unwrap();
+
+        assert_eq!(split.protocol_fee, 75);
+        assert_eq!(split.insurance_fee, 25);
+
+        let split = LiquidationSplit::price(10_000, 500, 100, 10_000).unwrap();
+        assert_eq!((split.protocol_fee, split.insurance_fee), (0, 100));
+    }
+
+    #[test]
+    fn bad_debt_is_insured_before_being_written_off() {
+        assert_eq!(
+            BadDebtCover::new(1_000, 300),
+            BadDebtCover { insured: 300, written_off: 700 }
+        );
+        assert_eq!(
+            BadDebtCover::new(1_000, 5_000),
+            BadDebtCover { insured: 1_000, written_off: 0 }
+        );
+        assert_eq!(BadDebtCover::new(0, 5_000), BadDebtCover { insured: 0, written_off: 0 });
+    }
+}
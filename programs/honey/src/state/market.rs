@@ -18,6 +18,7 @@
 use std::ops::{Deref, DerefMut};
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::clock::UnixTimestamp;
 use bytemuck::{Pod, Zeroable};
 
 use jet_math::Number;
@@ -29,6 +30,16 @@ use crate::utils::{FixedBuf, StoredPubkey};
 
 use super::Cache;
 
+/// The number of slots reserve data stays fresh for when the market doesn't configure one
+pub const DEFAULT_RESERVE_CACHE_TTL: u64 = 1;
+
+/// The upper bound on the number of slots reserve data may be considered fresh
+pub const MAX_RESERVE_CACHE_TTL: u64 = 150;
+
+/// How long liquidations stay blocked after repays resume, giving borrowers
+/// who were unable to repay a chance to do so.
+pub const LIQUIDATION_GRACE_PERIOD: UnixTimestamp = 60 * 60;
+
 /// Lending market account
 #[assert_size(12888)]
 #[account(zero_copy)]
@@ -74,8 +85,18 @@ pub struct Market {
     /// oracle price data
     pub market_oracle_state: [u8; 24],
 
+    /// The number of slots cached reserve data stays fresh for after a refresh
+    pub reserve_cache_ttl: u64,
+
+    /// The time repays were last resumed after being halted
+    pub repays_resumed_at: i64,
+
+    /// The account proposed as the next owner, which must accept before
+    /// ownership changes
+    pub pending_owner: Pubkey,
+
     /// Unused space before start of reserve list
-    _reserved: [u8; 352],
+    _reserved: [u8; 304],
 
     /// The storage for information on reserves in the market
     reserves: [u8; 12288],
@@ -142,16 +163,79 @@ impl Market {
         bytemuck::from_bytes(&self.market_oracle_state)
     }
 
+    /// Get the number of slots cached reserve data stays fresh for
+    pub fn reserve_cache_ttl(&self) -> u64 {
+        match self.reserve_cache_ttl {
+            0 => DEFAULT_RESERVE_CACHE_TTL,
+            ttl => ttl,
+        }
+    }
+
+    /// Set the number of slots cached reserve data stays fresh for
+    pub fn set_reserve_cache_ttl(&mut self, ttl: u64) -> Result<()> {
+        if ttl > MAX_RESERVE_CACHE_TTL {
+            msg!("reserve cache ttl {} exceeds the maximum of {}", ttl, MAX_RESERVE_CACHE_TTL);
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        self.reserve_cache_ttl = ttl;
+        Ok(())
+    }
+
     /// Get the current flags set on the market
     pub fn flags(&self) -> MarketFlags {
         MarketFlags::from_bits(self.flags).unwrap()
     }
 
-    /// Set new flags on the market
-    pub fn reset_flags(&mut self, flags: MarketFlags) {
+    /// Set new flags on the market, starting the liquidation grace
+    /// period if repays are resumed
+    pub fn reset_flags(&mut self, flags: MarketFlags, current_time: UnixTimestamp) {
+        if self.flags().resumes_repays(flags) {
+            self.repays_resumed_at = current_time;
+        }
+
         self.flags = flags.bits();
     }
 
+    /// Propose a new owner for the market, replacing any previous proposal
+    pub fn propose_owner(&mut self, new_owner: Pubkey) -> Result<()> {
+        if new_owner == Pubkey::default() || new_owner == self.owner {
+            msg!("the proposed owner must be a different, non-default account");
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        self.pending_owner = new_owner;
+        Ok(())
+    }
+
+    /// Make the pending owner the owner of the market, returning the previous owner
+    pub fn accept_owner(&mut self, new_owner: &Pubkey) -> Result<Pubkey> {
+        if self.pending_owner == Pubkey::default() || self.pending_owner != *new_owner {
+            msg!("the account has not been proposed as the market owner");
+            return err!(ErrorCode::Unauthorized);
+        }
+
+        let previous_owner = std::mem::replace(&mut self.owner, self.pending_owner);
+        self.pending_owner = Pubkey::default();
+
+        Ok(previous_owner)
+    }
+
+    /// Withdraw a proposed ownership change, returning the account that was proposed
+    pub fn cancel_owner_proposal(&mut self) -> Result<Pubkey> {
+        if self.pending_owner == Pubkey::default() {
+            msg!("there is no pending owner to cancel");
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        Ok(std::mem::take(&mut self.pending_owner))
+    }
+
+    /// Add flags to the market, leaving any already set in place
+    pub fn pause(&mut self, flags: MarketFlags) {
+        self.flags |= flags.bits();
+    }
+
     /// Verify that the market is currently allowing deposits and withdrawals
     pub fn verify_ability_deposit_withdraw(&self) -> Result<()> {
         if self.flags().contains(MarketFlags::HALT_DEPOSITS) {
@@ -175,7 +259,44 @@ impl Market {
     /// Verify that the market is currently allowing repayments to loans
     pub fn verify_ability_repay(&self) -> Result<()> {
         if self.flags().contains(MarketFlags::HALT_REPAYS) {
-            msg!("the market is currently not allowing borrows");
+            msg!("the market is currently not allowing repays");
+            return err!(ErrorCode::MarketHalted);
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the market is currently allowing liquidations
+    pub fn verify_ability_liquidate(&self, current_time: UnixTimestamp) -> Result<()> {
+        if !self.flags().allows_liquidations() {
+            msg!("the market is currently not allowing liquidations");
+            return err!(ErrorCode::MarketHalted);
+        }
+
+        verify_liquidation_grace_period(self.repays_resumed_at, current_time)
+    }
+
+    /// Verify that the market is currently allowing nfts to be deposited as collateral
+    pub fn verify_ability_deposit_nft(&self) -> Result<()> {
+        if self
+            .flags()
+            .intersects(MarketFlags::HALT_DEPOSITS | MarketFlags::HALT_NFT_DEPOSITS)
+        {
+            msg!("the market is currently not allowing nft deposits");
+            return err!(ErrorCode::MarketHalted);
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the market is currently allowing nft collateral to be withdrawn
+    pub fn verify_ability_withdraw_nft(&self) -> Result<()> {
+        if self.flags().intersects(
+            MarketFlags::HALT_BORROWS
+                | MarketFlags::HALT_DEPOSITS
+                | MarketFlags::HALT_NFT_WITHDRAWALS,
+        ) {
+            msg!("the market is currently not allowing nft withdrawals");
             return err!(ErrorCode::MarketHalted);
         }
 
@@ -219,18 +340,19 @@ impl MarketReserves {
         &self.reserve_info[index as usize]
     }
 
-    pub fn get_cached(&self, index: ReserveIndex, current_slot: u64) -> &CachedReserveInfo {
+    pub fn get_cached(&self, index: ReserveIndex, current_slot: u64) -> Result<&CachedReserveInfo> {
         let entry = self.get(index);
         match entry.cache.try_get(current_slot) {
-            Ok(info) => info,
-            Err(_) => {
+            Ok(info) => Ok(info),
+            Err(e) => {
                 msg!("reserve {} is stale in market", entry.reserve);
                 msg!(
                     "cached_slot = {}, current_slot = {}",
                     entry.cache.last_updated(),
                     current_slot
                 );
-                panic!("market reserve is stale");
+                let code = ErrorCode::from(e);
+                Err(error!(code))
             }
         }
     }
@@ -239,10 +361,14 @@ impl MarketReserves {
         &mut self,
         index: ReserveIndex,
         current_slot: u64,
-    ) -> &mut CachedReserveInfo {
-        self.get_mut(index)
-            .cache
-            .expect_mut(current_slot, "reserve market data is stale")
+    ) -> Result<&mut CachedReserveInfo> {
+        let entry = self.get_mut(index);
+        let key = entry.reserve;
+        entry.cache.try_get_mut(current_slot).map_err(|e| {
+            msg!("reserve {} is stale in market", key);
+            let code = ErrorCode::from(e);
+            error!(code)
+        })
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &ReserveInfo> {
@@ -366,19 +492,6 @@ impl DerefMut for ReserveInfo {
     }
 }
 
-impl ReserveInfo {
-    pub fn unwrap(&self, current_slot: u64) -> &CachedReserveInfo {
-        self.try_get(current_slot)
-            .unwrap_or_else(|_| panic!("reserve {}", self.reserve))
-    }
-
-    pub fn unwrap_mut(&mut self, current_slot: u64) -> &mut CachedReserveInfo {
-        let key = self.reserve;
-        self.try_get_mut(current_slot)
-            .unwrap_or_else(|_| panic!("reserve {}", key))
-    }
-}
-
 bitflags::bitflags! {
     pub struct MarketFlags: u64 {
         /// Disable all borrowing and collateral withdrawals
@@ -390,12 +503,54 @@ bitflags::bitflags! {
         /// Disable deposits + withdrawals
         const HALT_DEPOSITS = 1 << 2;
 
+        /// Disable liquidating unhealthy obligations
+        const HALT_LIQUIDATIONS = 1 << 3;
+
+        /// Disable depositing nfts as collateral
+        const HALT_NFT_DEPOSITS = 1 << 4;
+
+        /// Disable withdrawing nft collateral
+        const HALT_NFT_WITHDRAWALS = 1 << 5;
+
         /// Disable all operations
         const HALT_ALL = Self::HALT_BORROWS.bits
                        | Self::HALT_REPAYS.bits
-                       | Self::HALT_DEPOSITS.bits;
+                       | Self::HALT_DEPOSITS.bits
+                       | Self::HALT_LIQUIDATIONS.bits
+                       | Self::HALT_NFT_DEPOSITS.bits
+                       | Self::HALT_NFT_WITHDRAWALS.bits;
+
+    }
+}
+
+impl MarketFlags {
+    /// Liquidations are only allowed while borrowers are able to repay
+    pub fn allows_liquidations(&self) -> bool {
+        !self.intersects(MarketFlags::HALT_LIQUIDATIONS | MarketFlags::HALT_REPAYS)
+    }
+
+    /// Whether replacing these flags with `new_flags` allows repays again
+    pub fn resumes_repays(&self, new_flags: MarketFlags) -> bool {
+        self.contains(MarketFlags::HALT_REPAYS) && !new_flags.contains(MarketFlags::HALT_REPAYS)
+    }
+}
 
+/// Verify that liquidations are not within the grace period after repays resumed
+pub fn verify_liquidation_grace_period(
+    repays_resumed_at: UnixTimestamp,
+    current_time: UnixTimestamp,
+) -> Result<()> {
+    if repays_resumed_at == 0 {
+        return Ok(());
     }
+
+    let grace_period_end = repays_resumed_at.saturating_add(LIQUIDATION_GRACE_PERIOD);
+    if current_time < grace_period_end {
+        msg!("liquidations resume at {} after repays were halted", grace_period_end);
+        return err!(ErrorCode::LiquidationGracePeriod);
+    }
+
+    Ok(())
 }
 
 impl std::fmt::Debug for ReserveInfo {
@@ -428,3 +583,187 @@ impl std::fmt::Debug for Market {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::error_code;
+
+    fn refreshed_reserves(current_slot: u64, ttl: u32) -> MarketReserves {
+        let mut reserves = MarketReserves::zeroed();
+        let index = reserves.register(&Pubkey::new_unique()).unwrap();
+        let info = reserves.get_mut(index);
+
+        info.set_time_to_live(ttl);
+        info.refresh_to(current_slot);
+        reserves
+    }
+
+    #[test]
+    fn stale_reserve_is_an_error() {
+        let reserves = refreshed_reserves(10, 0);
+
+        assert!(reserves.get_cached(0, 10).is_ok());
+        assert!(reserves.get_cached(0, 11).is_ok());
+        assert_eq!(
+            error_code(reserves.get_cached(0, 12).unwrap_err()),
+            ErrorCode::StaleReserve.into()
+        );
+    }
+
+    #[test]
+    fn invalidated_reserve_is_exceptional() {
+        let mut reserves = refreshed_reserves(10, 0);
+        reserves.get_mut(0).invalidate();
+
+        assert_eq!(
+            error_code(reserves.get_cached(0, 10).unwrap_err()),
+            ErrorCode::ExceptionalReserveState.into()
+        );
+    }
+
+    #[test]
+    fn reserve_cache_ttl_is_configurable() {
+        let mut market = Market::zeroed();
+        assert_eq!(market.reserve_cache_ttl(), DEFAULT_RESERVE_CACHE_TTL);
+
+        market.set_reserve_cache_ttl(20).unwrap();
+        assert_eq!(market.reserve_cache_ttl(), 20);
+        assert!(market.set_reserve_cache_ttl(MAX_RESERVE_CACHE_TTL + 1).is_err());
+
+        let reserves = refreshed_reserves(10, market.reserve_cache_ttl() as u32);
+        assert!(reserves.get_cached(0, 30).is_ok());
+        assert!(reserves.get_cached(0, 31).is_err());
+    }
+
+    fn halted_operations(market: &Market) -> [bool; 6] {
+        [
+            market.verify_ability_deposit_withdraw().is_err(),
+            market.verify_ability_borrow().is_err(),
+            market.verify_ability_repay().is_err(),
+            market.verify_ability_liquidate(0).is_err(),
+            market.verify_ability_deposit_nft().is_err(),
+            market.verify_ability_withdraw_nft().is_err(),
+        ]
+    }
+
+    #[test]
+    fn market_flags_halt_their_operations() {
+        let mut market = Market::zeroed();
+        assert_eq!(halted_operations(&market), [false; 6]);
+
+        let cases = [
+            (MarketFlags::HALT_DEPOSITS, [true, false, false, false, true, true]),
+            (MarketFlags::HALT_BORROWS, [false, true, false, false, false, true]),
+            (MarketFlags::HALT_REPAYS, [false, false, true, true, false, false]),
+            (MarketFlags::HALT_LIQUIDATIONS, [false, false, false, true, false, false]),
+            (MarketFlags::HALT_NFT_DEPOSITS, [false, false, false, false, true, false]),
+            (MarketFlags::HALT_NFT_WITHDRAWALS, [false, false, false, false, false, true]),
+            (MarketFlags::HALT_ALL, [true; 6]),
+        ];
+
+        for (flags, expected) in cases {
+            market.reset_flags(flags, 0);
+            assert_eq!(halted_operations(&market), expected, "{:?}", flags);
+        }
+    }
+
+    #[test]
+    fn pause_only_adds_flags() {
+        let mut market = Market::zeroed();
+
+        market.reset_flags(MarketFlags::HALT_BORROWS, 0);
+        market.pause(MarketFlags::HALT_LIQUIDATIONS);
+        assert_eq!(
+            market.flags(),
+            MarketFlags::HALT_BORROWS | MarketFlags::HALT_LIQUIDATIONS
+        );
+
+        market.pause(MarketFlags::empty());
+        assert_eq!(
+            market.flags(),
+            MarketFlags::HALT_BORROWS | MarketFlags::HALT_LIQUIDATIONS
+        );
+    }
+
+    #[test]
+    fn liquidations_wait_for_grace_period_after_repays_resume() {
+        let mut market = Market::zeroed();
+        let now = 1_000_000;
+
+        market.reset_flags(MarketFlags::HALT_REPAYS, now);
+        assert!(!market.flags().allows_liquidations());
+        assert!(market.verify_ability_liquidate(now).is_err());
+
+        // halting something else doesn't resume repays
+        market.reset_flags(MarketFlags::HALT_REPAYS | MarketFlags::HALT_BORROWS, now + 10);
+        assert_eq!(market.repays_resumed_at, 0);
+
+        market.reset_flags(MarketFlags::empty(), now + 100);
+        assert!(market.flags().allows_liquidations());
+        assert_eq!(market.repays_resumed_at, now + 100);
+
+        let grace_period_end = now + 100 + LIQUIDATION_GRACE_PERIOD;
+        assert_eq!(
+            error_code(market.verify_ability_liquidate(grace_period_end - 1).unwrap_err()),
+            ErrorCode::LiquidationGracePeriod.into()
+        );
+        assert!(market.verify_ability_liquidate(grace_period_end).is_ok());
+        assert!(market.verify_ability_repay().is_ok());
+    }
+
+    #[test]
+    fn ownership_transfers_in_two_steps() {
+        let mut market = Market::zeroed();
+        let owner = Pubkey::new_unique();
+        let new_owner = Pubkey::new_unique();
+        market.owner = owner;
+
+        assert!(market.propose_owner(Pubkey::default()).is_err());
+        assert!(market.propose_owner(owner).is_err());
+        assert!(market.accept_owner(&new_owner).is_err());
+        assert!(market.accept_owner(&Pubkey::default()).is_err());
+
+        market.propose_owner(new_owner).unwrap();
+        assert_eq!(market.owner, owner);
+        assert_eq!(
+            error_code(market.accept_owner(&Pubkey::new_unique()).unwrap_err()),
+            ErrorCode::Unauthorized.into()
+        );
+
+        assert_eq!(market.accept_owner(&new_owner).unwrap(), owner);
+        assert_eq!(market.owner, new_owner);
+        assert_eq!(market.pending_owner, Pubkey::default());
+        assert!(market.accept_owner(&new_owner).is_err());
+    }
+
+    #[test]
+    fn ownership_proposal_can_be_cancelled() {
+        let mut market = Market::zeroed();
+        let owner = Pubkey::new_unique();
+        let mistyped = Pubkey::new_unique();
+        market.owner = owner;
+
+        assert!(market.cancel_owner_proposal().is_err());
+
+        market.propose_owner(mistyped).unwrap();
+        assert_eq!(market.cancel_owner_proposal().unwrap(), mistyped);
+        assert!(market.accept_owner(&mistyped).is_err());
+        assert_eq!(market.owner, owner);
+    }
+
+    #[test]
+    fn removed_reserve_slot_is_reused() {
+        let mut reserves = MarketReserves::zeroed();
+        let first = reserves.register(&Pubkey::new_unique()).unwrap();
+        let second = reserves.register(&Pubkey::new_unique()).unwrap();
+
+        reserves.remove(first);
+        assert_eq!(reserves.iter().count(), 1);
+
+        let replacement = Pubkey::new_unique();
+        assert_eq!(reserves.register(&replacement).unwrap(), first);
+        assert_eq!(*reserves.get(first).reserve, replacement);
+        assert_ne!(first, second);
+    }
+}
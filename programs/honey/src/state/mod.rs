@@ -16,13 +16,19 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod cache;
+mod liquidation;
 mod market;
 mod obligation;
 mod reserve;
+mod roles;
+mod withdrawal;
 mod bid;
 
 pub use cache::*;
+pub use liquidation::*;
 pub use market::*;
 pub use obligation::*;
 pub use reserve::*;
+pub use roles::*;
+pub use withdrawal::*;
 pub use bid::*;
@@ -50,8 +50,20 @@ pub struct Obligation {
     /// The address that owns the debt/assets as a part of this obligation
     pub owner: Pubkey,
 
+    /// The address offered ownership of the obligation, which must accept it
+    pub pending_owner: Pubkey,
+
+    /// An address allowed to manage the obligation on the owner's behalf
+    pub delegate: Pubkey,
+
+    /// The actions the delegate is allowed to take, as `ObligationPermissions`
+    pub delegate_permissions: u64,
+
+    /// How each nft in `collateral_nft_mint` is held, as `NftCustody`
+    pub collateral_custody: [u8; 11],
+
     /// Unused space before start of collateral info
-    pub _reserved1: [u8; 184],
+    pub _reserved1: [u8; 101],
 
     /// stores collateral nft key
     pub collateral_nft_mint: [Pubkey; 11], // can store 11 nfts max for now
@@ -65,16 +77,22 @@ pub struct Obligation {
 
 impl Obligation {
     pub fn register_nft(&mut self, account: Pubkey) -> Result<()> {
+        self.register_nft_with_custody(account, NftCustody::Escrow)
+    }
+
+    /// Record an nft as collateral, along with how the market holds it
+    pub fn register_nft_with_custody(&mut self, account: Pubkey, custody: NftCustody) -> Result<()> {
         if self.position_count() >= MAX_OBLIGATION_POSITIONS {
             return err!(ErrorCode::NoFreeObligation);
         }
 
-        for nft_mint in self.collateral_nft_mint.iter_mut() {
+        for (index, nft_mint) in self.collateral_nft_mint.iter_mut().enumerate() {
             if *nft_mint != Pubkey::default() {
                 return err!(ErrorCode::NftCollateralExists); //allow only 1 nft position per obligation
             }
 
             *nft_mint = account;
+            self.collateral_custody[index] = custody.into_integer();
             return Ok(());
         }
 
@@ -82,17 +100,52 @@ impl Obligation {
     }
 
     pub fn unregister_nft(&mut self, account: Pubkey) -> Result<()> {
-        for nft_mint in self.collateral_nft_mint.iter_mut() {
+        for (index, nft_mint) in self.collateral_nft_mint.iter_mut().enumerate() {
             if *nft_mint != account {
                 continue;
             }
             *nft_mint = Pubkey::default();
+            self.collateral_custody[index] = NftCustody::Escrow.into_integer();
             return Ok(());
         }
 
         err!(ErrorCode::UnregisteredNFTPosition)
     }
 
+    /// Unregister an nft that's moved out of the market authority's custody,
+    /// failing for nfts frozen in the owner's wallet
+    pub fn unregister_escrowed_nft(&mut self, account: Pubkey) -> Result<()> {
+        if self.nft_custody(&account)? != NftCustody::Escrow {
+            return err!(ErrorCode::NftCustodyMismatch);
+        }
+
+        self.unregister_nft(account)
+    }
+
+    /// Get how the market holds an nft registered as collateral
+    pub fn nft_custody(&self, account: &Pubkey) -> Result<NftCustody> {
+        let index = self
+            .collateral_nft_mint
+            .iter()
+            .position(|nft_mint| nft_mint == account && *account != Pubkey::default())
+            .ok_or_else(|| error!(ErrorCode::UnregisteredNFTPosition))?;
+
+        NftCustody::from_integer(self.collateral_custody[index]).ok_or_else(|| {
+            msg!("unknown custody {} for {}", self.collateral_custody[index], account);
+            error!(ErrorCode::NftCustodyMismatch)
+        })
+    }
+
+    /// Whether any collateral is frozen in the owner's wallet rather than escrowed
+    pub fn has_frozen_collateral(&self) -> bool {
+        self.collateral_nft_mint
+            .iter()
+            .zip(self.collateral_custody)
+            .any(|(m, custody)| {
+                *m != Pubkey::default() && custody == NftCustody::Frozen.into_integer()
+            })
+    }
+
     pub fn register_loan(&mut self, account: &Pubkey, reserve_index: ReserveIndex) -> Result<()> {
         if self.position_count() >= MAX_OBLIGATION_POSITIONS {
             return err!(ErrorCode::NoFreeObligation);
@@ -117,50 +170,182 @@ impl Obligation {
         self.loans_mut().subtract(loan_account, loan_notes_amount)
     }
 
+    /// Offer the obligation, with its collateral and debt, to a new owner,
+    /// replacing any previous offer
+    pub fn offer_transfer(&mut self, new_owner: Pubkey) -> Result<()> {
+        if new_owner == Pubkey::default() || new_owner == self.owner {
+            msg!("the obligation must be offered to a different, non-default account");
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        // collateral frozen in the owner's wallet can't follow the obligation
+        if self.has_frozen_collateral() {
+            return err!(ErrorCode::FrozenCollateral);
+        }
+
+        self.pending_owner = new_owner;
+        Ok(())
+    }
+
+    /// Make the account offered the obligation its owner, returning the previous owner
+    pub fn accept_transfer(&mut self, new_owner: &Pubkey) -> Result<Pubkey> {
+        if self.pending_owner == Pubkey::default() || self.pending_owner != *new_owner {
+            msg!("the obligation has not been offered to the account");
+            return err!(ErrorCode::Unauthorized);
+        }
+
+        let previous_owner = std::mem::replace(&mut self.owner, self.pending_owner);
+        self.pending_owner = Pubkey::default();
+
+        // The previous owner's delegate has no standing with the new owner
+        self.delegate = Pubkey::default();
+        self.delegate_permissions = 0;
+
+        Ok(previous_owner)
+    }
+
+    /// Withdraw an offer to transfer the obligation, returning the account it was offered to
+    pub fn cancel_transfer(&mut self) -> Result<Pubkey> {
+        if self.pending_owner == Pubkey::default() {
+            msg!("the obligation has no pending transfer to cancel");
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        Ok(std::mem::take(&mut self.pending_owner))
+    }
+
+    /// Get the actions the delegate is allowed to take
+    pub fn delegate_permissions(&self) -> ObligationPermissions {
+        ObligationPermissions::from_bits_truncate(self.delegate_permissions)
+    }
+
+    /// Allow an address to take some actions on the obligation on the owner's behalf,
+    /// replacing any previous delegate
+    pub fn set_delegate(&mut self, delegate: Pubkey, permissions: u64) -> Result<()> {
+        let permissions = match ObligationPermissions::from_bits(permissions) {
+            Some(permissions) if !permissions.is_empty() => permissions,
+            _ => {
+                msg!("the delegate must be given a valid, non-empty set of permissions");
+                return err!(ErrorCode::InvalidParameter);
+            }
+        };
+
+        if delegate == Pubkey::default() || delegate == self.owner {
+            msg!("the delegate must be a different, non-default account");
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        self.delegate = delegate;
+        self.delegate_permissions = permissions.bits();
+        Ok(())
+    }
+
+    /// Remove the delegate, returning the address that was delegated to
+    pub fn revoke_delegate(&mut self) -> Result<Pubkey> {
+        if self.delegate == Pubkey::default() {
+            msg!("the obligation has no delegate to revoke");
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        self.delegate_permissions = 0;
+        Ok(std::mem::take(&mut self.delegate))
+    }
+
+    /// Whether the signer is the owner, or a delegate allowed to take the action
+    pub fn is_authorized(&self, signer: &Pubkey, action: ObligationPermissions) -> bool {
+        *signer == self.owner
+            || (*signer == self.delegate
+                && self.delegate != Pubkey::default()
+                && self.delegate_permissions().contains(action))
+    }
+
+    /// Move the whole of a loan into a loan account from another reserve,
+    /// replacing the `repaid` notes with the `borrowed` notes
+    pub fn refinance(
+        &mut self,
+        from_loan_account: &Pubkey,
+        repaid_notes: Number,
+        to_loan_account: &Pubkey,
+        borrowed_notes: Number,
+    ) -> Result<()> {
+        self.cached_mut().invalidate();
+
+        let loans = self.loans_mut();
+        loans.subtract(from_loan_account, repaid_notes)?;
+        loans.add(to_loan_account, borrowed_notes)?;
+
+        if loans.position(from_loan_account)?.amount != Number::ZERO {
+            msg!("only a loan that is repaid in full can be refinanced");
+            return err!(ErrorCode::AnotherLoanOutstanding);
+        }
+
+        Ok(())
+    }
+
     /// Be smarter about compute
     pub fn cache_calculations(
         &mut self,
         market: &MarketReserves,
         current_slot: u64,
         nft_price_data: &MarketOracle
-    ) {
+    ) -> Result<()> {
         let loans: &ObligationSide = bytemuck::from_bytes(&self.loans);
         // let collateral: &ObligationSide = bytemuck::from_bytes(&self.collateral);
         let nft_deposited_collateral = &self.nft_collateral_value(nft_price_data.price);
+        let loan_value = loans._market_value(market, current_slot)?;
         let cached: &mut CalculationCache = bytemuck::from_bytes_mut(&mut self.cached);
 
         cached.refresh(current_slot);
 
         let values = cached.get_stale_mut();
-        values.loan_value = loans._market_value(market, current_slot);
+        values.loan_value = loan_value;
         // let reserve_deposited_collateral = collateral._market_value(market, current_slot);
         values.collateral_value = *nft_deposited_collateral;
+
+        Ok(())
     }
 
     /// Determine if the obligation is healthy, or otherwise unhealthy and
     /// at risk of liquidation.
-    pub fn is_healthy(&self, market: &MarketReserves, current_slot: u64) -> bool {
+    pub fn is_healthy(&self, market: &MarketReserves, current_slot: u64) -> Result<bool> {
         let max_min_c_ratio: Number;
         let _max_min_c_ratio = self
             .loans()
             .iter()
             .filter(|p| p.amount != Number::ZERO)
-            .map(|p| { market.get_cached(p.reserve_index, current_slot).min_collateral_ratio })
+            .map(|p| {
+                market
+                    .get_cached(p.reserve_index, current_slot)
+                    .map(|reserve| reserve.min_collateral_ratio)
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
             .max();
         if let Some(c) = _max_min_c_ratio {
             max_min_c_ratio = c;
         } else {
-            return true; // No loans
+            return Ok(true); // No loans
         }
 
-        let cached: &CalculationCache = bytemuck::from_bytes(&self.cached);
-
-        let cache_values = cached.expect(current_slot, "calculations not performed");
+        let cache_values = self.cached_calculations(current_slot)?;
         msg!("loan value {}", cache_values.loan_value);
         let min_collateral_value = cache_values.loan_value * max_min_c_ratio;
 
         msg!("{}, {}", min_collateral_value, cache_values.collateral_value);
-        min_collateral_value <= cache_values.collateral_value
+        Ok(min_collateral_value <= cache_values.collateral_value)
+    }
+
+    /// The most value the obligation can add to its loan from a reserve
+    /// while staying healthy. Requires calculations to have been cached.
+    pub fn borrowing_capacity(
+        &self,
+        reserve: &CachedReserveInfo,
+        current_slot: u64,
+    ) -> Result<Number> {
+        let cache_values = self.cached_calculations(current_slot)?;
+        let max_loan_value = cache_values.collateral_value / reserve.min_collateral_ratio;
+
+        Ok(max_loan_value.saturating_sub(cache_values.loan_value))
     }
 
     pub fn can_borrow_from_reserve(&self, index: ReserveIndex) -> Result<()> {
@@ -196,9 +381,9 @@ impl Obligation {
         value
     }
 
-    pub fn loan_value(&self, market: &MarketReserves, current_slot: u64) -> Number {
+    pub fn loan_value(&self, market: &MarketReserves, current_slot: u64) -> Result<Number> {
         if let Ok(values) = self.cached().try_get(current_slot) {
-            return values.loan_value;
+            return Ok(values.loan_value);
         }
 
         self.loans()._market_value(market, current_slot)
@@ -210,6 +395,22 @@ impl Obligation {
         loans
     }
 
+    /// Verify the obligation has no registered loans or NFT collateral, so
+    /// that it can be closed
+    pub fn verify_empty(&self) -> Result<()> {
+        if self.position_count() > 0 || self.has_nft_collateral() {
+            msg!("the obligation still has registered loans or collateral");
+            return err!(ErrorCode::PositionNotEmpty);
+        }
+
+        Ok(())
+    }
+
+    /// Whether any NFT is still deposited as collateral
+    pub fn has_nft_collateral(&self) -> bool {
+        self.collateral_nft_mint.iter().any(|m| *m != Pubkey::default())
+    }
+
     fn cached(&self) -> &CalculationCache {
         bytemuck::from_bytes(&self.cached)
     }
@@ -218,6 +419,14 @@ impl Obligation {
         bytemuck::from_bytes_mut(&mut self.cached)
     }
 
+    fn cached_calculations(&self, current_slot: u64) -> Result<&CalculationCacheInner> {
+        self.cached().try_get(current_slot).map_err(|e| {
+            msg!("obligation calculations are stale");
+            let code = ErrorCode::from(e);
+            error!(code)
+        })
+    }
+
     pub fn loans(&self) -> &ObligationSide {
         bytemuck::from_bytes(&self.loans)
     }
@@ -239,6 +448,20 @@ struct CalculationCacheInner {
 
 type CalculationCache = Cache<CalculationCacheInner, 0>;
 
+/// How the market holds an nft pledged as collateral
+#[derive(Contiguous, Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+pub enum NftCustody {
+    /// Moved into a token account of the market authority
+    Escrow,
+
+    /// Left in the owner's wallet, frozen with the market authority as its delegate
+    Frozen,
+
+    /// A compressed nft's leaf, owned by the market authority
+    Compressed,
+}
+
 #[assert_size(4)]
 #[derive(Contiguous, Debug, Clone, Copy, Eq, PartialEq)]
 #[repr(u32)]
@@ -299,7 +522,11 @@ impl ObligationSide {
                 continue;
             }
 
-            *position.account = Pubkey::default();
+            if position.amount != Number::ZERO {
+                return err!(ErrorCode::PositionNotEmpty);
+            }
+
+            *position = Position::zeroed();
 
             return Ok(());
         }
@@ -337,28 +564,33 @@ impl ObligationSide {
         Ok(position)
     }
 
-    pub fn market_value(&self, market_info: &MarketReserves, current_slot: u64) -> PositionValue {
+    pub fn market_value(
+        &self,
+        market_info: &MarketReserves,
+        current_slot: u64,
+    ) -> Result<PositionValue> {
         let mut value = PositionValue::zeroed();
 
-        for position in self.iter() {
-            let reserve = market_info.get(position.reserve_index).unwrap(current_slot);
+        // positions left empty may refer to a reserve that has since been closed
+        for position in self.iter().filter(|p| p.amount != Number::ZERO) {
+            let reserve = market_info.get_cached(position.reserve_index, current_slot)?;
             let position_value = position.market_value(reserve);
             value.market_value += position_value.market_value;
             value.complementary_limit += position_value.complementary_limit;
         }
 
-        value
+        Ok(value)
     }
 
-    fn _market_value(&self, market: &MarketReserves, current_slot: u64) -> Number {
+    fn _market_value(&self, market: &MarketReserves, current_slot: u64) -> Result<Number> {
         let mut value = Number::ZERO;
 
-        for pos in self.iter() {
-            let reserve = market.get_cached(pos.reserve_index, current_slot);
+        for pos in self.iter().filter(|p| p.amount != Number::ZERO) {
+            let reserve = market.get_cached(pos.reserve_index, current_slot)?;
             value = pos._market_value(reserve).saturating_add(value);
         }
 
-        value
+        Ok(value)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &Position> {
@@ -441,11 +673,31 @@ impl Position {
     }
 }
 
+bitflags::bitflags! {
+    /// The actions an obligation's delegate may take on the owner's behalf.
+    /// Withdrawing collateral always requires the owner.
+    pub struct ObligationPermissions: u64 {
+        /// Borrow, with the funds only going to an account of the owner
+        const BORROW = 1 << 0;
+
+        /// Repay loans using the delegate's own tokens. Anyone may repay a
+        /// loan, so this grants nothing beyond marking the delegate's role.
+        const REPAY = 1 << 1;
+
+        /// Deposit nft collateral from the delegate's own wallet
+        const DEPOSIT_NFT = 1 << 2;
+
+        /// Move loans between reserves of the same token
+        const REFINANCE = 1 << 3;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::ops::Mul;
 
     use crate::state::ReserveInfo;
+    use crate::test_utils::error_code;
 
     use super::*;
 
@@ -487,8 +739,8 @@ mod tests {
         let expected_nft_value = ctx.obligation.nft_collateral_value(price_from);
         assert_eq!(expected_nft_value, Number::from(price_from.mul(1u32)));
 
-        ctx.obligation.cache_calculations(&ctx.market, 0, &nft_oracle_price);
-        let healthy = ctx.obligation.is_healthy(&ctx.market, 0);
+        ctx.obligation.cache_calculations(&ctx.market, 0, &nft_oracle_price).unwrap();
+        let healthy = ctx.obligation.is_healthy(&ctx.market, 0).unwrap();
         assert!(healthy);
 
         let loan = ctx.create_loan(|reserve| {
@@ -501,8 +753,8 @@ mod tests {
         ctx.obligation.borrow(&loan, Number::from(1u32)).unwrap();
 
         // verify the obligation is still healthy
-        ctx.obligation.cache_calculations(&ctx.market, 0, &nft_oracle_price);
-        let healthy = ctx.obligation.is_healthy(&ctx.market, 0);
+        ctx.obligation.cache_calculations(&ctx.market, 0, &nft_oracle_price).unwrap();
+        let healthy = ctx.obligation.is_healthy(&ctx.market, 0).unwrap();
         assert!(!healthy);
     }
 
@@ -523,15 +775,210 @@ mod tests {
         let price_from = Number::from(122u32);
         let nft_oracle_price: MarketOracle = MarketOracle { price: price_from };
         // c-ratio = 100%
-        ctx.obligation.cache_calculations(&ctx.market, 0, &nft_oracle_price);
-        let healthy = ctx.obligation.is_healthy(&ctx.market, 0);
+        ctx.obligation.cache_calculations(&ctx.market, 0, &nft_oracle_price).unwrap();
+        let healthy = ctx.obligation.is_healthy(&ctx.market, 0).unwrap();
         assert!(!healthy);
 
         // c-ratio = 250%
         ctx.obligation.repay(&loan, Number::from(500_000u32)).unwrap();
 
-        ctx.obligation.cache_calculations(&ctx.market, 0, &nft_oracle_price);
-        let healthy = ctx.obligation.is_healthy(&ctx.market, 0);
+        ctx.obligation.cache_calculations(&ctx.market, 0, &nft_oracle_price).unwrap();
+        let healthy = ctx.obligation.is_healthy(&ctx.market, 0).unwrap();
         assert!(healthy);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn empty_position_in_closed_reserve_is_ignored() {
+        let mut ctx = ObligationTestContext::new();
+        let loan = ctx.create_loan(|reserve| {
+            let cache = reserve.cache.get_stale_mut();
+
+            cache.price = Number::from(2u32);
+            cache.loan_note_exchange_rate = Number::from(1u32);
+            cache.min_collateral_ratio = Number::from_bps(12500);
+        });
+
+        ctx.obligation.borrow(&loan, Number::from(10u32)).unwrap();
+        ctx.obligation.repay(&loan, Number::from(10u32)).unwrap();
+        ctx.market.remove(0);
+
+        let oracle = MarketOracle { price: Number::from(1u32) };
+        ctx.obligation.cache_calculations(&ctx.market, 0, &oracle).unwrap();
+        assert!(ctx.obligation.is_healthy(&ctx.market, 0).unwrap());
+    }
+
+    #[test]
+    fn stale_calculations_are_an_error() {
+        let mut ctx = ObligationTestContext::new();
+        ctx.create_loan(|reserve| {
+            reserve.cache.get_stale_mut().min_collateral_ratio = Number::from_bps(12500);
+        });
+        ctx.obligation.register_nft(Pubkey::new_unique()).unwrap();
+
+        let oracle = MarketOracle { price: Number::from(10u32) };
+        ctx.obligation.cache_calculations(&ctx.market, 0, &oracle).unwrap();
+        let reserve = ctx.market.get(0).cache.get_stale();
+        assert_eq!(ctx.obligation.borrowing_capacity(reserve, 0).unwrap(), Number::from(8u32));
+
+        assert_eq!(
+            error_code(ctx.obligation.borrowing_capacity(reserve, 100).unwrap_err()),
+            ErrorCode::StaleReserve.into()
+        );
+    }
+
+    #[test]
+    fn obligation_is_empty_once_positions_are_unregistered() {
+        let mut ctx = ObligationTestContext::new();
+        ctx.obligation.verify_empty().unwrap();
+
+        let loan = ctx.create_loan(|_| {});
+        ctx.obligation.borrow(&loan, Number::from(5u32)).unwrap();
+        assert!(ctx.obligation.verify_empty().is_err());
+        assert!(ctx.obligation.unregister_loan(&loan).is_err());
+
+        ctx.obligation.repay(&loan, Number::from(5u32)).unwrap();
+        ctx.obligation.unregister_loan(&loan).unwrap();
+        ctx.obligation.verify_empty().unwrap();
+
+        let nft_mint = Pubkey::new_unique();
+        ctx.obligation.register_nft(nft_mint).unwrap();
+        assert!(ctx.obligation.verify_empty().is_err());
+
+        ctx.obligation.unregister_nft(nft_mint).unwrap();
+        ctx.obligation.verify_empty().unwrap();
+    }
+
+    #[test]
+    fn refinancing_moves_the_loan_between_reserves() {
+        let mut ctx = ObligationTestContext::new();
+        let from = ctx.create_loan(|_| {});
+        let to = ctx.create_loan(|_| {});
+
+        ctx.obligation.borrow(&from, Number::from(10u32)).unwrap();
+        assert!(ctx.obligation.can_borrow_from_reserve(1).is_err());
+
+        assert!(ctx
+            .obligation
+            .refinance(&from, Number::from(9u32), &to, Number::from(11u32))
+            .is_err());
+
+        let mut ctx = ObligationTestContext::new();
+        let from = ctx.create_loan(|_| {});
+        let to = ctx.create_loan(|_| {});
+        ctx.obligation.borrow(&from, Number::from(10u32)).unwrap();
+
+        ctx.obligation
+            .refinance(&from, Number::from(10u32), &to, Number::from(11u32))
+            .unwrap();
+
+        assert_eq!(ctx.obligation.loans().position(&from).unwrap().amount, Number::ZERO);
+        assert_eq!(ctx.obligation.loans().position(&to).unwrap().amount, Number::from(11u32));
+        ctx.obligation.can_borrow_from_reserve(1).unwrap();
+        ctx.obligation.unregister_loan(&from).unwrap();
+    }
+
+    #[test]
+    fn obligations_transfer_once_accepted() {
+        let mut obligation = Obligation::zeroed();
+        let owner = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        obligation.owner = owner;
+
+        assert!(obligation.offer_transfer(owner).is_err());
+        assert!(obligation.offer_transfer(Pubkey::default()).is_err());
+        assert!(obligation.accept_transfer(&buyer).is_err());
+
+        obligation.offer_transfer(buyer).unwrap();
+        assert!(obligation.accept_transfer(&Pubkey::new_unique()).is_err());
+        assert_eq!(obligation.owner, owner);
+
+        assert_eq!(obligation.accept_transfer(&buyer).unwrap(), owner);
+        assert_eq!(obligation.owner, buyer);
+        assert_eq!(obligation.pending_owner, Pubkey::default());
+        assert!(obligation.cancel_transfer().is_err());
+    }
+
+    #[test]
+    fn delegates_are_limited_to_their_permissions() {
+        let mut obligation = Obligation::zeroed();
+        let owner = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        obligation.owner = owner;
+
+        assert!(!obligation.is_authorized(&Pubkey::default(), ObligationPermissions::REPAY));
+        assert!(obligation.set_delegate(delegate, 0).is_err());
+        assert!(obligation.set_delegate(delegate, 1 << 63).is_err());
+        assert!(obligation.set_delegate(owner, ObligationPermissions::REPAY.bits()).is_err());
+
+        let permissions = ObligationPermissions::REPAY | ObligationPermissions::DEPOSIT_NFT;
+        obligation.set_delegate(delegate, permissions.bits()).unwrap();
+
+        assert!(obligation.is_authorized(&owner, ObligationPermissions::BORROW));
+        assert!(obligation.is_authorized(&delegate, ObligationPermissions::REPAY));
+        assert!(obligation.is_authorized(&delegate, ObligationPermissions::DEPOSIT_NFT));
+        assert!(!obligation.is_authorized(&delegate, ObligationPermissions::BORROW));
+        assert!(!obligation.is_authorized(&Pubkey::new_unique(), ObligationPermissions::REPAY));
+
+        assert_eq!(obligation.revoke_delegate().unwrap(), delegate);
+        assert!(!obligation.is_authorized(&delegate, ObligationPermissions::REPAY));
+        assert!(obligation.revoke_delegate().is_err());
+    }
+
+    #[test]
+    fn accepting_a_transfer_drops_the_delegate() {
+        let mut obligation = Obligation::zeroed();
+        let buyer = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        obligation.owner = Pubkey::new_unique();
+
+        obligation.set_delegate(delegate, ObligationPermissions::all().bits()).unwrap();
+        obligation.offer_transfer(buyer).unwrap();
+        obligation.accept_transfer(&buyer).unwrap();
+
+        assert_eq!(obligation.delegate, Pubkey::default());
+        assert!(!obligation.is_authorized(&delegate, ObligationPermissions::REPAY));
+    }
+
+    #[test]
+    fn nfts_remember_their_custody() {
+        let mut obligation = Obligation::zeroed();
+        let escrowed = Pubkey::new_unique();
+        let frozen = Pubkey::new_unique();
+        obligation.owner = Pubkey::new_unique();
+
+        obligation.register_nft(escrowed).unwrap();
+        assert_eq!(obligation.nft_custody(&escrowed).unwrap(), NftCustody::Escrow);
+        assert!(obligation.nft_custody(&frozen).is_err());
+
+        // a custody the program never writes is an error rather than a panic
+        obligation.collateral_custody[0] = u8::MAX;
+        assert_eq!(
+            error_code(obligation.nft_custody(&escrowed).unwrap_err()),
+            ErrorCode::NftCustodyMismatch.into()
+        );
+        assert!(obligation.unregister_escrowed_nft(escrowed).is_err());
+        obligation.unregister_nft(escrowed).unwrap();
+
+        obligation.register_nft_with_custody(frozen, NftCustody::Frozen).unwrap();
+        assert_eq!(obligation.nft_custody(&frozen).unwrap(), NftCustody::Frozen);
+        assert!(obligation.has_frozen_collateral());
+        assert!(obligation.offer_transfer(Pubkey::new_unique()).is_err());
+
+        assert!(obligation.unregister_escrowed_nft(frozen).is_err());
+        obligation.unregister_nft(frozen).unwrap();
+        assert!(!obligation.has_frozen_collateral());
+        assert!(obligation.nft_custody(&frozen).is_err());
+        obligation.offer_transfer(Pubkey::new_unique()).unwrap();
+    }
+
+    #[test]
+    fn obligation_transfer_can_be_cancelled() {
+        let mut obligation = Obligation::zeroed();
+        let buyer = Pubkey::new_unique();
+        obligation.owner = Pubkey::new_unique();
+
+        obligation.offer_transfer(buyer).unwrap();
+        assert_eq!(obligation.cancel_transfer().unwrap(), buyer);
+        assert!(obligation.accept_transfer(&buyer).is_err());
+    }
+}
@@ -23,7 +23,8 @@ use std::cmp::Ordering;
 use jet_math::Number;
 use jet_proc_macros::assert_size;
 
-use crate::state::Cache;
+use crate::errors::ErrorCode;
+use crate::state::{verify_liquidation_grace_period, BadDebtCover, Cache, LiquidationSplit};
 use crate::utils::FixedBuf;
 use crate::utils::JobCompletion;
 
@@ -35,6 +36,9 @@ const SECONDS_PER_WEEK: UnixTimestamp = SECONDS_PER_DAY * 7;
 const SECONDS_PER_YEAR: UnixTimestamp = 31_536_000;
 const MAX_ACCRUAL_SECONDS: UnixTimestamp = SECONDS_PER_WEEK;
 
+/// The protocol's fee on new loans, in basis points
+const PROTOCOL_BORROW_FEE_BPS: u16 = 150;
+
 static_assertions::const_assert_eq!(SECONDS_PER_HOUR, 60 * 60);
 static_assertions::const_assert_eq!(SECONDS_PER_2H, 60 * 60 * 2);
 static_assertions::const_assert_eq!(SECONDS_PER_12H, 60 * 60 * 12);
@@ -81,7 +85,8 @@ pub struct ReserveConfig {
     /// The minimum allowable collateralization ratio for an obligation
     pub min_collateral_ratio: u16,
 
-    /// The amount given as a bonus to a liquidator
+    /// The reward paid to the keeper executing a liquidation, in basis
+    /// points of the debt repaid
     pub liquidation_premium: u16,
 
     /// The threshold at which to collect the fees accumulated from interest into
@@ -102,11 +107,45 @@ pub struct ReserveConfig {
     /// liquidating assetr from this reserve as collateral.
     // pub liquidation_dex_trade_max: u64,
 
-    /// unused
-    pub _reserved0: u16,
+    /// The fee rate charged to liquidators on the debt they repay, paid to the protocol
+    pub liquidation_protocol_fee: u16,
+
+    /// The share of liquidation protocol fees paid into the insurance fund, in basis points
+    pub insurance_fund_fee_share: u16,
+
+    /// The most tokens the reserve will supply, counting both the tokens in
+    /// its vault and those lent out, or zero for no limit
+    pub deposit_cap: u64,
+
+    /// The most tokens the reserve will have lent out, or zero for no limit
+    pub borrow_cap: u64,
+
+    /// The most tokens a single obligation may owe the reserve, or zero for no limit
+    pub max_obligation_borrow: u64,
+
+    /// The highest utilization rate a new borrow may leave the reserve at,
+    /// in basis points, or zero for no limit
+    pub max_utilization: u16,
+
+    /// The fee charged on flash loans, in basis points of the amount borrowed
+    pub flash_loan_fee: u16,
 
-    pub _reserved1: [u8; 24],
-    pub _reserved2: [u8; 10]
+    pub _reserved1: [u8; 4],
+}
+
+impl ReserveConfig {
+    /// Reject settings that would break every liquidation, such as a premium
+    /// and fee that together cost the liquidator more than the whole debt
+    pub fn validate(&self) -> Result<()> {
+        let liquidation_cost = self.liquidation_premium as u32 + self.liquidation_protocol_fee as u32;
+
+        if liquidation_cost > 10_000 {
+            msg!("liquidation premium and protocol fee add up to {} bps", liquidation_cost);
+            return err!(ErrorCode::InvalidReserveConfig);
+        }
+
+        Ok(())
+    }
 }
 
 #[assert_size(1976)]
@@ -144,7 +183,19 @@ pub struct Reserve {
     /// The account with custody of the notes generated from protocol collected fees
     pub protocol_fee_note_vault: Pubkey,
 
-    pub _reserved0: [u8; 408],
+    /// Storage for flags that can be set on the reserve.
+    pub flags: u64,
+
+    /// The time repays were last resumed after being halted
+    pub repays_resumed_at: i64,
+
+    /// The account holding tokens set aside to cover bad debt
+    pub insurance_fund: Pubkey,
+
+    /// The tokens lent out by a flash loan that has yet to be repaid
+    pub flash_loan_amount: u64,
+
+    pub _reserved0: [u8; 352],
 
     pub config: ReserveConfig,
 
@@ -174,13 +225,156 @@ impl Reserve {
         self.state().get_stale().total_loan_notes
     }
 
-    pub fn unwrap_outstanding_debt(&self, current_slot: u64) -> &Number {
-        &self.unwrap_state(current_slot).outstanding_debt
+    pub fn outstanding_debt(&self, current_slot: u64) -> Result<Number> {
+        Ok(self.try_state(current_slot)?.outstanding_debt)
     }
 
     #[cfg(test)]
     fn unwrap_outstanding_debt_mut(&mut self, current_slot: u64) -> &mut Number {
-        &mut self.unwrap_state_mut(current_slot).outstanding_debt
+        &mut self.try_state_mut(current_slot).unwrap().outstanding_debt
+    }
+
+    /// Whether the reserve state needs to be refreshed before it can be used
+    pub fn is_stale(&self, current_slot: u64) -> bool {
+        self.state().validate_fresh(current_slot).is_err()
+    }
+
+    /// Get the current flags set on the reserve
+    pub fn flags(&self) -> ReserveFlags {
+        ReserveFlags::from_bits_truncate(self.flags)
+    }
+
+    /// Set new flags on the reserve, starting the liquidation grace
+    /// period if repays are resumed
+    pub fn reset_flags(&mut self, flags: ReserveFlags, current_time: UnixTimestamp) {
+        if self.flags().resumes_repays(flags) {
+            self.repays_resumed_at = current_time;
+        }
+
+        self.flags = flags.bits();
+    }
+
+    /// Add flags to the reserve, leaving any already set in place
+    pub fn pause(&mut self, flags: ReserveFlags) {
+        self.flags |= flags.bits();
+    }
+
+    /// Verify that the reserve is currently allowing deposits and withdrawals
+    pub fn verify_ability_deposit_withdraw(&self) -> Result<()> {
+        self.verify_not_halted(ReserveFlags::HALT_DEPOSITS, "deposits/withdrawals")
+    }
+
+    /// Verify that the reserve is currently allowing new deposits
+    pub fn verify_ability_deposit(&self) -> Result<()> {
+        self.verify_ability_deposit_withdraw()?;
+        self.verify_not_halted(ReserveFlags::WIND_DOWN, "new deposits")
+    }
+
+    /// Verify that the reserve is currently allowing new borrows
+    pub fn verify_ability_borrow(&self) -> Result<()> {
+        self.verify_not_halted(ReserveFlags::HALT_BORROWS | ReserveFlags::WIND_DOWN, "borrows")
+    }
+
+    /// Verify that depositing another `token_amount` tokens keeps the
+    /// reserve's supply under its deposit cap
+    pub fn verify_deposit_cap(&self, current_slot: u64, token_amount: u64) -> Result<()> {
+        let state = self.try_state(current_slot)?;
+
+        if self.config.deposit_cap == 0 {
+            return Ok(());
+        }
+
+        let supply = Number::from(state.total_deposits) + state.outstanding_debt;
+        if supply + Number::from(token_amount) > Number::from(self.config.deposit_cap) {
+            return err!(ErrorCode::DepositCapExceeded);
+        }
+
+        Ok(())
+    }
+
+    /// Verify that lending out `token_amount` tokens, for `debt` tokens owed
+    /// by an obligation that will then owe `obligation_debt` tokens in total,
+    /// stays within the reserve's borrow limits
+    pub fn verify_borrow_limits(
+        &self,
+        current_slot: u64,
+        token_amount: u64,
+        debt: u64,
+        obligation_debt: u64,
+    ) -> Result<()> {
+        let state = self.try_state(current_slot)?;
+        let config = &self.config;
+        let outstanding_debt = state.outstanding_debt + Number::from(debt);
+
+        if config.borrow_cap != 0 && outstanding_debt > Number::from(config.borrow_cap) {
+            return err!(ErrorCode::BorrowCapExceeded);
+        }
+
+        if config.max_obligation_borrow != 0 && obligation_debt > config.max_obligation_borrow {
+            return err!(ErrorCode::ObligationBorrowCapExceeded);
+        }
+
+        let vault_total = state.total_deposits.saturating_sub(token_amount);
+        if config.max_utilization != 0
+            && utilization_rate(outstanding_debt, vault_total) > Number::from_bps(config.max_utilization)
+        {
+            return err!(ErrorCode::MaxUtilizationExceeded);
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the reserve is winding down with nothing left owed to or
+    /// by its users, given the number of deposit notes held as fees.
+    pub fn verify_closable(&self, fee_notes: u64) -> Result<()> {
+        let state = self.state().get_stale();
+
+        if !self.flags().contains(ReserveFlags::WIND_DOWN) {
+            msg!("the reserve must be winding down before it can be closed");
+            return err!(ErrorCode::ReserveNotClosable);
+        }
+
+        if state.total_loan_notes != 0 || state.outstanding_debt != Number::ZERO {
+            msg!("the reserve still has loans outstanding");
+            return err!(ErrorCode::ReserveNotClosable);
+        }
+
+        if state.total_deposit_notes != fee_notes {
+            msg!("the reserve still has deposits outstanding");
+            return err!(ErrorCode::ReserveNotClosable);
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the reserve is currently allowing repayments to loans
+    pub fn verify_ability_repay(&self) -> Result<()> {
+        self.verify_not_halted(ReserveFlags::HALT_REPAYS, "repays")
+    }
+
+    /// Verify that the reserve is currently allowing its loans to be liquidated
+    pub fn verify_ability_liquidate(&self, current_time: UnixTimestamp) -> Result<()> {
+        // borrowers must be able to repay before they can be liquidated
+        self.verify_not_halted(
+            ReserveFlags::HALT_LIQUIDATIONS | ReserveFlags::HALT_REPAYS,
+            "liquidations",
+        )?;
+
+        verify_liquidation_grace_period(self.repays_resumed_at, current_time)
+    }
+
+    fn verify_not_halted(&self, flags: ReserveFlags, operation: &str) -> Result<()> {
+        if self.flags().intersects(flags) {
+            msg!("the reserve is currently not allowing {}", operation);
+            return err!(ErrorCode::ReserveHalted);
+        }
+
+        Ok(())
+    }
+
+    /// Set the number of slots the reserve state stays fresh for after a refresh
+    pub fn set_cache_ttl(&mut self, ttl: u64) {
+        self.state_mut().set_time_to_live(ttl as u32);
     }
 
     fn state(&self) -> &Cache<ReserveState, 1> {
@@ -191,14 +385,20 @@ impl Reserve {
         bytemuck::from_bytes_mut(&mut self.state)
     }
 
-    fn unwrap_state(&self, current_slot: u64) -> &ReserveState {
-        self.state()
-            .expect(current_slot, "Reserve needs to be refreshed")
+    fn try_state(&self, current_slot: u64) -> Result<&ReserveState> {
+        self.state().try_get(current_slot).map_err(|e| {
+            msg!("reserve needs to be refreshed: {:?}", e);
+            let code = ErrorCode::from(e);
+            error!(code)
+        })
     }
 
-    fn unwrap_state_mut(&mut self, current_slot: u64) -> &mut ReserveState {
-        self.state_mut()
-            .expect_mut(current_slot, "Reserve needs to be refreshed")
+    fn try_state_mut(&mut self, current_slot: u64) -> Result<&mut ReserveState> {
+        self.state_mut().try_get_mut(current_slot).map_err(|e| {
+            msg!("reserve needs to be refreshed: {:?}", e);
+            let code = ErrorCode::from(e);
+            error!(code)
+        })
     }
 
     /// Record an amount of tokens deposited into the reserve
@@ -210,11 +410,24 @@ impl Reserve {
     }
 
     /// Record an amount of tokens withdrawn from the reserve
-    pub fn withdraw(&mut self, token_amount: u64, note_amount: u64) {
+    pub fn withdraw(&mut self, token_amount: u64, note_amount: u64) -> Result<()> {
         let state = self.state_mut().get_stale_mut();
 
-        state.total_deposits = state.total_deposits.checked_sub(token_amount).unwrap();
-        state.total_deposit_notes = state.total_deposit_notes.checked_sub(note_amount).unwrap();
+        state.total_deposits = state
+            .total_deposits
+            .checked_sub(token_amount)
+            .ok_or(ErrorCode::InsufficientLiquidity)?;
+        state.total_deposit_notes = state
+            .total_deposit_notes
+            .checked_sub(note_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    /// The tokens available to be withdrawn, given the balance of the vault
+    pub fn available_liquidity(&self, vault_total: u64) -> u64 {
+        std::cmp::min(self.total_deposits(), vault_total)
     }
 
     /// Calculates the borrow fee token amount for
@@ -226,14 +439,95 @@ impl Reserve {
         fee_owed.as_u64_ceil(0)
     }
 
+    /// The least a liquidator pays to repay `debt` tokens of a loan. Without
+    /// an insurance fund, the protocol keeps the whole fee.
+    pub fn liquidation_price(&self, debt: u64) -> Result<LiquidationSplit> {
+        let insurance_share = match self.has_insurance_fund() {
+            true => self.config.insurance_fund_fee_share,
+            false => 0,
+        };
+
+        LiquidationSplit::price(
+            debt,
+            self.config.liquidation_premium,
+            self.config.liquidation_protocol_fee,
+            insurance_share,
+        )
+    }
+
+    /// Whether the reserve has an insurance fund. Reserves initialized
+    /// before there were insurance funds have none until
+    /// `init_insurance_fund` creates one.
+    pub fn has_insurance_fund(&self) -> bool {
+        self.insurance_fund != Pubkey::default()
+    }
+
+    /// Check an account passed as the reserve's insurance fund, which can
+    /// be any account while the reserve has none
+    pub fn is_insurance_fund(&self, account: &Pubkey) -> bool {
+        !self.has_insurance_fund() || self.insurance_fund == *account
+    }
+
     /// Calculates the protocol borrow fee
     pub fn protocol_fee(&self, token_amount: u64) -> u64 {
-        let origination_fee = Number::from_bps(150);
+        let origination_fee = Number::from_bps(PROTOCOL_BORROW_FEE_BPS);
         let fee_owed = origination_fee * token_amount;
 
         fee_owed.as_u64_ceil(0)
     }
 
+    /// The most tokens that can be borrowed while owing at most `max_debt`
+    /// tokens once fees are added
+    pub fn max_borrow_for_debt(&self, max_debt: u64) -> u64 {
+        let debt_rate = Number::ONE
+            + Number::from_bps(self.config.loan_origination_fee)
+            + Number::from_bps(PROTOCOL_BORROW_FEE_BPS);
+        let mut tokens = (Number::from(max_debt) / debt_rate).as_u64(0);
+
+        // fees are rounded up, which may take the debt a token or two over
+        while tokens > 0 && self.borrow_debt(tokens) > max_debt {
+            tokens -= 1;
+        }
+
+        tokens
+    }
+
+    /// Record a flash loan of `token_amount` tokens, of which there may
+    /// only be one outstanding at a time
+    pub fn flash_borrow(&mut self, token_amount: u64) -> Result<()> {
+        if self.flash_loan_amount != 0 {
+            return err!(ErrorCode::FlashLoanActive);
+        }
+        if token_amount == 0 {
+            return err!(ErrorCode::InvalidParameter);
+        }
+
+        self.flash_loan_amount = token_amount;
+        Ok(())
+    }
+
+    /// Record the outstanding flash loan as repaid, returning the tokens
+    /// lent and the fee owed on them
+    pub fn flash_repay(&mut self) -> Result<(u64, u64)> {
+        let amount = self.flash_loan_amount;
+
+        if amount == 0 {
+            return err!(ErrorCode::NoFlashLoan);
+        }
+
+        let fee = (Number::from_bps(self.config.flash_loan_fee) * amount).as_u64_ceil(0);
+        self.flash_loan_amount = 0;
+
+        Ok((amount, fee))
+    }
+
+    /// The tokens owed for borrowing `token_amount`, including fees
+    pub fn borrow_debt(&self, token_amount: u64) -> u64 {
+        token_amount
+            .saturating_add(self.borrow_fee(token_amount))
+            .saturating_add(self.protocol_fee(token_amount))
+    }
+
     /// Record an amount of tokens to be borrowed from the reserve.
     pub fn borrow(
         &mut self,
@@ -242,10 +536,10 @@ impl Reserve {
         note_amount: u64,
         fees: u64,
         protocol_fees: u64,
-    ) {
+    ) -> Result<()> {
         let borrowed_amount = Number::from(token_amount);
 
-        let state = self.unwrap_state_mut(current_slot);
+        let state = self.try_state_mut(current_slot)?;
 
         let fees = Number::from_decimal(fees, 0);
 
@@ -256,11 +550,35 @@ impl Reserve {
         state.outstanding_debt += borrowed_amount + fees + protocol_fees;
         state.total_deposits = state.total_deposits.checked_sub(token_amount).unwrap();
         state.total_loan_notes = state.total_loan_notes.checked_add(note_amount).unwrap();
+
+        Ok(())
+    }
+
+    /// Remove debt that will never be repaid from the reserve, leaving
+    /// depositors to absorb the loss.
+    pub fn write_off_debt(&mut self, current_slot: u64, token_amount: u64) -> Result<()> {
+        let state = self.try_state_mut(current_slot)?;
+
+        state.outstanding_debt = state.outstanding_debt.saturating_sub(Number::from(token_amount));
+
+        if state.total_loan_notes == 0 && state.outstanding_debt < Number::ONE {
+            // Truncate any leftover fraction from debts
+            state.outstanding_debt = Number::ZERO;
+        }
+
+        Ok(())
+    }
+
+    /// Record a shortfall paid from the insurance fund into the vault, with
+    /// the remainder written down against depositors.
+    pub fn cover_bad_debt(&mut self, current_slot: u64, cover: &BadDebtCover) -> Result<()> {
+        self.repay(current_slot, cover.insured, 0)?;
+        self.write_off_debt(current_slot, cover.written_off)
     }
 
     /// Record an amount of tokens repaid back to the reserve.
-    pub fn repay(&mut self, current_slot: u64, token_amount: u64, note_amount: u64) {
-        let state = self.unwrap_state_mut(current_slot);
+    pub fn repay(&mut self, current_slot: u64, token_amount: u64, note_amount: u64) -> Result<()> {
+        let state = self.try_state_mut(current_slot)?;
 
         state.outstanding_debt -= Number::from(token_amount);
         state.total_loan_notes = state.total_loan_notes.checked_sub(note_amount).unwrap();
@@ -270,15 +588,19 @@ impl Reserve {
             // Truncate any leftover fraction from debts
             state.outstanding_debt = Number::ZERO;
         }
+
+        Ok(())
     }
 
     /// Record an amount of tokens added to the vault which need
     /// to be collected as fees later.
     /// might have to add protocol fees also TBD during liquidation
-    pub fn add_uncollected_fees(&mut self, current_slot: u64, amount: u64) {
-        let state = self.unwrap_state_mut(current_slot);
+    pub fn add_uncollected_fees(&mut self, current_slot: u64, amount: u64) -> Result<()> {
+        let state = self.try_state_mut(current_slot)?;
         state.uncollected_fees += Number::from(amount);
         state.total_deposits = state.total_deposits.checked_add(amount).unwrap();
+
+        Ok(())
     }
 
     /// Calculate the exchange rate for deposit notes (tokens per note)
@@ -287,8 +609,8 @@ impl Reserve {
         current_slot: u64,
         vault_total: u64,
         mint_supply: u64,
-    ) -> Number {
-        let state = self.unwrap_state(current_slot);
+    ) -> Result<Number> {
+        let state = self.try_state(current_slot)?;
         let calc = DepositNoteCalculator {
             outstanding_debt: state.outstanding_debt,
             uncollected_fees: state.uncollected_fees,
@@ -297,18 +619,18 @@ impl Reserve {
             mint_supply,
         };
 
-        calc.exchange_rate()
+        Ok(calc.exchange_rate())
     }
 
     /// Calculate the exchange rate for loan notes (tokens per note)
-    pub fn loan_note_exchange_rate(&self, current_slot: u64, mint_supply: u64) -> Number {
-        let state = self.unwrap_state(current_slot);
+    pub fn loan_note_exchange_rate(&self, current_slot: u64, mint_supply: u64) -> Result<Number> {
+        let state = self.try_state(current_slot)?;
         let calc = LoanNoteCalculator {
             outstanding_debt: state.outstanding_debt,
             mint_supply,
         };
 
-        calc.exchange_rate()
+        Ok(calc.exchange_rate())
     }
 
     /// Accrue the interest charges for outstanding borrows
@@ -363,13 +685,13 @@ impl Reserve {
     /// Collect any fees that were accumulated
     ///
     /// Returns the number of notes to mint to represent the fees collected
-    pub fn collect_accrued_fees(&mut self, current_slot: u64, exchange_rate: Number) -> u64 {
+    pub fn collect_accrued_fees(&mut self, current_slot: u64, exchange_rate: Number) -> Result<u64> {
         let threshold = Number::from(self.config.manage_fee_collection_threshold);
-        let state = self.unwrap_state_mut(current_slot);
+        let state = self.try_state_mut(current_slot)?;
 
         if threshold > state.uncollected_fees {
             // not enough accumulated to be worth minting new notes for
-            return 0;
+            return Ok(0);
         }
 
         let fee_notes = (state.uncollected_fees / exchange_rate).as_u64(0);
@@ -377,7 +699,7 @@ impl Reserve {
         state.uncollected_fees = Number::ZERO;
         state.total_deposit_notes = state.total_deposit_notes.checked_add(fee_notes).unwrap();
 
-        fee_notes
+        Ok(fee_notes)
     }
 
     /// Collect any protocol fees that were accumulated
@@ -387,13 +709,13 @@ impl Reserve {
         &mut self,
         current_slot: u64,
         exchange_rate: Number,
-    ) -> u64 {
+    ) -> Result<u64> {
         let threshold = Number::from(self.config.manage_fee_collection_threshold);
-        let state = self.unwrap_state_mut(current_slot);
+        let state = self.try_state_mut(current_slot)?;
 
         if threshold > state.protocol_uncollected_fees {
             // not enough accumulated to be worth minting new notes for
-            return 0;
+            return Ok(0);
         }
 
         let fee_notes = (state.protocol_uncollected_fees / exchange_rate).as_u64(0);
@@ -401,7 +723,7 @@ impl Reserve {
         state.protocol_uncollected_fees = Number::ZERO;
         state.total_deposit_notes = state.total_deposit_notes.checked_add(fee_notes).unwrap();
 
-        fee_notes
+        Ok(fee_notes)
     }
 
     /// Computes the effective applicable interest rate assuming continuous
@@ -482,6 +804,39 @@ impl Reserve {
     }
 }
 
+bitflags::bitflags! {
+    pub struct ReserveFlags: u64 {
+        /// Disable borrowing from the reserve
+        const HALT_BORROWS = 1 << 0;
+
+        /// Disable repaying loans to the reserve
+        const HALT_REPAYS = 1 << 1;
+
+        /// Disable deposits + withdrawals
+        const HALT_DEPOSITS = 1 << 2;
+
+        /// Disable liquidating loans taken from the reserve
+        const HALT_LIQUIDATIONS = 1 << 3;
+
+        /// Disable all operations
+        const HALT_ALL = Self::HALT_BORROWS.bits
+                       | Self::HALT_REPAYS.bits
+                       | Self::HALT_DEPOSITS.bits
+                       | Self::HALT_LIQUIDATIONS.bits;
+
+        /// Disable new deposits and borrows so the reserve can be emptied
+        /// and closed, while still allowing withdrawals and repays
+        const WIND_DOWN = 1 << 4;
+    }
+}
+
+impl ReserveFlags {
+    /// Whether replacing these flags with `new_flags` allows repays again
+    pub fn resumes_repays(&self, new_flags: ReserveFlags) -> bool {
+        self.contains(ReserveFlags::HALT_REPAYS) && !new_flags.contains(ReserveFlags::HALT_REPAYS)
+    }
+}
+
 /// Information about a single collateral or loan account registered with an obligation
 #[assert_size(aligns, 520)]
 #[derive(Pod, Zeroable, Clone, Copy)]
@@ -618,8 +973,10 @@ impl std::fmt::Debug for Reserve {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::error_code;
     use bytemuck::Zeroable;
 
+
     #[test]
     fn sane_deposit_note_exchange_rate() {
         let vault_total = 100_000_000;
@@ -969,7 +1326,9 @@ mod tests {
         let deposit_notes = 200_000_000_000;
         *reserve.unwrap_outstanding_debt_mut(0) = Number::from(100_000_000_000u64);
 
-        let deposit_note_value = reserve.deposit_note_exchange_rate(0, vault_total, deposit_notes);
+        let deposit_note_value = reserve
+            .deposit_note_exchange_rate(0, vault_total, deposit_notes)
+            .unwrap();
 
         assert_eq!(deposit_note_value, Number::from(1));
 
@@ -977,7 +1336,9 @@ mod tests {
 
         reserve.try_accrue_interest(vault_total, target_time, 0);
 
-        let deposit_note_value = reserve.deposit_note_exchange_rate(0, vault_total, deposit_notes);
+        let deposit_note_value = reserve
+            .deposit_note_exchange_rate(0, vault_total, deposit_notes)
+            .unwrap();
 
         assert_eq!(deposit_note_value.as_u64(-6), 1_000_107);
 
@@ -985,8 +1346,252 @@ mod tests {
 
         assert_eq!(owed, 100_023_975_476);
 
-        let fees = reserve.collect_accrued_fees(0, deposit_note_value);
+        let fees = reserve.collect_accrued_fees(0, deposit_note_value).unwrap();
 
         assert_eq!(fees, 2_397_288);
     }
+
+    #[test]
+    fn reserve_flags_halt_their_operations() {
+        let mut reserve = Reserve::zeroed();
+
+        let halted = |reserve: &Reserve| {
+            [
+                reserve.verify_ability_deposit().is_err(),
+                reserve.verify_ability_deposit_withdraw().is_err(),
+                reserve.verify_ability_borrow().is_err(),
+                reserve.verify_ability_repay().is_err(),
+                reserve.verify_ability_liquidate(0).is_err(),
+            ]
+        };
+        assert_eq!(halted(&reserve), [false; 5]);
+
+        let cases = [
+            (ReserveFlags::HALT_DEPOSITS, [true, true, false, false, false]),
+            (ReserveFlags::HALT_BORROWS, [false, false, true, false, false]),
+            (ReserveFlags::HALT_REPAYS, [false, false, false, true, true]),
+            (ReserveFlags::HALT_LIQUIDATIONS, [false, false, false, false, true]),
+            (ReserveFlags::WIND_DOWN, [true, false, true, false, false]),
+            (ReserveFlags::HALT_ALL, [true; 5]),
+        ];
+
+        for (flags, expected) in cases {
+            reserve.reset_flags(flags, 0);
+            assert_eq!(halted(&reserve), expected, "{:?}", flags);
+        }
+
+        reserve.reset_flags(ReserveFlags::HALT_BORROWS, 0);
+        reserve.pause(ReserveFlags::HALT_REPAYS);
+        assert_eq!(reserve.flags(), ReserveFlags::HALT_BORROWS | ReserveFlags::HALT_REPAYS);
+
+        // resuming repays starts the liquidation grace period
+        reserve.reset_flags(ReserveFlags::empty(), 500);
+        assert_eq!(reserve.repays_resumed_at, 500);
+        assert!(reserve.verify_ability_liquidate(500).is_err());
+        assert!(reserve
+            .verify_ability_liquidate(500 + crate::state::LIQUIDATION_GRACE_PERIOD)
+            .is_ok());
+    }
+
+    #[test]
+    fn reserve_closes_only_when_wound_down_and_empty() {
+        let mut reserve = Reserve::zeroed();
+        reserve.deposit(1_000, 1_000);
+        reserve.borrow(0, 500, 500, 0, 0).unwrap();
+
+        assert!(reserve.verify_closable(0).is_err());
+
+        reserve.reset_flags(ReserveFlags::WIND_DOWN, 0);
+        assert!(reserve.verify_closable(0).is_err());
+
+        reserve.repay(0, 500, 500).unwrap();
+        assert!(reserve.verify_closable(0).is_err());
+
+        // fees collected as deposit notes don't keep the reserve open
+        reserve.withdraw(990, 990).unwrap();
+        assert!(reserve.verify_closable(0).is_err());
+        assert!(reserve.verify_closable(10).is_ok());
+
+        reserve.reset_flags(ReserveFlags::empty(), 0);
+        assert!(reserve.verify_closable(10).is_err());
+    }
+
+    #[test]
+    fn liquidation_price_is_debt_plus_premium_and_fee() {
+        let mut reserve = Reserve::zeroed();
+        reserve.config.liquidation_premium = 500;
+        reserve.config.liquidation_protocol_fee = 100;
+
+        let price = reserve.liquidation_price(10_000).unwrap();
+        assert_eq!((price.repay, price.premium, price.protocol_fee), (10_000, 500, 100));
+        assert_eq!(price.total().unwrap(), 10_600);
+
+        reserve.config.liquidation_premium = 0;
+        reserve.config.liquidation_protocol_fee = 0;
+        assert_eq!(reserve.liquidation_price(10_000).unwrap().total().unwrap(), 10_000);
+    }
+
+    #[test]
+    fn liquidation_premium_and_fee_are_capped_at_the_debt() {
+        let mut config = Reserve::zeroed().config;
+        config.liquidation_premium = 9_000;
+        config.liquidation_protocol_fee = 1_000;
+        assert!(config.validate().is_ok());
+
+        config.liquidation_protocol_fee = 1_001;
+        assert_eq!(error_code(config.validate().unwrap_err()), ErrorCode::InvalidReserveConfig.into());
+
+        config.liquidation_premium = u16::MAX;
+        config.liquidation_protocol_fee = u16::MAX;
+        assert_eq!(error_code(config.validate().unwrap_err()), ErrorCode::InvalidReserveConfig.into());
+    }
+
+    #[test]
+    fn reserves_without_an_insurance_fund_keep_the_whole_fee() {
+        let mut reserve = Reserve::zeroed();
+        reserve.config.liquidation_protocol_fee = 100;
+        reserve.config.insurance_fund_fee_share = 2_500;
+
+        let any_account = Pubkey::new_unique();
+        assert!(!reserve.has_insurance_fund());
+        assert!(reserve.is_insurance_fund(&any_account));
+
+        let price = reserve.liquidation_price(10_000).unwrap();
+        assert_eq!((price.protocol_fee, price.insurance_fee), (100, 0));
+
+        reserve.insurance_fund = Pubkey::new_unique();
+        assert!(!reserve.is_insurance_fund(&any_account));
+        assert!(reserve.is_insurance_fund(&reserve.insurance_fund.clone()));
+
+        let price = reserve.liquidation_price(10_000).unwrap();
+        assert_eq!((price.protocol_fee, price.insurance_fee), (75, 25));
+    }
+
+    #[test]
+    fn written_off_debt_is_not_repaid_to_depositors() {
+        let mut reserve = Reserve::zeroed();
+        reserve.deposit(1_000, 1_000);
+        reserve.borrow(0, 500, 500, 0, 0).unwrap();
+
+        reserve.repay(0, 200, 500).unwrap();
+        assert_eq!(reserve.outstanding_debt(0).unwrap(), Number::from(300u64));
+
+        reserve.write_off_debt(0, 300).unwrap();
+        assert_eq!(reserve.outstanding_debt(0).unwrap(), Number::ZERO);
+        assert_eq!(reserve.total_deposits(), 700);
+        assert_eq!(reserve.total_loan_notes(), 0);
+    }
+
+    #[test]
+    fn insured_bad_debt_is_returned_to_depositors() {
+        let mut reserve = Reserve::zeroed();
+        reserve.deposit(1_000, 1_000);
+        reserve.borrow(0, 500, 500, 0, 0).unwrap();
+
+        reserve.repay(0, 200, 500).unwrap();
+        reserve
+            .cover_bad_debt(0, &BadDebtCover::new(300, 100))
+            .unwrap();
+
+        assert_eq!(reserve.outstanding_debt(0).unwrap(), Number::ZERO);
+        assert_eq!(reserve.total_deposits(), 800);
+    }
+
+    #[test]
+    fn deposits_respect_deposit_cap() {
+        let mut reserve = Reserve::zeroed();
+        reserve.deposit(1_000, 1_000);
+        reserve.borrow(0, 400, 400, 0, 0).unwrap();
+
+        assert!(reserve.verify_deposit_cap(0, u64::MAX / 2).is_ok());
+
+        reserve.config.deposit_cap = 1_500;
+        assert!(reserve.verify_deposit_cap(0, 500).is_ok());
+        assert_eq!(
+            error_code(reserve.verify_deposit_cap(0, 501).unwrap_err()),
+            ErrorCode::DepositCapExceeded.into()
+        );
+    }
+
+    #[test]
+    fn borrows_respect_caps_and_max_utilization() {
+        let mut reserve = Reserve::zeroed();
+        reserve.deposit(1_000, 1_000);
+        reserve.borrow(0, 400, 400, 0, 0).unwrap();
+
+        assert!(reserve.verify_borrow_limits(0, 600, 600, 600).is_ok());
+
+        reserve.config.borrow_cap = 700;
+        assert!(reserve.verify_borrow_limits(0, 300, 300, 300).is_ok());
+        assert_eq!(
+            error_code(reserve.verify_borrow_limits(0, 300, 301, 301).unwrap_err()),
+            ErrorCode::BorrowCapExceeded.into()
+        );
+
+        reserve.config.max_obligation_borrow = 500;
+        assert!(reserve.verify_borrow_limits(0, 100, 100, 500).is_ok());
+        assert_eq!(
+            error_code(reserve.verify_borrow_limits(0, 100, 100, 501).unwrap_err()),
+            ErrorCode::ObligationBorrowCapExceeded.into()
+        );
+
+        // 400 lent out of 1000 supplied, so another 100 reaches 50%
+        reserve.config.max_utilization = 5_000;
+        assert!(reserve.verify_borrow_limits(0, 100, 100, 100).is_ok());
+        assert_eq!(
+            error_code(reserve.verify_borrow_limits(0, 101, 101, 101).unwrap_err()),
+            ErrorCode::MaxUtilizationExceeded.into()
+        );
+    }
+
+    #[test]
+    fn withdrawals_are_limited_to_available_liquidity() {
+        let mut reserve = Reserve::zeroed();
+        reserve.deposit(1_000, 1_000);
+        reserve.borrow(0, 600, 600, 0, 0).unwrap();
+
+        assert_eq!(reserve.available_liquidity(5_000), 400);
+        assert_eq!(reserve.available_liquidity(300), 300);
+
+        assert_eq!(
+            error_code(reserve.withdraw(401, 401).unwrap_err()),
+            ErrorCode::InsufficientLiquidity.into()
+        );
+        reserve.withdraw(400, 400).unwrap();
+        assert_eq!(reserve.total_deposits(), 0);
+    }
+
+    #[test]
+    fn max_borrow_leaves_room_for_fees() {
+        let mut reserve = Reserve::zeroed();
+        reserve.config.loan_origination_fee = 10;
+
+        for max_debt in [0, 1, 99, 1_000, 1_000_003, 123_456_789] {
+            let tokens = reserve.max_borrow_for_debt(max_debt);
+
+            assert!(reserve.borrow_debt(tokens) <= max_debt);
+            assert!(reserve.borrow_debt(tokens + 1) > max_debt);
+        }
+    }
+
+    #[test]
+    fn one_flash_loan_at_a_time() {
+        let mut reserve = Reserve::zeroed();
+        reserve.config.flash_loan_fee = 9;
+
+        assert_eq!(
+            error_code(reserve.flash_repay().unwrap_err()),
+            ErrorCode::NoFlashLoan.into()
+        );
+
+        reserve.flash_borrow(10_000).unwrap();
+        assert_eq!(
+            error_code(reserve.flash_borrow(1).unwrap_err()),
+            ErrorCode::FlashLoanActive.into()
+        );
+
+        assert_eq!(reserve.flash_repay().unwrap(), (10_000, 9));
+        reserve.flash_borrow(1).unwrap();
+        assert_eq!(reserve.flash_repay().unwrap(), (1, 1));
+    }
 }
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+/// The roles that can be delegated by a market owner
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarketRole {
+    /// Allowed to change reserve configuration and risk parameters
+    RiskAdmin,
+
+    /// Allowed to halt operations, but not to resume them
+    Guardian,
+
+    /// Receives the protocol's share of liquidation proceeds
+    FeeCollector,
+
+    /// Allowed to liquidate and withdraw collateral through solvent
+    SolventExecutor,
+}
+
+/// The accounts holding each delegated role in a market. The owner role
+/// is held by `Market::owner`, which is the only account allowed to change
+/// these.
+#[account]
+#[derive(Default)]
+pub struct MarketRoles {
+    /// The market these roles apply to
+    pub market: Pubkey,
+
+    pub risk_admin: Pubkey,
+    pub guardian: Pubkey,
+    pub fee_collector: Pubkey,
+    pub solvent_executor: Pubkey,
+
+    pub bump: u8,
+}
+
+impl MarketRoles {
+    /// The account currently holding a role, or the default key if unassigned
+    pub fn holder(&self, role: MarketRole) -> Pubkey {
+        match role {
+            MarketRole::RiskAdmin => self.risk_admin,
+            MarketRole::Guardian => self.guardian,
+            MarketRole::FeeCollector => self.fee_collector,
+            MarketRole::SolventExecutor => self.solvent_executor,
+        }
+    }
+
+    /// Assign a role to a new account, or unassign it with the default key
+    pub fn set_holder(&mut self, role: MarketRole, holder: Pubkey) {
+        let slot = match role {
+            MarketRole::RiskAdmin => &mut self.risk_admin,
+            MarketRole::Guardian => &mut self.guardian,
+            MarketRole::FeeCollector => &mut self.fee_collector,
+            MarketRole::SolventExecutor => &mut self.solvent_executor,
+        };
+
+        *slot = holder;
+    }
+
+    /// Check if an account holds an assigned role
+    pub fn has_role(&self, role: MarketRole, account: &Pubkey) -> bool {
+        let holder = self.holder(role);
+        holder != Pubkey::default() && holder == *account
+    }
+
+    /// Check if an account may halt operations on the market and its reserves
+    pub fn is_pause_authority(&self, owner: &Pubkey, account: &Pubkey) -> bool {
+        account == owner || self.has_role(MarketRole::Guardian, account)
+    }
+
+    /// Check if an account may change risk parameters on the market's reserves
+    pub fn is_risk_authority(&self, owner: &Pubkey, account: &Pubkey) -> bool {
+        account == owner || self.has_role(MarketRole::RiskAdmin, account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROLES: [MarketRole; 4] = [
+        MarketRole::RiskAdmin,
+        MarketRole::Guardian,
+        MarketRole::FeeCollector,
+        MarketRole::SolventExecutor,
+    ];
+
+    #[test]
+    fn roles_are_held_independently() {
+        let mut roles = MarketRoles::default();
+        let holders = ROLES.map(|_| Pubkey::new_unique());
+
+        for role in ROLES {
+            assert!(!roles.has_role(role, &Pubkey::default()));
+        }
+
+        for (role, holder) in ROLES.iter().zip(holders) {
+            roles.set_holder(*role, holder);
+        }
+
+        for (i, role) in ROLES.iter().enumerate() {
+            for (j, holder) in holders.iter().enumerate() {
+                assert_eq!(roles.has_role(*role, holder), i == j, "{:?}", role);
+            }
+        }
+
+        roles.set_holder(MarketRole::Guardian, Pubkey::default());
+        assert!(!roles.has_role(MarketRole::Guardian, &holders[1]));
+        assert!(!roles.has_role(MarketRole::Guardian, &Pubkey::default()));
+    }
+
+    #[test]
+    fn owner_keeps_pause_and_risk_authority() {
+        let mut roles = MarketRoles::default();
+        let owner = Pubkey::new_unique();
+        let guardian = Pubkey::new_unique();
+        let risk_admin = Pubkey::new_unique();
+
+        assert!(roles.is_pause_authority(&owner, &owner));
+        assert!(roles.is_risk_authority(&owner, &owner));
+        assert!(!roles.is_pause_authority(&owner, &guardian));
+        assert!(!roles.is_pause_authority(&owner, &Pubkey::default()));
+
+        roles.set_holder(MarketRole::Guardian, guardian);
+        roles.set_holder(MarketRole::RiskAdmin, risk_admin);
+
+        assert!(roles.is_pause_authority(&owner, &guardian));
+        assert!(!roles.is_risk_authority(&owner, &guardian));
+        assert!(roles.is_risk_authority(&owner, &risk_admin));
+        assert!(!roles.is_pause_authority(&owner, &risk_admin));
+    }
+}
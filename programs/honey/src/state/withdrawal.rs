@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anchor_lang::prelude::*;
+
+use crate::common::Rounding;
+use crate::errors::ErrorCode;
+use crate::state::CachedReserveInfo;
+
+/// A depositor's request to withdraw deposit notes the reserve doesn't yet
+/// have the liquidity to pay out, filled as loans are repaid
+#[account]
+#[derive(Default)]
+pub struct WithdrawalRequest {
+    pub reserve: Pubkey,
+    pub depositor: Pubkey,
+
+    /// The token account the withdrawn tokens are paid out to
+    pub receiver: Pubkey,
+
+    /// The deposit notes still waiting to be withdrawn
+    pub notes_remaining: u64,
+
+    /// The tokens paid out by the request so far
+    pub tokens_filled: u64,
+
+    pub bump: u8,
+}
+
+impl WithdrawalRequest {
+    /// The notes that can be withdrawn now, given the notes the depositor
+    /// still holds and the notes the reserve's liquidity is worth
+    pub fn fillable(&self, note_balance: u64, available_notes: u64) -> u64 {
+        self.notes_remaining.min(note_balance).min(available_notes)
+    }
+
+    /// Fill as much of the request as the reserve's `liquidity` and the
+    /// depositor's `note_balance` allow, returning the notes to burn and the
+    /// tokens to pay out
+    pub fn fill(
+        &mut self,
+        reserve_info: &CachedReserveInfo,
+        liquidity: u64,
+        note_balance: u64,
+    ) -> Result<(u64, u64)> {
+        if self.is_filled() {
+            return err!(ErrorCode::WithdrawalRequestFilled);
+        }
+
+        let available_notes = reserve_info.deposit_notes_from_tokens(liquidity, Rounding::Down);
+        let notes = self.fillable(note_balance, available_notes);
+        let tokens = reserve_info.deposit_notes_to_tokens(notes, Rounding::Down);
+
+        if notes == 0 {
+            return err!(ErrorCode::InsufficientLiquidity);
+        }
+
+        self.record_fill(notes, tokens)?;
+        Ok((notes, tokens))
+    }
+
+    /// Record notes withdrawn for the request
+    pub fn record_fill(&mut self, notes: u64, tokens: u64) -> Result<()> {
+        self.notes_remaining = self
+            .notes_remaining
+            .checked_sub(notes)
+            .ok_or(ErrorCode::WithdrawalRequestFilled)?;
+        self.tokens_filled = self
+            .tokens_filled
+            .checked_add(tokens)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.notes_remaining == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::error_code;
+    use bytemuck::Zeroable;
+    use jet_math::Number;
+
+    fn reserve_info(deposit_note_exchange_rate: Number) -> CachedReserveInfo {
+        let mut info = CachedReserveInfo::zeroed();
+        info.deposit_note_exchange_rate = deposit_note_exchange_rate;
+        info
+    }
+
+    #[test]
+    fn requests_fill_as_liquidity_arrives() {
+        let mut request = WithdrawalRequest {
+            notes_remaining: 1_000,
+            ..Default::default()
+        };
+
+        assert_eq!(request.fillable(1_000, 0), 0);
+        assert_eq!(request.fillable(1_000, 400), 400);
+        request.record_fill(400, 420).unwrap();
+
+        // notes withdrawn directly by the depositor can't be filled again
+        assert_eq!(request.fillable(500, 5_000), 500);
+        assert_eq!(request.fillable(1_000, 5_000), 600);
+        request.record_fill(600, 630).unwrap();
+
+        assert!(request.is_filled());
+        assert_eq!(request.tokens_filled, 1_050);
+        assert!(request.record_fill(1, 1).is_err());
+    }
+
+    #[test]
+    fn fills_are_clamped_to_the_reserve_liquidity() {
+        // each note is worth 1.5 tokens
+        let info = reserve_info(Number::from_bps(15_000));
+        let mut request = WithdrawalRequest {
+            notes_remaining: 1_000,
+            ..Default::default()
+        };
+
+        // no liquidity, or too little for a single note, fills nothing
+        for liquidity in [0, 1] {
+            assert_eq!(
+                error_code(request.fill(&info, liquidity, 1_000).unwrap_err()),
+                ErrorCode::InsufficientLiquidity.into()
+            );
+        }
+        assert_eq!(request.notes_remaining, 1_000);
+
+        // a partial fill takes only the notes the liquidity covers
+        assert_eq!(request.fill(&info, 601, 1_000).unwrap(), (400, 600));
+        assert_eq!(request.notes_remaining, 600);
+        assert!(!request.is_filled());
+
+        // the rest fills once liquidity returns, never past the request
+        assert_eq!(request.fill(&info, 1_000_000, 1_000).unwrap(), (600, 900));
+        assert!(request.is_filled());
+        assert_eq!(request.tokens_filled, 1_500);
+
+        assert_eq!(
+            error_code(request.fill(&info, 1_000_000, 1_000).unwrap_err()),
+            ErrorCode::WithdrawalRequestFilled.into()
+        );
+    }
+
+    #[test]
+    fn fills_are_clamped_to_the_depositor_balance() {
+        let info = reserve_info(Number::ONE);
+        let mut request = WithdrawalRequest {
+            notes_remaining: 1_000,
+            ..Default::default()
+        };
+
+        assert_eq!(request.fill(&info, 5_000, 250).unwrap(), (250, 250));
+        assert_eq!(
+            error_code(request.fill(&info, 5_000, 0).unwrap_err()),
+            ErrorCode::InsufficientLiquidity.into()
+        );
+        assert_eq!(request.notes_remaining, 750);
+    }
+}
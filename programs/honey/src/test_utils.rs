@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+// Copyright (C) 2021 JET PROTOCOL HOLDINGS, LLC.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Helpers shared by the unit tests of the program's modules
+
+use anchor_lang::prelude::*;
+
+/// The error code number of an anchor error, for comparing against an `ErrorCode`
+pub fn error_code(error: Error) -> u32 {
+    match error {
+        Error::AnchorError(e) => e.error_code_number,
+        Error::ProgramError(e) => panic!("unexpected program error {}", e),
+    }
+}
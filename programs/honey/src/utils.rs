@@ -21,6 +21,8 @@ use std::{
 };
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke_signed;
 use mpl_token_metadata::state::Metadata;
 use mpl_token_metadata::state::TokenMetadataAccount;
 use mpl_token_metadata::ID as metadata_program_id;
@@ -286,6 +288,56 @@ pub fn verify_valid_metadata(metadata: &AccountInfo, nft_collection_creator: &Ac
     Ok(())
 }
 
+/// An nft left in its owner's wallet, which the market authority holds
+/// as the token account's delegate and freezes through Token Metadata
+pub struct DelegatedNft<'a, 'info> {
+    pub delegate: &'a AccountInfo<'info>,
+    pub token_account: &'a AccountInfo<'info>,
+    pub edition: &'a AccountInfo<'info>,
+    pub mint: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub token_metadata_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> DelegatedNft<'a, 'info> {
+    pub fn freeze(&self, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        self.invoke(mpl_token_metadata::instruction::freeze_delegated_account, signer_seeds)
+    }
+
+    pub fn thaw(&self, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        self.invoke(mpl_token_metadata::instruction::thaw_delegated_account, signer_seeds)
+    }
+
+    fn invoke(
+        &self,
+        instruction: fn(Pubkey, Pubkey, Pubkey, Pubkey, Pubkey) -> Instruction,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        assert_keys_eq!(self.token_metadata_program.key(), metadata_program_id, "token metadata program");
+
+        invoke_signed(
+            &instruction(
+                metadata_program_id,
+                self.delegate.key(),
+                self.token_account.key(),
+                self.edition.key(),
+                self.mint.key(),
+            ),
+            &[
+                self.delegate.clone(),
+                self.token_account.clone(),
+                self.edition.clone(),
+                self.mint.clone(),
+                self.token_program.clone(),
+                self.token_metadata_program.clone(),
+            ],
+            signer_seeds,
+        )?;
+
+        Ok(())
+    }
+}
+
 pub fn validate(metadata_bump: u8, metadata: &AccountInfo, deposit_nft_mint: &Account<Mint>) -> Result<()> {
     msg!(
         "Received Metadata Pubkey {}",
@@ -0,0 +1,51 @@
+#!/bin/bash
+# Dump the deployed builds of the programs the tests load at genesis into deps/.
+# The dumps aren't committed, so this runs before the validator starts.
+# --force dumps them again, and --check only fails if they're out of date.
+set -e
+cd "$(dirname "$0")/.."
+
+# bump when the programs below change, so stale dumps are replaced
+DEPS_VERSION=3
+
+# the validator won't start unless every program Anchor.toml loads at genesis is present
+missing_deps() {
+  grep -oP '^program = "\K[^"]+' Anchor.toml | while read -r program; do
+    [ -f "$program" ] || echo "$program"
+  done
+}
+
+if [ "$1" != "--force" ] && [ "$(cat deps/.version 2>/dev/null)" == "$DEPS_VERSION" ] && [ -z "$(missing_deps)" ]; then
+  echo "deps/ is up to date"
+  exit 0
+fi
+if [ "$1" == "--check" ]; then
+  echo "deps/ is out of date; run scripts/fetch-deps.sh before starting the validator"
+  exit 1
+fi
+
+mkdir -p deps
+dump() {
+  echo "Dumping $1 to deps/$2.so..."
+  solana program dump -u "${3:-m}" "$1" "deps/$2.so"
+}
+
+# the tests trade against Serum's devnet deployment
+dump DESVgJVGajEgKGXhb6XmqDHGz3VjdgP7rEVESBgxmroY serum_dex d
+
+# pNFT support needs Token Metadata 1.9 or later, and Token Auth Rules for rule sets
+dump metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s mpl_token_metadata
+dump auth9SigNpDKz4sJJ1DfCTuZrZNSAgh9sFD3rboVmgg mpl_token_auth_rules
+
+# compressed nfts are Bubblegum leaves in an spl account compression tree
+dump BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY mpl_bubblegum
+dump cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK spl_account_compression
+dump noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV spl_noop
+
+missing=$(missing_deps)
+if [ -n "$missing" ]; then
+  echo "deps/ is still missing" $missing
+  exit 1
+fi
+
+echo "$DEPS_VERSION" > deps/.version
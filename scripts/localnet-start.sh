@@ -1,3 +1,5 @@
 #!/bin/bash
-echo "Starting test validator with Serum dex & mpl_token_metadata..."
-solana-test-validator -r --bpf-program metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s ./deps/mpl_token_metadata.so --bpf-program DESVgJVGajEgKGXhb6XmqDHGz3VjdgP7rEVESBgxmroY ./deps/serum_dex.so
+set -e
+"$(dirname "$0")/fetch-deps.sh"
+echo "Starting test validator with Serum dex, mpl_token_metadata, mpl_token_auth_rules & Bubblegum..."
+solana-test-validator -r --bpf-program metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s ./deps/mpl_token_metadata.so --bpf-program auth9SigNpDKz4sJJ1DfCTuZrZNSAgh9sFD3rboVmgg ./deps/mpl_token_auth_rules.so --bpf-program BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY ./deps/mpl_bubblegum.so --bpf-program cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK ./deps/spl_account_compression.so --bpf-program noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV ./deps/spl_noop.so --bpf-program DESVgJVGajEgKGXhb6XmqDHGz3VjdgP7rEVESBgxmroY ./deps/serum_dex.so
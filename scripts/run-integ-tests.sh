@@ -1,2 +1,6 @@
 #!/bin/bash
-npx ts-mocha -p ./tsconfig.json -t 1000000 --paths tests/honey.spec.ts
\ No newline at end of file
+# anchor test has already loaded deps/ into the validator by now, so this can
+# only stop the run when they're stale
+set -e
+"$(dirname "$0")/fetch-deps.sh" --check
+npx ts-mocha -p ./tsconfig.json -t 1000000 --paths tests/*.spec.ts
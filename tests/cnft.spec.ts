@@ -0,0 +1,293 @@
+import * as anchor from "@project-serum/anchor";
+import { BN } from "@project-serum/anchor";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, NATIVE_MINT, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { ConcurrentMerkleTreeAccount, MerkleTree } from "@solana/spl-account-compression";
+import { Creator } from "@metaplex-foundation/mpl-bubblegum";
+import { assert } from "chai";
+import { Honey } from "target/types/honey";
+import { createObligation, findLoanAccountAddress } from "honey-cli/src/helpers/obligation";
+import { createKeypair } from "./utils";
+import {
+  BUBBLEGUM_PROGRAM_ID,
+  Cnft,
+  compressedNftArgs,
+  createTree,
+  mintCnft,
+  SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+  SPL_NOOP_PROGRAM_ID,
+  TestTree,
+  transferCnft,
+} from "./utils/cnft";
+import {
+  associatedAddress,
+  borrow,
+  expectProgramError,
+  findBidAddresses,
+  placeBid,
+  refreshReserveInstruction,
+  setMinCollateralRatio,
+  setupTestMarket,
+  TestMarket,
+} from "./utils/market";
+
+// preflight is left on, so failed transactions come back with their program error
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+const program: anchor.Program = anchor.workspace.Honey as anchor.Program<Honey>;
+
+describe("compressed nft collateral", () => {
+  let testMarket: TestMarket;
+  let tree: TestTree;
+
+  const bubblegumAccounts = () => ({
+    merkleTree: tree.merkleTree,
+    treeAuthority: tree.treeAuthority,
+    logWrapper: SPL_NOOP_PROGRAM_ID,
+    compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+    bubblegumProgram: BUBBLEGUM_PROGRAM_ID,
+    systemProgram: SystemProgram.programId,
+  });
+
+  const verifiedBy = (creator: PublicKey, verified = true): Creator[] => [
+    { address: creator, verified, share: 100 },
+  ];
+
+  async function depositCnft(
+    owner: Keypair,
+    obligation: PublicKey,
+    cnft: Cnft,
+    creators?: Creator[]
+  ) {
+    const { market, marketAuthority } = testMarket;
+    const { nft, proof } = await compressedNftArgs(provider, tree, cnft, creators);
+
+    await program.methods
+      .depositCnft(nft)
+      .accounts({
+        market,
+        marketAuthority,
+        obligation,
+        owner: owner.publicKey,
+        leafDelegate: cnft.delegate,
+        ...bubblegumAccounts(),
+      })
+      .remainingAccounts(proof)
+      .signers([owner])
+      .rpc();
+    transferCnft(tree, cnft, marketAuthority);
+  }
+
+  async function collateralAssets(obligation: PublicKey): Promise<PublicKey[]> {
+    const data = await program.account.obligation.fetch(obligation);
+    return data.collateralNftMint.filter((mint: PublicKey) => !mint.equals(PublicKey.default));
+  }
+
+  // the tree on chain matches the copy the proofs are built from, so each
+  // leaf is held by whoever the test last moved it to
+  async function assertLeavesMatch() {
+    const account = await ConcurrentMerkleTreeAccount.fromAccountAddress(
+      provider.connection,
+      tree.merkleTree
+    );
+    assert(
+      account.getCurrentRoot().equals(new MerkleTree(tree.leaves).root),
+      "The tree's leaves aren't the ones expected!"
+    );
+  }
+
+  before(async () => {
+    testMarket = await setupTestMarket(provider, program, 1.5 * LAMPORTS_PER_SOL);
+    tree = await createTree(provider, testMarket.collectionCreator);
+  });
+
+  describe("deposit and withdraw", () => {
+    let owner: Keypair;
+    let obligation: PublicKey;
+    let cnft: Cnft;
+
+    before(async () => {
+      const { collectionCreator, market } = testMarket;
+      owner = await createKeypair(provider);
+      obligation = await createObligation(program, owner, market);
+      cnft = await mintCnft(
+        provider,
+        tree,
+        collectionCreator,
+        owner.publicKey,
+        verifiedBy(collectionCreator.publicKey)
+      );
+    });
+
+    it("moves a deposited cNFT to the market", async () => {
+      await depositCnft(owner, obligation, cnft);
+
+      await assertLeavesMatch();
+      assert((await collateralAssets(obligation)).some((asset) => asset.equals(cnft.assetId)));
+    });
+
+    it("moves the cNFT back when it's withdrawn", async () => {
+      const { market, marketAuthority } = testMarket;
+      const { nft, proof } = await compressedNftArgs(provider, tree, cnft);
+
+      await program.methods
+        .withdrawCnft(nft)
+        .accounts({
+          market,
+          marketAuthority,
+          obligation,
+          owner: owner.publicKey,
+          ...bubblegumAccounts(),
+        })
+        .remainingAccounts(proof)
+        .signers([owner])
+        .rpc();
+      transferCnft(tree, cnft, owner.publicKey);
+
+      await assertLeavesMatch();
+      assert.isEmpty(await collateralAssets(obligation));
+    });
+  });
+
+  describe("creators", () => {
+    let owner: Keypair;
+    let obligation: PublicKey;
+
+    before(async () => {
+      owner = await createKeypair(provider);
+      obligation = await createObligation(program, owner, testMarket.market);
+    });
+
+    it("rejects a cNFT verified by a foreign creator", async () => {
+      const foreignCreator = await createKeypair(provider);
+      const cnft = await mintCnft(
+        provider,
+        tree,
+        testMarket.collectionCreator,
+        owner.publicKey,
+        verifiedBy(foreignCreator.publicKey),
+        [foreignCreator]
+      );
+
+      await expectProgramError(
+        program,
+        depositCnft(owner, obligation, cnft),
+        "VerifiedCreatorMismatch"
+      );
+      assert.isEmpty(await collateralAssets(obligation));
+    });
+
+    it("rejects a cNFT whose creator never verified it", async () => {
+      const { collectionCreator } = testMarket;
+      const cnft = await mintCnft(
+        provider,
+        tree,
+        collectionCreator,
+        owner.publicKey,
+        verifiedBy(collectionCreator.publicKey, false)
+      );
+
+      await expectProgramError(
+        program,
+        depositCnft(owner, obligation, cnft),
+        "VerifiedCreatorMismatch"
+      );
+
+      // claiming the creator verified it changes the leaf's creator hash,
+      // so Bubblegum can't prove the leaf
+      let failed = false;
+      try {
+        await depositCnft(owner, obligation, cnft, verifiedBy(collectionCreator.publicKey));
+      } catch (err) {
+        failed = true;
+      }
+      assert(failed, "A cNFT with a spoofed creator was deposited!");
+      assert.isEmpty(await collateralAssets(obligation));
+      await assertLeavesMatch();
+    });
+  });
+
+  describe("liquidation", () => {
+    let borrower: Keypair;
+    let obligation: PublicKey;
+    let cnft: Cnft;
+    let bidder: Keypair;
+
+    before(async () => {
+      const { collectionCreator, market } = testMarket;
+      borrower = await createKeypair(provider);
+      obligation = await createObligation(program, borrower, market);
+      cnft = await mintCnft(
+        provider,
+        tree,
+        collectionCreator,
+        borrower.publicKey,
+        verifiedBy(collectionCreator.publicKey)
+      );
+      await depositCnft(borrower, obligation, cnft);
+
+      // half the borrowing capacity, which a 300% minimum ratio makes unhealthy
+      await borrow(testMarket, borrower, obligation, { units: { bps: {} }, value: new BN(5000) });
+      await setMinCollateralRatio(testMarket, 30000);
+
+      bidder = await createKeypair(provider);
+      await placeBid(testMarket, bidder, 1.5 * LAMPORTS_PER_SOL);
+    });
+
+    it("moves the cNFT to the bidder", async () => {
+      const { market, marketAuthority, reserve, roles, owner } = testMarket;
+      const data = await program.account.reserve.fetch(reserve);
+      const { bid, bidEscrow, bidEscrowAuthority } = await findBidAddresses(
+        testMarket,
+        bidder.publicKey
+      );
+      const [loanAccount] = await findLoanAccountAddress(
+        program,
+        reserve,
+        obligation,
+        borrower.publicKey
+      );
+      const keeper = await createKeypair(provider);
+      const { nft, proof } = await compressedNftArgs(provider, tree, cnft);
+
+      await program.methods
+        .executeLiquidateBidCnft(nft)
+        .accounts({
+          market,
+          marketAuthority,
+          obligation,
+          reserve,
+          vault: data.vault,
+          // reserves without an insurance fund take any writable account
+          insuranceFund: data.insuranceFund.equals(PublicKey.default)
+            ? data.vault
+            : data.insuranceFund,
+          loanNoteMint: data.loanNoteMint,
+          loanAccount,
+          bid,
+          bidder: bidder.publicKey,
+          roles,
+          feeCollector: owner.publicKey,
+          bidMint: NATIVE_MINT,
+          bidEscrow,
+          bidEscrowAuthority,
+          keeperRewardReceiver: await associatedAddress(NATIVE_MINT, keeper.publicKey),
+          protocolFeeReceiver: await associatedAddress(NATIVE_MINT, owner.publicKey),
+          keeper: keeper.publicKey,
+          payer: keeper.publicKey,
+          ...bubblegumAccounts(),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(proof)
+        .preInstructions([await refreshReserveInstruction(testMarket)])
+        .signers([keeper])
+        .rpc();
+      transferCnft(tree, cnft, bidder.publicKey);
+
+      await assertLeavesMatch();
+      assert.isEmpty(await collateralAssets(obligation));
+    });
+  });
+});
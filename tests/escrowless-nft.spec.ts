@@ -0,0 +1,186 @@
+import * as anchor from "@project-serum/anchor";
+import { BN } from "@project-serum/anchor";
+import { AccountMeta, Keypair, LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { createAssociatedTokenAccount, getAccount, transfer } from "@solana/spl-token-latest";
+import { PROGRAM_ID as METADATA_PROGRAM_ID } from "@metaplex-foundation/mpl-token-metadata";
+import { assert } from "chai";
+import { Honey } from "target/types/honey";
+import { createObligation, findMetadataAddress } from "honey-cli/src/helpers/obligation";
+import { createKeypair } from "./utils";
+import {
+  associatedAddress,
+  borrow,
+  executeLiquidateBid,
+  mintCollectionNft,
+  placeBid,
+  setMinCollateralRatio,
+  setupTestMarket,
+  TestMarket,
+} from "./utils/market";
+
+// preflight is left on, so failed transactions come back with their program error
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+const program: anchor.Program = anchor.workspace.Honey as anchor.Program<Honey>;
+
+interface Nft {
+  mint: PublicKey;
+  tokenAccount: PublicKey;
+  metadata: PublicKey;
+  edition: PublicKey;
+}
+
+// the accounts Token Metadata needs to freeze or thaw an nft in place
+const frozenNftAccounts = (nft: Nft): AccountMeta[] => [
+  { pubkey: nft.edition, isSigner: false, isWritable: false },
+  { pubkey: METADATA_PROGRAM_ID, isSigner: false, isWritable: false },
+];
+
+describe("escrowless nft collateral", () => {
+  let testMarket: TestMarket;
+
+  async function depositNftEscrowless(owner: Keypair, obligation: PublicKey, nft: Nft) {
+    const { market, marketAuthority, collectionCreator } = testMarket;
+    const [, metadataBump] = await findMetadataAddress(nft.mint);
+
+    return program.methods
+      .depositNftEscrowless(metadataBump)
+      .accounts({
+        market,
+        marketAuthority,
+        obligation,
+        owner: owner.publicKey,
+        depositSource: nft.tokenAccount,
+        depositNftMint: nft.mint,
+        nftCollectionCreator: collectionCreator.publicKey,
+        metadata: nft.metadata,
+        edition: nft.edition,
+        tokenMetadataProgram: METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([owner])
+      .rpc();
+  }
+
+  async function collateralMints(obligation: PublicKey): Promise<PublicKey[]> {
+    const data = await program.account.obligation.fetch(obligation);
+    return data.collateralNftMint.filter((mint: PublicKey) => !mint.equals(PublicKey.default));
+  }
+
+  before(async () => {
+    testMarket = await setupTestMarket(provider, program, 1.5 * LAMPORTS_PER_SOL);
+  });
+
+  describe("deposit and withdraw", () => {
+    let owner: Keypair;
+    let obligation: PublicKey;
+    let nft: Nft;
+
+    before(async () => {
+      owner = await createKeypair(provider);
+      obligation = await createObligation(program, owner, testMarket.market);
+      nft = await mintCollectionNft(testMarket, owner.publicKey);
+    });
+
+    it("freezes a deposited nft in the owner's wallet", async () => {
+      await depositNftEscrowless(owner, obligation, nft);
+
+      const account = await getAccount(provider.connection, nft.tokenAccount);
+      assert.equal(account.amount.toString(), "1", "The nft left the owner's wallet!");
+      assert(account.isFrozen, "The nft wasn't frozen!");
+      assert(account.delegate?.equals(testMarket.marketAuthority), "The market isn't the delegate!");
+      assert((await collateralMints(obligation)).some((mint) => mint.equals(nft.mint)));
+    });
+
+    it("keeps the owner from moving the frozen nft", async () => {
+      const other = await createAssociatedTokenAccount(
+        provider.connection,
+        owner,
+        nft.mint,
+        Keypair.generate().publicKey
+      );
+
+      let failed = false;
+      try {
+        await transfer(provider.connection, owner, nft.tokenAccount, other, owner, 1);
+      } catch (err) {
+        failed = true;
+      }
+      assert(failed, "The frozen nft was transferred!");
+    });
+
+    it("thaws the nft when it's withdrawn", async () => {
+      const { market, marketAuthority, collectionCreator } = testMarket;
+      const [, metadataBump] = await findMetadataAddress(nft.mint);
+
+      await program.methods
+        .withdrawNft(metadataBump)
+        .accounts({
+          market,
+          marketAuthority,
+          obligation,
+          owner: owner.publicKey,
+          // unused for an nft that never left the owner's wallet
+          depositTo: nft.tokenAccount,
+          nftCollectionCreator: collectionCreator.publicKey,
+          metadata: nft.metadata,
+          depositNftMint: nft.mint,
+          collateralAccount: nft.tokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(frozenNftAccounts(nft))
+        .signers([owner])
+        .rpc();
+
+      const account = await getAccount(provider.connection, nft.tokenAccount);
+      assert.equal(account.amount.toString(), "1");
+      assert(!account.isFrozen, "The nft is still frozen!");
+      assert.isNull(account.delegate, "The market is still the delegate!");
+      assert.isEmpty(await collateralMints(obligation));
+    });
+  });
+
+  describe("liquidation", () => {
+    let borrower: Keypair;
+    let obligation: PublicKey;
+    let nft: Nft;
+    let bidder: Keypair;
+
+    before(async () => {
+      borrower = await createKeypair(provider);
+      obligation = await createObligation(program, borrower, testMarket.market);
+      nft = await mintCollectionNft(testMarket, borrower.publicKey);
+      await depositNftEscrowless(borrower, obligation, nft);
+
+      // half the borrowing capacity, which a 300% minimum ratio makes unhealthy
+      await borrow(testMarket, borrower, obligation, { units: { bps: {} }, value: new BN(5000) });
+      await setMinCollateralRatio(testMarket, 30000);
+
+      bidder = await createKeypair(provider);
+      await placeBid(testMarket, bidder, 1.5 * LAMPORTS_PER_SOL);
+    });
+
+    it("thaws the frozen nft and transfers it to the bidder", async () => {
+      await executeLiquidateBid(testMarket, {
+        obligation,
+        borrower: borrower.publicKey,
+        nftMint: nft.mint,
+        collateralAccount: nft.tokenAccount,
+        bidder: bidder.publicKey,
+        keeper: await createKeypair(provider),
+        nftAccounts: frozenNftAccounts(nft),
+      });
+
+      const received = await getAccount(
+        provider.connection,
+        await associatedAddress(nft.mint, bidder.publicKey)
+      );
+      const left = await getAccount(provider.connection, nft.tokenAccount);
+      assert.equal(received.amount.toString(), "1", "The bidder didn't get the nft!");
+      assert(!received.isFrozen, "The bidder's nft is frozen!");
+      assert.equal(left.amount.toString(), "0");
+      assert.isEmpty(await collateralMints(obligation));
+    });
+  });
+});
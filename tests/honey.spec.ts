@@ -64,7 +64,8 @@ import { increaseBid } from "honey-cli/src/actions/liquidations/increaseBid";
 import { revokeBid } from "honey-cli/src/actions/liquidations/revokeBid";
 import { executeBid } from "honey-cli/src/actions/liquidations/executeBid";
 import { getObligationState } from "honey-cli/src/actions/getObligationState";
-import { createAccount, NATIVE_MINT } from "@solana/spl-token-latest";
+import { createAccount, getAccount, NATIVE_MINT } from "@solana/spl-token-latest";
+import { findLoanAccountAddress, findObligations } from "honey-cli/src/helpers/obligation";
 
 chaiUse(chaiAsPromised.default);
 // SET GLOBAL VARIABLES
@@ -92,6 +93,21 @@ let userKeypair: anchor.web3.Keypair,
 let collectionCreator: PublicKey, dropletMint: PublicKey, userPk: PublicKey;
 const nftInfos: NftInfo[] = [];
 
+// The tokens an owner's obligation owes the reserve, from its loan notes
+async function loanTokens(owner: PublicKey): Promise<number> {
+  const [obligation] = await findObligations(honeyProgram, honeyMarketPk, owner);
+  if (!obligation) return 0;
+
+  const [loanAccount] = await findLoanAccountAddress(honeyProgram, reservePk, obligation, owner);
+  if (!(await provider.connection.getAccountInfo(loanAccount))) return 0;
+
+  const { amount } = await getAccount(provider.connection, loanAccount);
+  await honeyMarket.refresh();
+  return new BN(amount.toString())
+    .mul(honeyMarket.reserves[0].loanNoteExchangeRate)
+    .div(new BN(Math.pow(10, 15))).toNumber();
+}
+
 let nftAggregatorPk: PublicKey = new PublicKey("4FcQKqmQKXuiyJatHkMy7pXwUSMPuXh5sXLacAnyxh7v");
 let tokenAggregatorPk: PublicKey = new PublicKey("DfZxR1TKfDMvjCLM1Si3BDDSS283jba8HTd1cewhNAnN");
 let nftAggregator: AggregatorAccount;
@@ -569,9 +585,7 @@ describe("honey", async () => {
     // are we actually using deposits()?
     // const tokenCollateral = honeyUser.collateral().length > 0? honeyUser.collateral()[0].amount.toNumber(): 0;
     const nftCollateral = (nftPriceUsd.toNumber() / tokenPriceUsd.toNumber()) * LAMPORTS_PER_SOL;//price in quote token
-    loanAmount = honeyUser.loans().length > 0? honeyUser.loans()[0].amount
-      .mul(honeyUser.market.reserves[0].loanNoteExchangeRate)
-      .div(new BN(Math.pow(10, 15))).toNumber():0;
+    loanAmount = await loanTokens(nftInfos[0].holderKeypair.publicKey);
     const minColRatio = new BN(honeyReserve.data.config.minCollateralRatio).toNumber() / 10000;
 
     console.log('nftCollateral', nftCollateral);
@@ -597,11 +611,8 @@ describe("honey", async () => {
     await honeyUser.refresh();
     await honeyUser.market.refresh();
 
-    console.log('honeyUser.loans()[0].amount', honeyUser.loans()[0].amount.toString());
-    // await honeyMarket.refresh();
-    loanAmount = honeyUser.loans().length > 0? (honeyUser.loans()[0].amount
-      .mul(honeyUser.market.reserves[0].loanNoteExchangeRate)
-      .div(new BN(Math.pow(10, 15))).toNumber()):0;
+    loanAmount = await loanTokens(nftInfos[0].holderKeypair.publicKey);
+    assert(loanAmount > 0, "Borrow didn't record a loan!");
 
     // considering reduction due to precision in number calc
     loanAmount *= 1.02;
@@ -642,9 +653,10 @@ describe("honey", async () => {
 
     // liquidator is trying to execute nftInfos[0]'s obligation
     const obligation:ObligationAccount = await getObligationState(nftInfos[0].holderKeypair, honeyMarketPk);
-    const [obligationAddress, obligationBump] = await PublicKey.findProgramAddress(
-      [Buffer.from('obligation'), honeyMarketPk.toBuffer(), nftInfos[0].holderKeypair.publicKey.toBuffer()],
-      honeyProgram.programId,
+    const [obligationAddress] = await findObligations(
+      honeyProgram,
+      honeyMarketPk,
+      nftInfos[0].holderKeypair.publicKey
     );
     const bidAccount = await liquidatorClient.findBidAccount(honeyMarketPk, liquidatorKeypair.publicKey);
 
@@ -702,9 +714,7 @@ describe("honey", async () => {
 
     console.log('honeyUser.market.reserves[0].loanNoteExchangeRate after repay',
       honeyUser.market.reserves[0].loanNoteExchangeRate.toString());
-    loanAmount = honeyUser.loans().length > 0? (honeyUser.loans()[0].amount
-      .mul(honeyUser.market.reserves[0].loanNoteExchangeRate)
-      .div(new BN(Math.pow(10, 15))).toNumber()):0;
+    loanAmount = await loanTokens(nftInfos[0].holderKeypair.publicKey);
     console.log('loanAmount after repay', loanAmount);
 
     assert(await withdrawNFT(nftInfos[0].holderKeypair,
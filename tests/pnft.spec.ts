@@ -0,0 +1,216 @@
+import * as anchor from "@project-serum/anchor";
+import { BN } from "@project-serum/anchor";
+import {
+  AccountMeta,
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} from "@solana/web3.js";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { getAccount } from "@solana/spl-token-latest";
+import { PROGRAM_ID as METADATA_PROGRAM_ID } from "@metaplex-foundation/mpl-token-metadata";
+import { assert } from "chai";
+import { Honey } from "target/types/honey";
+import {
+  createObligation,
+  findCollateralAddress,
+  findMetadataAddress,
+} from "honey-cli/src/helpers/obligation";
+import { createKeypair } from "./utils";
+import {
+  associatedAddress,
+  borrow,
+  executeLiquidateBid,
+  expectProgramError,
+  placeBid,
+  setMinCollateralRatio,
+  setupTestMarket,
+  TestMarket,
+} from "./utils/market";
+import { AUTH_RULES_PROGRAM_ID, createRuleSet, findTokenRecordAddress, mintPnft } from "./utils/pnft";
+
+// preflight is left on, so failed transactions come back with their program error
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+const program: anchor.Program = anchor.workspace.Honey as anchor.Program<Honey>;
+
+interface Pnft {
+  mint: PublicKey;
+  tokenAccount: PublicKey;
+  metadata: PublicKey;
+  edition: PublicKey;
+  tokenRecord: PublicKey;
+}
+
+describe("programmable nft collateral", () => {
+  let testMarket: TestMarket;
+  let ruleSet: PublicKey;
+  let otherRuleSet: PublicKey;
+
+  async function depositPnft(owner: Keypair, obligation: PublicKey, pnft: Pnft, rules = ruleSet) {
+    const { market, marketAuthority, collectionCreator } = testMarket;
+    const [, metadataBump] = await findMetadataAddress(pnft.mint);
+    const collateralAccount = await findCollateralAddress(marketAuthority, pnft.mint);
+
+    return program.methods
+      .depositPnft(metadataBump)
+      .accounts({
+        market,
+        marketAuthority,
+        obligation,
+        owner: owner.publicKey,
+        depositSource: pnft.tokenAccount,
+        depositNftMint: pnft.mint,
+        nftCollectionCreator: collectionCreator.publicKey,
+        metadata: pnft.metadata,
+        edition: pnft.edition,
+        ownerTokenRecord: pnft.tokenRecord,
+        collateralTokenRecord: await findTokenRecordAddress(pnft.mint, collateralAccount),
+        collateralAccount,
+        tokenMetadataProgram: METADATA_PROGRAM_ID,
+        sysvarInstructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        authorizationRulesProgram: AUTH_RULES_PROGRAM_ID,
+        authorizationRules: rules,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .signers([owner])
+      .rpc();
+  }
+
+  async function collateralMints(obligation: PublicKey): Promise<PublicKey[]> {
+    const data = await program.account.obligation.fetch(obligation);
+    return data.collateralNftMint.filter((mint: PublicKey) => !mint.equals(PublicKey.default));
+  }
+
+  before(async () => {
+    testMarket = await setupTestMarket(provider, program, 1.5 * LAMPORTS_PER_SOL);
+    ruleSet = await createRuleSet(provider, testMarket.collectionCreator, "honey-pnft");
+    otherRuleSet = await createRuleSet(provider, testMarket.collectionCreator, "honey-pnft-other");
+  });
+
+  describe("deposit and withdraw", () => {
+    let owner: Keypair;
+    let obligation: PublicKey;
+    let pnft: Pnft;
+
+    before(async () => {
+      owner = await createKeypair(provider);
+      obligation = await createObligation(program, owner, testMarket.market);
+      pnft = await mintPnft(provider, testMarket.collectionCreator, owner.publicKey, ruleSet);
+    });
+
+    it("rejects a rule set other than the pNFT's", async () => {
+      await expectProgramError(
+        program,
+        depositPnft(owner, obligation, pnft, otherRuleSet),
+        "RuleSetMismatch"
+      );
+    });
+
+    it("escrows a deposited pNFT with the market", async () => {
+      await depositPnft(owner, obligation, pnft);
+
+      const collateralAccount = await findCollateralAddress(testMarket.marketAuthority, pnft.mint);
+      const escrow = await getAccount(provider.connection, collateralAccount);
+      const source = await getAccount(provider.connection, pnft.tokenAccount);
+      assert.equal(escrow.amount.toString(), "1", "The pNFT wasn't escrowed!");
+      assert(escrow.isFrozen, "Token Metadata didn't lock the escrowed pNFT!");
+      assert.equal(source.amount.toString(), "0");
+      assert((await collateralMints(obligation)).some((mint) => mint.equals(pnft.mint)));
+    });
+
+    it("returns the pNFT when it's withdrawn", async () => {
+      const { market, marketAuthority, collectionCreator } = testMarket;
+      const [, metadataBump] = await findMetadataAddress(pnft.mint);
+      const collateralAccount = await findCollateralAddress(marketAuthority, pnft.mint);
+
+      await program.methods
+        .withdrawPnft(metadataBump)
+        .accounts({
+          market,
+          marketAuthority,
+          obligation,
+          owner: owner.publicKey,
+          depositTo: pnft.tokenAccount,
+          nftCollectionCreator: collectionCreator.publicKey,
+          metadata: pnft.metadata,
+          edition: pnft.edition,
+          collateralTokenRecord: await findTokenRecordAddress(pnft.mint, collateralAccount),
+          ownerTokenRecord: pnft.tokenRecord,
+          depositNftMint: pnft.mint,
+          collateralAccount,
+          tokenMetadataProgram: METADATA_PROGRAM_ID,
+          sysvarInstructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          authorizationRulesProgram: AUTH_RULES_PROGRAM_ID,
+          authorizationRules: ruleSet,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .signers([owner])
+        .rpc();
+
+      const returned = await getAccount(provider.connection, pnft.tokenAccount);
+      assert.equal(returned.amount.toString(), "1", "The pNFT wasn't returned!");
+      assert.isEmpty(await collateralMints(obligation));
+    });
+  });
+
+  describe("liquidation", () => {
+    let borrower: Keypair;
+    let obligation: PublicKey;
+    let pnft: Pnft;
+    let bidder: Keypair;
+
+    before(async () => {
+      borrower = await createKeypair(provider);
+      obligation = await createObligation(program, borrower, testMarket.market);
+      pnft = await mintPnft(provider, testMarket.collectionCreator, borrower.publicKey, ruleSet);
+      await depositPnft(borrower, obligation, pnft);
+
+      // half the borrowing capacity, which a 300% minimum ratio makes unhealthy
+      await borrow(testMarket, borrower, obligation, { units: { bps: {} }, value: new BN(5000) });
+      await setMinCollateralRatio(testMarket, 30000);
+
+      bidder = await createKeypair(provider);
+      await placeBid(testMarket, bidder, 1.5 * LAMPORTS_PER_SOL);
+    });
+
+    it("transfers the pNFT to the bidder under its rule set", async () => {
+      const collateralAccount = await findCollateralAddress(testMarket.marketAuthority, pnft.mint);
+      const receiverAccount = await associatedAddress(pnft.mint, bidder.publicKey);
+      const account = (pubkey: PublicKey, isWritable = false): AccountMeta => ({
+        pubkey,
+        isSigner: false,
+        isWritable,
+      });
+
+      await executeLiquidateBid(testMarket, {
+        obligation,
+        borrower: borrower.publicKey,
+        nftMint: pnft.mint,
+        collateralAccount,
+        bidder: bidder.publicKey,
+        keeper: await createKeypair(provider),
+        nftAccounts: [
+          account(pnft.metadata, true),
+          account(pnft.edition),
+          account(await findTokenRecordAddress(pnft.mint, collateralAccount), true),
+          account(await findTokenRecordAddress(pnft.mint, receiverAccount), true),
+          account(METADATA_PROGRAM_ID),
+          account(SYSVAR_INSTRUCTIONS_PUBKEY),
+          account(AUTH_RULES_PROGRAM_ID),
+          account(ruleSet),
+        ],
+      });
+
+      const received = await getAccount(provider.connection, receiverAccount);
+      assert.equal(received.amount.toString(), "1", "The bidder didn't get the pNFT!");
+      assert.isEmpty(await collateralMints(obligation));
+    });
+  });
+});
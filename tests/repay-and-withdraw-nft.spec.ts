@@ -0,0 +1,115 @@
+import * as anchor from "@project-serum/anchor";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, TransactionInstruction } from "@solana/web3.js";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { getAccount, NATIVE_MINT } from "@solana/spl-token-latest";
+import { assert } from "chai";
+import { Honey } from "target/types/honey";
+import {
+  findCollateralAddress,
+  findLoanAccountAddress,
+  reserveRefreshAccounts,
+} from "honey-cli/src/helpers/obligation";
+import {
+  associatedAddress,
+  setupBorrower,
+  setupTestMarket,
+  TestMarket,
+  wrapSolInstructions,
+} from "./utils/market";
+
+// preflight is left on, so failed transactions come back with their program error
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+const program: anchor.Program = anchor.workspace.Honey as anchor.Program<Honey>;
+
+describe("repay and withdraw nft", () => {
+  const borrowed = LAMPORTS_PER_SOL / 2;
+
+  let testMarket: TestMarket;
+  let borrower: Keypair;
+  let nftMint: PublicKey;
+  let nftReceiver: PublicKey;
+  let obligation: PublicKey;
+  let loanAccount: PublicKey;
+  let collateralAccount: PublicKey;
+
+  async function repayAndWithdrawNft(preInstructions: TransactionInstruction[] = []) {
+    const { market, marketAuthority, reserve } = testMarket;
+    const { vault, loanNoteMint } = await program.account.reserve.fetch(reserve);
+
+    return program.methods
+      .repayAndWithdrawNft()
+      .accounts({
+        market,
+        marketAuthority,
+        obligation,
+        reserve,
+        vault,
+        loanNoteMint,
+        loanAccount,
+        payerAccount: await associatedAddress(NATIVE_MINT, borrower.publicKey),
+        payer: borrower.publicKey,
+        nftMint,
+        collateralAccount,
+        nftReceiver,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(await reserveRefreshAccounts(program, reserve))
+      .preInstructions(preInstructions)
+      .signers([borrower])
+      .rpc();
+  }
+
+  before(async () => {
+    testMarket = await setupTestMarket(provider, program, LAMPORTS_PER_SOL);
+
+    // the borrowed tokens land in the borrower's wrapped SOL account, which
+    // then holds less than the debt once the origination fee is added
+    const { nft, ...position } = await setupBorrower(testMarket, borrowed);
+    ({ borrower, obligation } = position);
+    nftMint = nft.mint;
+    nftReceiver = nft.tokenAccount;
+    [loanAccount] = await findLoanAccountAddress(
+      program,
+      testMarket.reserve,
+      obligation,
+      borrower.publicKey
+    );
+    collateralAccount = await findCollateralAddress(testMarket.marketAuthority, nftMint);
+  });
+
+  it("rejects paying back only part of the loan", async () => {
+    let failed = false;
+    try {
+      await repayAndWithdrawNft();
+    } catch (err) {
+      failed = true;
+    }
+    assert(failed, "The nft was withdrawn without the loan being repaid in full!");
+
+    const { amount: loanNotes } = await getAccount(provider.connection, loanAccount);
+    const { amount: escrowed } = await getAccount(provider.connection, collateralAccount);
+    assert(loanNotes > BigInt(0), "The loan was repaid!");
+    assert.equal(escrowed.toString(), "1", "The nft left the escrow!");
+  });
+
+  it("repays the whole loan and withdraws the nft", async () => {
+    // anything above the debt stays in the borrower's account
+    const payerAccount = await associatedAddress(NATIVE_MINT, borrower.publicKey);
+    await repayAndWithdrawNft(wrapSolInstructions(borrower.publicKey, payerAccount, borrowed / 10));
+
+    const { amount: received } = await getAccount(provider.connection, nftReceiver);
+    assert.equal(received.toString(), "1", "The nft wasn't returned!");
+    assert.isNull(await provider.connection.getAccountInfo(loanAccount), "The loan is still open!");
+    assert.isNull(
+      await provider.connection.getAccountInfo(collateralAccount),
+      "The escrow is still open!"
+    );
+
+    const data = await program.account.obligation.fetch(obligation);
+    assert(
+      !data.collateralNftMint.some((mint: PublicKey) => mint.equals(nftMint)),
+      "The nft is still registered as collateral!"
+    );
+  });
+});
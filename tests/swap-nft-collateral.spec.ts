@@ -0,0 +1,110 @@
+import * as anchor from "@project-serum/anchor";
+import { BN } from "@project-serum/anchor";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { getAccount } from "@solana/spl-token-latest";
+import { assert } from "chai";
+import { Honey } from "target/types/honey";
+import { findCollateralAddress, findMetadataAddress } from "honey-cli/src/helpers/obligation";
+import {
+  expectProgramError,
+  mintCollectionNft,
+  refreshReserveInstruction,
+  setMinCollateralRatio,
+  setupBorrower,
+  setupTestMarket,
+  TestMarket,
+} from "./utils/market";
+
+// preflight is left on, so failed transactions come back with their program error
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+const program: anchor.Program = anchor.workspace.Honey as anchor.Program<Honey>;
+
+interface Nft {
+  mint: PublicKey;
+  tokenAccount: PublicKey;
+}
+
+describe("swap nft collateral", () => {
+  let testMarket: TestMarket;
+  let borrower: Keypair;
+  let obligation: PublicKey;
+  let deposited: Nft;
+  let held: Nft;
+
+  async function swapNftCollateral(newNft: Nft, oldNft: Nft) {
+    const { market, marketAuthority, collectionCreator } = testMarket;
+    const [newNftMetadata, metadataBump] = await findMetadataAddress(newNft.mint);
+
+    return program.methods
+      .swapNftCollateral(metadataBump)
+      .accounts({
+        market,
+        marketAuthority,
+        obligation,
+        owner: borrower.publicKey,
+        nftCollectionCreator: collectionCreator.publicKey,
+        newNftMint: newNft.mint,
+        newNftMetadata,
+        newNftSource: newNft.tokenAccount,
+        newCollateralAccount: await findCollateralAddress(marketAuthority, newNft.mint),
+        oldNftMint: oldNft.mint,
+        oldCollateralAccount: await findCollateralAddress(marketAuthority, oldNft.mint),
+        oldNftReceiver: oldNft.tokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      // the health check needs the reserve's cached prices to be current
+      .preInstructions([await refreshReserveInstruction(testMarket)])
+      .signers([borrower])
+      .rpc();
+  }
+
+  async function escrowed(nft: Nft): Promise<boolean> {
+    const data = await program.account.obligation.fetch(obligation);
+    return data.collateralNftMint.some((mint: PublicKey) => mint.equals(nft.mint));
+  }
+
+  before(async () => {
+    testMarket = await setupTestMarket(provider, program, 1.5 * LAMPORTS_PER_SOL);
+
+    // borrowing half the obligation's capacity keeps the loan a fixed share
+    // of the collateral's value, whatever the floor price is
+    const position = await setupBorrower(testMarket, {
+      units: { bps: {} },
+      value: new BN(5000),
+    });
+    ({ borrower, obligation } = position);
+    deposited = position.nft;
+    held = await mintCollectionNft(testMarket, borrower.publicKey);
+  });
+
+  it("swaps the collateral of a healthy obligation", async () => {
+    await swapNftCollateral(held, deposited);
+
+    assert(await escrowed(held), "The new nft isn't collateral!");
+    assert(!(await escrowed(deposited)), "The old nft is still collateral!");
+
+    const { amount: returned } = await getAccount(provider.connection, deposited.tokenAccount);
+    assert.equal(returned.toString(), "1", "The old nft wasn't returned!");
+    assert.isNull(
+      await provider.connection.getAccountInfo(
+        await findCollateralAddress(testMarket.marketAuthority, deposited.mint)
+      ),
+      "The old nft's escrow is still open!"
+    );
+  });
+
+  it("rejects a swap that leaves the obligation unhealthy", async () => {
+    // at a 300% minimum the loan of 40% of the floor price is undercollateralized
+    await setMinCollateralRatio(testMarket, 30000);
+
+    await expectProgramError(program, swapNftCollateral(deposited, held), "ObligationUnhealthy");
+
+    assert(await escrowed(held), "The collateral was released!");
+    assert(!(await escrowed(deposited)), "The swap was recorded!");
+  });
+});
@@ -0,0 +1,202 @@
+import * as anchor from "@project-serum/anchor";
+import { BN } from "@project-serum/anchor";
+import {
+  computeCompressedNFTHash,
+  computeDataHash,
+  createCreateTreeInstruction,
+  createMintV1Instruction,
+  Creator,
+  getLeafAssetId,
+  MetadataArgs,
+  PROGRAM_ID as BUBBLEGUM_PROGRAM_ID,
+  TokenProgramVersion,
+  TokenStandard,
+} from "@metaplex-foundation/mpl-bubblegum";
+import {
+  ConcurrentMerkleTreeAccount,
+  createAllocTreeIx,
+  MerkleTree,
+  SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+  SPL_NOOP_PROGRAM_ID,
+} from "@solana/spl-account-compression";
+import { AccountMeta, Keypair, PublicKey } from "@solana/web3.js";
+
+export { BUBBLEGUM_PROGRAM_ID, SPL_ACCOUNT_COMPRESSION_PROGRAM_ID, SPL_NOOP_PROGRAM_ID };
+
+/**
+ * A Bubblegum tree, along with a copy of its leaves to build proofs from
+ */
+export interface TestTree {
+  merkleTree: PublicKey;
+  treeAuthority: PublicKey;
+  leaves: Buffer[];
+}
+
+/**
+ * A compressed nft minted into a test tree
+ */
+export interface Cnft {
+  assetId: PublicKey;
+  metadata: MetadataArgs;
+  nonce: number;
+  owner: PublicKey;
+  delegate: PublicKey;
+}
+
+/**
+ * Create a private Bubblegum tree of depth 3, with no canopy, so every proof
+ * is passed in full
+ */
+export async function createTree(
+  provider: anchor.AnchorProvider,
+  creator: Keypair
+): Promise<TestTree> {
+  const maxDepth = 3;
+  const maxBufferSize = 8;
+  const merkleTree = Keypair.generate();
+  const [treeAuthority] = await PublicKey.findProgramAddress(
+    [merkleTree.publicKey.toBuffer()],
+    BUBBLEGUM_PROGRAM_ID
+  );
+
+  const transaction = new anchor.web3.Transaction().add(
+    await createAllocTreeIx(
+      provider.connection,
+      merkleTree.publicKey,
+      creator.publicKey,
+      { maxDepth, maxBufferSize },
+      0
+    ),
+    createCreateTreeInstruction(
+      {
+        treeAuthority,
+        merkleTree: merkleTree.publicKey,
+        payer: creator.publicKey,
+        treeCreator: creator.publicKey,
+        logWrapper: SPL_NOOP_PROGRAM_ID,
+        compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+      },
+      { maxDepth, maxBufferSize, public: false }
+    )
+  );
+  await provider.sendAndConfirm(transaction, [creator, merkleTree]);
+
+  return {
+    merkleTree: merkleTree.publicKey,
+    treeAuthority,
+    leaves: Array.from({ length: 2 ** maxDepth }, () => Buffer.alloc(32)),
+  };
+}
+
+/**
+ * Mint a compressed nft to `owner`. The tree's creator pays and signs, so
+ * it's verified if listed as a verified creator; other creators are only
+ * verified when `signers` includes them.
+ */
+export async function mintCnft(
+  provider: anchor.AnchorProvider,
+  tree: TestTree,
+  treeCreator: Keypair,
+  owner: PublicKey,
+  creators: Creator[],
+  signers: Keypair[] = []
+): Promise<Cnft> {
+  const nonce = tree.leaves.findIndex((leaf) => leaf.equals(Buffer.alloc(32)));
+  const metadata: MetadataArgs = {
+    name: "Pretty Cool cNFT",
+    symbol: "CNFT",
+    uri: "https://pretty-cool-nft.xyz/metadata",
+    sellerFeeBasisPoints: 10,
+    primarySaleHappened: false,
+    isMutable: false,
+    editionNonce: null,
+    tokenStandard: TokenStandard.NonFungible,
+    collection: null,
+    uses: null,
+    tokenProgramVersion: TokenProgramVersion.Original,
+    creators,
+  };
+
+  const mint = createMintV1Instruction(
+    {
+      treeAuthority: tree.treeAuthority,
+      leafOwner: owner,
+      leafDelegate: owner,
+      merkleTree: tree.merkleTree,
+      payer: treeCreator.publicKey,
+      treeDelegate: treeCreator.publicKey,
+      logWrapper: SPL_NOOP_PROGRAM_ID,
+      compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+    },
+    { message: metadata }
+  );
+  // creators other than the payer verify by signing the mint
+  for (const signer of signers) {
+    mint.keys.push({ pubkey: signer.publicKey, isSigner: true, isWritable: false });
+  }
+  await provider.sendAndConfirm(new anchor.web3.Transaction().add(mint), [treeCreator, ...signers]);
+
+  const cnft = {
+    assetId: await getLeafAssetId(tree.merkleTree, new BN(nonce)),
+    metadata,
+    nonce,
+    owner,
+    delegate: owner,
+  };
+  setLeaf(tree, cnft);
+
+  return cnft;
+}
+
+/**
+ * Record a transfer made on chain, which also resets the leaf's delegate
+ */
+export function transferCnft(tree: TestTree, cnft: Cnft, newOwner: PublicKey) {
+  cnft.owner = newOwner;
+  cnft.delegate = newOwner;
+  setLeaf(tree, cnft);
+}
+
+function setLeaf(tree: TestTree, cnft: Cnft) {
+  tree.leaves[cnft.nonce] = computeCompressedNFTHash(
+    cnft.assetId,
+    cnft.owner,
+    cnft.delegate,
+    new BN(cnft.nonce),
+    cnft.metadata
+  );
+}
+
+/**
+ * The `CompressedNft` argument for moving a leaf, and its proof as the
+ * trailing accounts. `creators` overrides the ones the leaf was minted with.
+ */
+export async function compressedNftArgs(
+  provider: anchor.AnchorProvider,
+  tree: TestTree,
+  cnft: Cnft,
+  creators: Creator[] = cnft.metadata.creators
+) {
+  const account = await ConcurrentMerkleTreeAccount.fromAccountAddress(
+    provider.connection,
+    tree.merkleTree
+  );
+  const { proof } = new MerkleTree(tree.leaves).getProof(cnft.nonce);
+
+  return {
+    nft: {
+      root: Array.from(account.getCurrentRoot()),
+      dataHash: Array.from(computeDataHash(cnft.metadata)),
+      creators,
+      nonce: new BN(cnft.nonce),
+      index: cnft.nonce,
+    },
+    proof: proof.map(
+      (node): AccountMeta => ({
+        pubkey: new PublicKey(node),
+        isSigner: false,
+        isWritable: false,
+      })
+    ),
+  };
+}
@@ -0,0 +1,419 @@
+import * as anchor from "@project-serum/anchor";
+import { BN, Program } from "@project-serum/anchor";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, NATIVE_MINT, Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import {
+  AccountMeta,
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import { assert } from "chai";
+import { createMarket } from "honey-cli/src/actions/createMarket";
+import { createReserves } from "honey-cli/src/actions/createReserves";
+import { depositNFT } from "honey-cli/src/actions/depositNFT";
+import { depositTokens } from "honey-cli/src/actions/depositTokens";
+import { reserveConfig } from "honey-cli/src/helpers/utils";
+import {
+  amountTokens,
+  findLoanAccountAddress,
+  findObligations,
+  loadOrCreateLoanAccount,
+  reserveRefreshAccounts,
+} from "honey-cli/src/helpers/obligation";
+import { createKeypair, mintNft, verifyCollection } from ".";
+
+// The devnet feeds the honey suite prices its nfts and SOL with
+export const NFT_AGGREGATOR = new PublicKey("4FcQKqmQKXuiyJatHkMy7pXwUSMPuXh5sXLacAnyxh7v");
+export const TOKEN_AGGREGATOR = new PublicKey("DfZxR1TKfDMvjCLM1Si3BDDSS283jba8HTd1cewhNAnN");
+
+// An amount argument, such as `{ units: { bps: {} }, value: new BN(5000) }`
+export type Amount = { units: object; value: BN };
+
+export interface TestMarket {
+  program: Program;
+  provider: anchor.AnchorProvider;
+  market: PublicKey;
+  marketAuthority: PublicKey;
+  reserve: PublicKey;
+  roles: PublicKey;
+  owner: Keypair;
+  collectionCreator: Keypair;
+  collectionMint: PublicKey;
+}
+
+/**
+ * Create a market with a single SOL reserve for a new collection, with its
+ * roles account, the owner as fee collector and `liquidity` lamports lent
+ * to the reserve
+ */
+export async function setupTestMarket(
+  provider: anchor.AnchorProvider,
+  program: Program,
+  liquidity: number
+): Promise<TestMarket> {
+  const owner = (provider.wallet as anchor.Wallet).payer;
+  const collectionCreator = await createKeypair(provider);
+  const { mint: collectionMint } = await mintNft(
+    provider,
+    "TEST",
+    collectionCreator,
+    collectionCreator.publicKey
+  );
+
+  const market = await createMarket(
+    program,
+    collectionCreator.publicKey.toString(),
+    NFT_AGGREGATOR.toString(),
+    owner
+  );
+  const { reserve } = await createReserves(
+    owner,
+    market,
+    TOKEN_AGGREGATOR.toString(),
+    Keypair.generate().publicKey
+  );
+  const { marketAuthority } = await program.account.market.fetch(market);
+
+  const [roles] = await PublicKey.findProgramAddress(
+    [Buffer.from("roles"), market.toBuffer()],
+    program.programId
+  );
+  await program.methods
+    .initMarketRoles()
+    .accounts({ market, roles, owner: owner.publicKey, systemProgram: SystemProgram.programId })
+    .rpc();
+  await program.methods
+    .setMarketRole({ feeCollector: {} }, owner.publicKey)
+    .accounts({ market, roles, owner: owner.publicKey })
+    .rpc();
+
+  if (liquidity > 0) {
+    const lender = await createKeypair(provider);
+    assert(
+      await depositTokens(lender, market, liquidity, NATIVE_MINT.toString()),
+      "Lending to the reserve failed!"
+    );
+  }
+
+  return {
+    program,
+    provider,
+    market,
+    marketAuthority,
+    reserve,
+    roles,
+    owner,
+    collectionCreator,
+    collectionMint,
+  };
+}
+
+/**
+ * Mint an nft of the market's collection to `holder`, verified as part of it
+ */
+export async function mintCollectionNft(testMarket: TestMarket, holder: PublicKey) {
+  const { provider, collectionCreator, collectionMint } = testMarket;
+  const nft = await mintNft(provider, "TEST", collectionCreator, holder, collectionMint);
+  await verifyCollection(provider, nft.mint, collectionMint, collectionCreator);
+
+  return nft;
+}
+
+/**
+ * A new borrower with a collection nft deposited as collateral, who has
+ * borrowed `amount` against it, in lamports unless given in other units
+ */
+export async function setupBorrower(
+  testMarket: TestMarket,
+  amount: number | Amount
+) {
+  const { program, provider, market, collectionCreator } = testMarket;
+  const borrower = await createKeypair(provider);
+  const nft = await mintCollectionNft(testMarket, borrower.publicKey);
+
+  assert(
+    await depositNFT(borrower, market, nft.tokenAccount, nft.mint, collectionCreator.publicKey),
+    "Nft deposit failed!"
+  );
+  const [obligation] = await findObligations(program, market, borrower.publicKey);
+  if (amount !== 0) {
+    await borrow(
+      testMarket,
+      borrower,
+      obligation,
+      typeof amount === "number" ? amountTokens(amount) : amount
+    );
+  }
+
+  return { borrower, nft, obligation };
+}
+
+/**
+ * Borrow SOL into the borrower's wrapped SOL account
+ */
+export async function borrow(
+  testMarket: TestMarket,
+  borrower: Keypair,
+  obligation: PublicKey,
+  amount: Amount
+) {
+  const { program, market, marketAuthority, reserve } = testMarket;
+  const data = await program.account.reserve.fetch(reserve);
+  const loanAccount = await loadOrCreateLoanAccount(program, borrower, market, obligation, reserve);
+  const receiverAccount = await associatedAddress(NATIVE_MINT, borrower.publicKey);
+  const preInstructions = [];
+  if (!(await program.provider.connection.getAccountInfo(receiverAccount))) {
+    preInstructions.push(
+      Token.createAssociatedTokenAccountInstruction(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        NATIVE_MINT,
+        receiverAccount,
+        borrower.publicKey,
+        borrower.publicKey
+      )
+    );
+  }
+
+  return program.methods
+    .borrow(amount)
+    .accounts({
+      market,
+      marketAuthority,
+      obligation,
+      reserve,
+      vault: data.vault,
+      loanNoteMint: data.loanNoteMint,
+      borrower: borrower.publicKey,
+      loanAccount,
+      receiverAccount,
+      tokenMint: NATIVE_MINT,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      nftSwitchboardPriceAggregator: NFT_AGGREGATOR,
+    })
+    .remainingAccounts(await reserveRefreshAccounts(program, reserve))
+    .preInstructions(preInstructions)
+    .signers([borrower])
+    .rpc();
+}
+
+export async function refreshReserveInstruction(
+  testMarket: TestMarket
+): Promise<TransactionInstruction> {
+  const { program, market, marketAuthority, reserve } = testMarket;
+  const data = await program.account.reserve.fetch(reserve);
+
+  return program.methods
+    .refreshReserve()
+    .accounts({
+      market,
+      marketAuthority,
+      reserve,
+      feeNoteVault: data.feeNoteVault,
+      protocolFeeNoteVault: data.protocolFeeNoteVault,
+      depositNoteMint: data.depositNoteMint,
+      switchboardPriceAggregator: data.switchboardPriceAggregator,
+      nftSwitchboardPriceAggregator: NFT_AGGREGATOR,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .instruction();
+}
+
+/**
+ * Change the reserve's minimum collateral ratio, which is how the tests make
+ * an obligation unhealthy without moving the oracle price
+ */
+export async function setMinCollateralRatio(testMarket: TestMarket, minCollateralRatio: number) {
+  const { program, market, roles, reserve, owner } = testMarket;
+
+  await program.methods
+    .updateReserveConfig({ ...reserveConfig, minCollateralRatio })
+    .accounts({ market, roles, reserve, owner: owner.publicKey })
+    .postInstructions([await refreshReserveInstruction(testMarket)])
+    .rpc();
+}
+
+/**
+ * Send a transaction expected to fail with the program error `name`
+ */
+export async function expectProgramError(
+  program: Program,
+  transaction: Promise<unknown>,
+  name: string
+) {
+  const { code } = program.idl.errors.find((error) => error.name === name);
+
+  try {
+    await transaction;
+  } catch (err) {
+    const message = `${err?.message ?? err}\n${(err?.logs ?? []).join("\n")}`;
+    if (
+      err?.error?.errorCode?.code === name ||
+      message.includes(`"Custom":${code}`) ||
+      message.includes(`0x${code.toString(16)}`)
+    ) {
+      return;
+    }
+    throw err;
+  }
+
+  assert.fail(`expected the transaction to fail with ${name}`);
+}
+
+export async function associatedAddress(mint: PublicKey, owner: PublicKey) {
+  return Token.getAssociatedTokenAddress(
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    TOKEN_PROGRAM_ID,
+    mint,
+    owner,
+    true
+  );
+}
+
+/**
+ * Send lamports to a wrapped SOL account and credit them to its token balance
+ */
+export function wrapSolInstructions(
+  owner: PublicKey,
+  account: PublicKey,
+  lamports: number
+): TransactionInstruction[] {
+  return [
+    SystemProgram.transfer({ fromPubkey: owner, toPubkey: account, lamports }),
+    // spl token `SyncNative`
+    new TransactionInstruction({
+      programId: TOKEN_PROGRAM_ID,
+      keys: [{ pubkey: account, isSigner: false, isWritable: true }],
+      data: Buffer.from([17]),
+    }),
+  ];
+}
+
+export async function findBidAddresses(testMarket: TestMarket, bidder: PublicKey) {
+  const { program, market } = testMarket;
+  const [bid, bidBump] = await PublicKey.findProgramAddress(
+    [Buffer.from("bid"), market.toBuffer(), bidder.toBuffer()],
+    program.programId
+  );
+  const [bidEscrow, bidEscrowBump] = await PublicKey.findProgramAddress(
+    [Buffer.from("escrow"), market.toBuffer(), bidder.toBuffer()],
+    program.programId
+  );
+  const [bidEscrowAuthority, bidEscrowAuthorityBump] = await PublicKey.findProgramAddress(
+    [bidEscrow.toBuffer()],
+    program.programId
+  );
+
+  return {
+    bid,
+    bidEscrow,
+    bidEscrowAuthority,
+    bumps: { bid: bidBump, bidEscrow: bidEscrowBump, bidEscrowAuthority: bidEscrowAuthorityBump },
+  };
+}
+
+/**
+ * Place a bid of `lamports` wrapped SOL, open for an hour
+ */
+export async function placeBid(testMarket: TestMarket, bidder: Keypair, lamports: number) {
+  const { program, market, marketAuthority } = testMarket;
+  const { bid, bidEscrow, bidEscrowAuthority, bumps } = await findBidAddresses(
+    testMarket,
+    bidder.publicKey
+  );
+  const depositSource = await associatedAddress(NATIVE_MINT, bidder.publicKey);
+  const expiresAt = new BN(Math.floor(Date.now() / 1000) + 3600);
+
+  await program.methods
+    .placeLiquidateBid(bumps, new BN(lamports), expiresAt, null, null)
+    .accounts({
+      market,
+      marketAuthority,
+      bid,
+      bidder: bidder.publicKey,
+      depositSource,
+      bidMint: NATIVE_MINT,
+      bidEscrow,
+      bidEscrowAuthority,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY,
+    })
+    .preInstructions([
+      Token.createAssociatedTokenAccountInstruction(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        NATIVE_MINT,
+        depositSource,
+        bidder.publicKey,
+        bidder.publicKey
+      ),
+      ...wrapSolInstructions(bidder.publicKey, depositSource, lamports),
+    ])
+    .signers([bidder])
+    .rpc();
+
+  return bid;
+}
+
+export interface Liquidation {
+  obligation: PublicKey;
+  borrower: PublicKey;
+  nftMint: PublicKey;
+  collateralAccount: PublicKey;
+  bidder: PublicKey;
+  keeper: Keypair;
+  nftAccounts?: AccountMeta[];
+}
+
+/**
+ * Liquidate an unhealthy obligation's nft with the bidder's bid
+ */
+export async function executeLiquidateBid(testMarket: TestMarket, liquidation: Liquidation) {
+  const { program, market, marketAuthority, reserve, roles, owner } = testMarket;
+  const { obligation, borrower, nftMint, collateralAccount, bidder, keeper } = liquidation;
+  const data = await program.account.reserve.fetch(reserve);
+  const { bid, bidEscrow, bidEscrowAuthority, bumps } = await findBidAddresses(testMarket, bidder);
+  const [loanAccount] = await findLoanAccountAddress(program, reserve, obligation, borrower);
+
+  return program.methods
+    .executeLiquidateBid(bumps)
+    .accounts({
+      market,
+      marketAuthority,
+      obligation,
+      obligationOwner: borrower,
+      reserve,
+      vault: data.vault,
+      // reserves without an insurance fund take any writable account
+      insuranceFund: data.insuranceFund.equals(PublicKey.default)
+        ? data.vault
+        : data.insuranceFund,
+      loanNoteMint: data.loanNoteMint,
+      loanAccount,
+      bid,
+      bidder,
+      roles,
+      feeCollector: owner.publicKey,
+      bidMint: NATIVE_MINT,
+      bidEscrow,
+      bidEscrowAuthority,
+      nftMint,
+      collateralAccount,
+      receiverAccount: await associatedAddress(nftMint, bidder),
+      keeperRewardReceiver: await associatedAddress(NATIVE_MINT, keeper.publicKey),
+      protocolFeeReceiver: await associatedAddress(NATIVE_MINT, owner.publicKey),
+      keeper: keeper.publicKey,
+      payer: keeper.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    })
+    .remainingAccounts(liquidation.nftAccounts ?? [])
+    .preInstructions([await refreshReserveInstruction(testMarket)])
+    .signers([keeper])
+    .rpc();
+}
@@ -0,0 +1,163 @@
+import * as anchor from "@project-serum/anchor";
+import { encode } from "@msgpack/msgpack";
+import {
+  createCreateOrUpdateInstruction,
+  PROGRAM_ID as AUTH_RULES_PROGRAM_ID,
+} from "@metaplex-foundation/mpl-token-auth-rules";
+import {
+  createCreateInstruction,
+  createMintInstruction,
+  PrintSupply,
+  PROGRAM_ID as METADATA_PROGRAM_ID,
+  TokenStandard,
+} from "@metaplex-foundation/mpl-token-metadata-latest";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey, SystemProgram, SYSVAR_INSTRUCTIONS_PUBKEY } from "@solana/web3.js";
+
+export { AUTH_RULES_PROGRAM_ID };
+
+const metadataSeed = (mint: PublicKey) => [
+  Buffer.from("metadata"),
+  METADATA_PROGRAM_ID.toBuffer(),
+  mint.toBuffer(),
+];
+
+export async function findEditionAddress(mint: PublicKey): Promise<PublicKey> {
+  const [edition] = await PublicKey.findProgramAddress(
+    [...metadataSeed(mint), Buffer.from("edition")],
+    METADATA_PROGRAM_ID
+  );
+  return edition;
+}
+
+/**
+ * The record Token Metadata keeps of a token account holding a pNFT
+ */
+export async function findTokenRecordAddress(
+  mint: PublicKey,
+  token: PublicKey
+): Promise<PublicKey> {
+  const [tokenRecord] = await PublicKey.findProgramAddress(
+    [...metadataSeed(mint), Buffer.from("token_record"), token.toBuffer()],
+    METADATA_PROGRAM_ID
+  );
+  return tokenRecord;
+}
+
+/**
+ * Create a rule set owned by `owner` that lets pNFTs be transferred by
+ * their owner, which is every transfer the market makes
+ */
+export async function createRuleSet(
+  provider: anchor.AnchorProvider,
+  owner: Keypair,
+  name: string
+): Promise<PublicKey> {
+  const [ruleSet] = await PublicKey.findProgramAddress(
+    [Buffer.from("rule_set"), owner.publicKey.toBuffer(), Buffer.from(name)],
+    AUTH_RULES_PROGRAM_ID
+  );
+  const serializedRuleSet = encode({
+    libVersion: 1,
+    ruleSetName: name,
+    owner: Array.from(owner.publicKey.toBytes()),
+    operations: {
+      "Transfer:Owner": "Pass",
+    },
+  });
+
+  const transaction = new anchor.web3.Transaction().add(
+    createCreateOrUpdateInstruction(
+      { payer: owner.publicKey, ruleSetPda: ruleSet, systemProgram: SystemProgram.programId },
+      { createOrUpdateArgs: { __kind: "V1", serializedRuleSet } }
+    )
+  );
+  await provider.sendAndConfirm(transaction, [owner]);
+
+  return ruleSet;
+}
+
+/**
+ * Mint a programmable nft verified as created by `creator` to the
+ * destination's associated token account
+ */
+export async function mintPnft(
+  provider: anchor.AnchorProvider,
+  creator: Keypair,
+  destination: PublicKey,
+  ruleSet: PublicKey | null
+) {
+  const mint = Keypair.generate();
+  const [metadata] = await PublicKey.findProgramAddress(
+    metadataSeed(mint.publicKey),
+    METADATA_PROGRAM_ID
+  );
+  const edition = await findEditionAddress(mint.publicKey);
+  const tokenAccount = await Token.getAssociatedTokenAddress(
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    TOKEN_PROGRAM_ID,
+    mint.publicKey,
+    destination
+  );
+  const tokenRecord = await findTokenRecordAddress(mint.publicKey, tokenAccount);
+
+  const transaction = new anchor.web3.Transaction().add(
+    createCreateInstruction(
+      {
+        metadata,
+        masterEdition: edition,
+        mint: mint.publicKey,
+        authority: creator.publicKey,
+        payer: creator.publicKey,
+        updateAuthority: creator.publicKey,
+        systemProgram: SystemProgram.programId,
+        sysvarInstructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        splTokenProgram: TOKEN_PROGRAM_ID,
+      },
+      {
+        createArgs: {
+          __kind: "V1",
+          assetData: {
+            name: "Pretty Cool pNFT",
+            symbol: "PNFT",
+            uri: "https://pretty-cool-nft.xyz/metadata",
+            sellerFeeBasisPoints: 10,
+            creators: [{ address: creator.publicKey, share: 100, verified: true }],
+            primarySaleHappened: false,
+            isMutable: true,
+            tokenStandard: TokenStandard.ProgrammableNonFungible,
+            collection: null,
+            uses: null,
+            collectionDetails: null,
+            ruleSet,
+          },
+          decimals: 0,
+          printSupply: { __kind: "Zero" } as PrintSupply,
+        },
+      }
+    ),
+    createMintInstruction(
+      {
+        token: tokenAccount,
+        tokenOwner: destination,
+        metadata,
+        masterEdition: edition,
+        tokenRecord,
+        mint: mint.publicKey,
+        authority: creator.publicKey,
+        payer: creator.publicKey,
+        systemProgram: SystemProgram.programId,
+        sysvarInstructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        splTokenProgram: TOKEN_PROGRAM_ID,
+        splAtaProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        authorizationRulesProgram: AUTH_RULES_PROGRAM_ID,
+        authorizationRules: ruleSet ?? METADATA_PROGRAM_ID,
+      },
+      { mintArgs: { __kind: "V1", amount: 1, authorizationData: null } }
+    )
+  );
+  // the create instruction creates the mint, so the mint signs
+  await provider.sendAndConfirm(transaction, [creator, mint]);
+
+  return { mint: mint.publicKey, tokenAccount, metadata, edition, tokenRecord };
+}
@@ -0,0 +1,147 @@
+import * as anchor from "@project-serum/anchor";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram } from "@solana/web3.js";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { createAssociatedTokenAccount, getAccount, NATIVE_MINT } from "@solana/spl-token-latest";
+import { assert } from "chai";
+import { Honey } from "target/types/honey";
+import { depositTokens } from "honey-cli/src/actions/depositTokens";
+import { repayTokens } from "honey-cli/src/actions/repayTokens";
+import { reserveRefreshAccounts } from "honey-cli/src/helpers/obligation";
+import { createKeypair } from "./utils";
+import { expectProgramError, setupBorrower, setupTestMarket, TestMarket } from "./utils/market";
+
+// preflight is left on, so failed transactions come back with their program error
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+const program: anchor.Program = anchor.workspace.Honey as anchor.Program<Honey>;
+
+describe("withdrawal queue", () => {
+  const lent = LAMPORTS_PER_SOL;
+  const borrowed = LAMPORTS_PER_SOL / 2;
+
+  let testMarket: TestMarket;
+  let lender: Keypair;
+  let borrower: Keypair;
+  let receiver: PublicKey;
+  let withdrawalRequest: PublicKey;
+  let depositNoteAccount: PublicKey;
+
+  async function fillWithdrawal() {
+    const { market, marketAuthority, reserve } = testMarket;
+    const { vault, depositNoteMint } = await program.account.reserve.fetch(reserve);
+
+    return program.methods
+      .fillWithdrawal()
+      .accounts({
+        market,
+        marketAuthority,
+        reserve,
+        vault,
+        depositNoteMint,
+        withdrawalRequest,
+        depositor: lender.publicKey,
+        depositNoteAccount,
+        receiver,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(await reserveRefreshAccounts(program, reserve))
+      .rpc();
+  }
+
+  function closeWithdrawal() {
+    return program.methods
+      .closeWithdrawal()
+      .accounts({ depositor: lender.publicKey, withdrawalRequest })
+      .rpc();
+  }
+
+  before(async () => {
+    testMarket = await setupTestMarket(provider, program, 0);
+    const { market, reserve } = testMarket;
+
+    // the lender's deposit is the only liquidity, and half of it is lent out
+    lender = await createKeypair(provider);
+    assert(await depositTokens(lender, market, lent, NATIVE_MINT.toString()), "Deposit failed!");
+
+    ({ borrower } = await setupBorrower(testMarket, borrowed));
+
+    receiver = await createAssociatedTokenAccount(
+      provider.connection,
+      lender,
+      NATIVE_MINT,
+      lender.publicKey
+    );
+    [withdrawalRequest] = await PublicKey.findProgramAddress(
+      [Buffer.from("withdrawal"), reserve.toBuffer(), lender.publicKey.toBuffer()],
+      program.programId
+    );
+    [depositNoteAccount] = await PublicKey.findProgramAddress(
+      [Buffer.from("deposits"), reserve.toBuffer(), lender.publicKey.toBuffer()],
+      program.programId
+    );
+  });
+
+  it("queues the whole deposit", async () => {
+    const { market, reserve } = testMarket;
+    const notes = new anchor.BN(
+      (await getAccount(provider.connection, depositNoteAccount)).amount.toString()
+    );
+
+    await program.methods
+      .queueWithdrawal(notes)
+      .accounts({
+        market,
+        reserve,
+        depositor: lender.publicKey,
+        depositNoteAccount,
+        withdrawalRequest,
+        receiver,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([lender])
+      .rpc();
+
+    const request = await program.account.withdrawalRequest.fetch(withdrawalRequest);
+    assert(request.notesRemaining.eq(notes));
+    assert(request.tokensFilled.isZero());
+  });
+
+  it("partially fills the request with the liquidity left", async () => {
+    await fillWithdrawal();
+
+    const request = await program.account.withdrawalRequest.fetch(withdrawalRequest);
+    const received = (await getAccount(provider.connection, receiver)).amount;
+    assert(request.tokensFilled.gtn(0), "Nothing was filled!");
+    assert(request.tokensFilled.ltn(lent), "The loaned out tokens were filled!");
+    assert(request.notesRemaining.gtn(0), "The request was filled in full!");
+    assert.equal(received.toString(), request.tokensFilled.toString());
+  });
+
+  it("can't fill the request any further while the rest is lent out", async () => {
+    await expectProgramError(program, fillWithdrawal(), "InsufficientLiquidity");
+  });
+
+  it("can't close a request that isn't filled", async () => {
+    await expectProgramError(program, closeWithdrawal(), "WithdrawalRequestPending");
+  });
+
+  it("fills the rest once the loan is repaid, and closes", async () => {
+    // repaying more than the debt only pays the debt off
+    assert(await repayTokens(borrower, testMarket.market, 2 * borrowed, NATIVE_MINT), "Repay failed!");
+    await fillWithdrawal();
+
+    const request = await program.account.withdrawalRequest.fetch(withdrawalRequest);
+    const received = (await getAccount(provider.connection, receiver)).amount;
+    assert(request.notesRemaining.isZero(), "The request wasn't filled in full!");
+    assert(request.tokensFilled.gten(lent - 1), "The deposit wasn't paid back!");
+    assert.equal(received.toString(), request.tokensFilled.toString());
+
+    await expectProgramError(program, fillWithdrawal(), "WithdrawalRequestFilled");
+
+    const lamportsBefore = await provider.connection.getBalance(lender.publicKey);
+    await closeWithdrawal();
+    assert.isNull(await provider.connection.getAccountInfo(withdrawalRequest));
+    assert.isAbove(await provider.connection.getBalance(lender.publicKey), lamportsBefore);
+  });
+});